@@ -0,0 +1,56 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use proc_macro2::TokenStream;
+
+use crate::implementation::{
+    codegen::{make_assertion, AssertionSite, ViolationInfo},
+    Contract, ContractMode, ContractType,
+};
+
+/// Kind of contract used in messages of violated assertions.
+const KIND: &str = "Contract assertion";
+
+pub(crate) fn assert_contract(toks: TokenStream) -> TokenStream {
+    // assertions inside of a function body are checked like pre-conditions
+    // of the code following them
+    let contract = Contract::from_toks(ContractType::Requires, ContractMode::Always, toks);
+    let mode = contract.final_mode();
+
+    let desc = if let Some(desc) = contract.desc.as_ref() {
+        format!("{} violated: {}", KIND, desc)
+    } else {
+        format!("{} violated", KIND)
+    };
+
+    let info = ViolationInfo {
+        func_name: None,
+        kind: KIND,
+        desc: &desc,
+        description: contract.desc.as_deref(),
+    };
+
+    let assertions = contract
+        .assertions
+        .iter()
+        .zip(contract.streams.iter())
+        .map(|(expr, display)| {
+            make_assertion(
+                mode,
+                ContractType::Requires,
+                &info,
+                display.clone(),
+                expr,
+                &contract.options,
+                AssertionSite::Other,
+            )
+        })
+        .collect::<TokenStream>();
+
+    quote::quote! {
+        {
+            #assertions
+        }
+    }
+}
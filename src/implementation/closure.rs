@@ -0,0 +1,148 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use proc_macro2::{Span, TokenStream};
+use syn::{Expr, ExprBlock, FnArg, Ident, Item, ItemFn, Local, Pat, Stmt};
+
+/// Parse a `let` statement binding a closure, like `let f = |x: i32| x + 1;`,
+/// together with a function taking the same arguments as the closure.
+///
+/// The contracts are generated for this function, whose new body is then
+/// moved back into the closure by [`into_closure`].
+pub(crate) fn parse_closure(toks: &TokenStream) -> Option<(Local, ItemFn)> {
+    let local = match syn::parse2::<Stmt>(toks.clone()) {
+        Ok(Stmt::Local(local)) => local,
+        _ => return None,
+    };
+
+    let closure = match local.init.as_ref().map(|init| &*init.expr) {
+        Some(Expr::Closure(closure)) => closure,
+        _ => return None,
+    };
+
+    // the name of the binding is used in the messages of violations
+    let ident = match &local.pat {
+        Pat::Ident(pat) => pat.ident.clone(),
+        Pat::Type(pat) => match &*pat.pat {
+            Pat::Ident(pat) => pat.ident.clone(),
+            _ => Ident::new("closure", Span::call_site()),
+        },
+        _ => Ident::new("closure", Span::call_site()),
+    };
+
+    // arguments without a type are left to inference, as they would be in the
+    // closure
+    let inputs = closure.inputs.iter().map(|pat| -> FnArg {
+        match pat {
+            Pat::Type(pat) => FnArg::Typed(pat.clone()),
+            pat => syn::parse_quote!(#pat: _),
+        }
+    });
+
+    let attrs = &local.attrs;
+    let output = &closure.output;
+
+    // a block body is used as it is, so its braces aren't doubled
+    let block: syn::Block = match &*closure.body {
+        Expr::Block(body) if body.attrs.is_empty() && body.label.is_none() => body.block.clone(),
+        body => syn::parse_quote!({ #body }),
+    };
+
+    let func = syn::parse_quote! {
+        #(#attrs)*
+        fn #ident(#(#inputs),*) #output #block
+    };
+
+    Some((local, func))
+}
+
+/// Move the body of the function generated for a closure back into the
+/// closure of the `let` statement.
+pub(crate) fn into_closure(mut local: Local, generated: TokenStream) -> TokenStream {
+    // errors are reported as they are. Other generated functions, like the
+    // checker of the pre-conditions, can't be called for closures.
+    let func = match syn::parse2::<syn::File>(generated.clone()) {
+        Ok(file) => match file.items.into_iter().next() {
+            Some(Item::Fn(func)) => func,
+            _ => return generated,
+        },
+        Err(_) => return generated,
+    };
+
+    if let Some(Expr::Closure(closure)) = local.init.as_mut().map(|init| &mut *init.expr) {
+        *closure.body = Expr::Block(ExprBlock {
+            attrs: Vec::new(),
+            label: None,
+            block: *func.block,
+        });
+    }
+
+    // documentation and `#[track_caller]` only apply to functions
+    local.attrs = func
+        .attrs
+        .into_iter()
+        .filter(|attr| !attr.path().is_ident("doc") && !attr.path().is_ident("track_caller"))
+        .collect();
+
+    quote::quote! { #local }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::implementation::{ContractMode, ContractType, FuncWithContracts};
+
+    #[test]
+    fn contracts_on_closure() {
+        let toks: TokenStream =
+            syn::parse_str("#[ensures(ret > 0)] #[allow(unused)] let f = move |x: i32, y| x + y;")
+                .unwrap();
+
+        let (local, func) = parse_closure(&toks).unwrap();
+        assert_eq!(func.sig.ident, "f");
+        assert_eq!(func.sig.inputs.len(), 2);
+
+        let generated = FuncWithContracts::new_with_initial_contract(
+            func,
+            ContractType::Requires,
+            ContractMode::Always,
+            quote::quote!(x > 0),
+        )
+        .generate();
+
+        let stmt: Stmt = syn::parse2(into_closure(local, generated)).unwrap();
+        let local = match stmt {
+            Stmt::Local(local) => local,
+            stmt => panic!("expected a `let` statement, got {:?}", stmt),
+        };
+
+        // only the non-contract attributes are left
+        assert_eq!(local.attrs.len(), 1);
+        assert!(local.attrs[0].path().is_ident("allow"));
+
+        let closure = match *local.init.unwrap().expr {
+            Expr::Closure(closure) => closure,
+            expr => panic!("expected a closure, got {:?}", expr),
+        };
+        assert!(closure.capture.is_some());
+
+        // the body is wrapped to bind the return value
+        let body = quote::quote!(#closure).to_string();
+        assert!(body.contains("__contracts_ret"));
+    }
+
+    #[test]
+    fn functions_are_not_closures() {
+        let toks = quote::quote! {
+            fn f(x: i32) -> i32 { x }
+        };
+        assert!(parse_closure(&toks).is_none());
+
+        let toks = quote::quote! {
+            let x = 5;
+        };
+        assert!(parse_closure(&toks).is_none());
+    }
+}
@@ -2,16 +2,47 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use proc_macro2::{Ident, Span, TokenStream};
+use std::collections::HashMap;
+
+use proc_macro2::{Delimiter, Group, Ident, Span, TokenStream, TokenTree};
 use quote::ToTokens;
 use syn::{
     spanned::Spanned,
-    visit::{visit_return_type, Visit},
+    visit::{self, visit_return_type, Visit},
     visit_mut::{self as visitor, visit_block_mut, visit_expr_mut, VisitMut},
-    Attribute, Expr, ExprCall, ExprClosure, ReturnType, TypeImplTrait,
+    Attribute, BinOp, Expr, ExprAsync, ExprBinary, ExprCall, ExprClosure, ExprMethodCall, FnArg,
+    Item, ItemFn, Pat, ReceiverKind, ReturnType, Type, TypeImplTrait, TypeParamBound,
+};
+
+use crate::implementation::{
+    ret_binding, Contract, ContractMode, ContractOptions, ContractType, FuncWithContracts,
+    RET_BINDING,
 };
 
-use crate::implementation::{Contract, ContractMode, ContractType, FuncWithContracts};
+/// Find a `?` operator applying to the enclosing function, ignoring the ones
+/// in closures, async blocks and nested items.
+fn find_question_mark(expr: &Expr) -> Option<syn::token::Question> {
+    struct TryFinder {
+        found: Option<syn::token::Question>,
+    }
+
+    impl<'a> Visit<'a> for TryFinder {
+        fn visit_expr_try(&mut self, node: &'a syn::ExprTry) {
+            self.found.get_or_insert(node.question_token);
+            visit::visit_expr_try(self, node);
+        }
+
+        fn visit_expr_closure(&mut self, _node: &'a ExprClosure) {}
+
+        fn visit_expr_async(&mut self, _node: &'a ExprAsync) {}
+
+        fn visit_item(&mut self, _node: &'a Item) {}
+    }
+
+    let mut finder = TryFinder { found: None };
+    finder.visit_expr(expr);
+    finder.found
+}
 
 /// Substitution for `old()` expressions.
 pub(crate) struct OldExpr {
@@ -28,19 +59,67 @@ pub(crate) fn extract_old_calls(contracts: &mut [Contract]) -> Vec<OldExpr> {
     struct OldExtractor {
         last_id: usize,
         olds: Vec<OldExpr>,
+        names: HashMap<String, String>,
     }
 
     // if the call is a call to old() then the argument will be
-    // returned.
+    // returned. For calls to old_clone() the argument is wrapped in a call to
+    // `clone()`, so references are snapshotted as owned values. References
+    // kept alive during the call have to be requested using old_ref().
+    // old_self() snapshots a clone of `self`, which only compiles if `Self`
+    // implements `Clone`.
     fn get_old_data(call: &ExprCall) -> Option<Expr> {
+        if let Expr::Path(path) = &*call.func {
+            if path.path.is_ident("old_self") && call.args.is_empty() {
+                let span = call.span();
+                return Some(syn::parse_quote_spanned! { span=>
+                    <Self as ::core::clone::Clone>::clone(&self)
+                });
+            }
+        }
+
         // must have only one argument
         if call.args.len() != 1 {
             return None;
         }
 
         if let Expr::Path(path) = &*call.func {
+            let arg = &call.args[0];
+
+            let is_old = ["old", "old_ref", "old_clone"]
+                .iter()
+                .any(|name| path.path.is_ident(name));
+
+            // the snapshots are taken before the function body is run, where
+            // `?` would return early without running it
+            if is_old {
+                if let Some(question_mark) = find_question_mark(arg) {
+                    let msg = format!(
+                        "the argument of {}() can't use `?`, snapshot the fallible value \
+                         instead and handle the error in the condition",
+                        path.to_token_stream()
+                    );
+                    let err = syn::Error::new_spanned(question_mark, msg);
+                    return Some(Expr::Verbatim(err.into_compile_error()));
+                }
+            }
+
             if path.path.is_ident("old") {
-                Some(call.args[0].clone())
+                if let Expr::Reference(_) = arg {
+                    let err = syn::Error::new_spanned(
+                        arg,
+                        "old() of a reference borrows the state during the call, use \
+                         old_clone() to snapshot the value or old_ref() to keep the reference",
+                    );
+                    return Some(Expr::Verbatim(err.into_compile_error()));
+                }
+
+                Some(arg.clone())
+            } else if path.path.is_ident("old_ref") {
+                Some(arg.clone())
+            } else if path.path.is_ident("old_clone") {
+                let span = arg.span();
+                Some(syn::parse_quote_spanned! { span=> (#arg).clone() })
             } else {
                 None
             }
@@ -59,17 +138,27 @@ pub(crate) fn extract_old_calls(contracts: &mut [Contract]) -> Vec<OldExpr> {
 
                     self.visit_expr_mut(&mut old_arg);
 
-                    let id = self.last_id;
-                    self.last_id += 1;
+                    // identical expressions are only evaluated once
+                    let key = old_arg.to_token_stream().to_string();
 
-                    let old_var_name = format!("__contract_old_{}", id);
+                    let old_var_name = if let Some(name) = self.names.get(&key) {
+                        name.clone()
+                    } else {
+                        let id = self.last_id;
+                        self.last_id += 1;
 
-                    let old_expr = OldExpr {
-                        name: old_var_name.clone(),
-                        expr: old_arg,
-                    };
+                        let old_var_name = format!("__contracts_old_{}", id);
 
-                    self.olds.push(old_expr);
+                        let old_expr = OldExpr {
+                            name: old_var_name.clone(),
+                            expr: old_arg,
+                        };
+
+                        self.olds.push(old_expr);
+                        self.names.insert(key, old_var_name.clone());
+
+                        old_var_name
+                    };
 
                     // override the original expression with the new variable
                     // identifier
@@ -78,9 +167,7 @@ pub(crate) fn extract_old_calls(contracts: &mut [Contract]) -> Vec<OldExpr> {
 
                         let ident = syn::Ident::new(&old_var_name, span);
 
-                        let toks = quote::quote_spanned! { span=> #ident };
-
-                        syn::parse(toks.into()).unwrap()
+                        syn::parse_quote_spanned! { span=> #ident }
                     };
                 } else {
                     // otherwise continue visiting the expression call
@@ -92,13 +179,59 @@ pub(crate) fn extract_old_calls(contracts: &mut [Contract]) -> Vec<OldExpr> {
         }
     }
 
+    // pre-conditions and invariants are checked before the function is run,
+    // so there is no earlier state old() could refer to. Invariants which are
+    // only checked on exit can use it like post-conditions.
+    struct OldRejector {
+        invariant: bool,
+    }
+
+    impl visitor::VisitMut for OldRejector {
+        fn visit_expr_mut(&mut self, expr: &mut Expr) {
+            if let Expr::Call(call) = expr {
+                if get_old_data(call).is_some() {
+                    let func = &call.func;
+                    let allowed = if self.invariant {
+                        "post-conditions and invariants with `when = \"exit\"`"
+                    } else {
+                        "post-conditions"
+                    };
+                    let msg = format!(
+                        "{}() is only allowed in {}",
+                        func.to_token_stream(),
+                        allowed
+                    );
+                    let err = syn::Error::new_spanned(func, msg);
+                    *expr = Expr::Verbatim(err.into_compile_error());
+                    return;
+                }
+            }
+
+            visitor::visit_expr_mut(self, expr);
+        }
+    }
+
     let mut extractor = OldExtractor {
         last_id: 0,
         olds: vec![],
+        names: HashMap::new(),
     };
 
     for contract in contracts {
-        if contract.ty != ContractType::Ensures {
+        if !contract.ty.is_post() && contract.checked_on_entry() {
+            let mut rejector = OldRejector {
+                invariant: contract.ty == ContractType::Invariant,
+            };
+
+            for assertion in &mut contract.assertions {
+                rejector.visit_expr_mut(assertion);
+            }
+
+            continue;
+        }
+
+        // disabled contracts aren't checked, so their snapshots aren't taken
+        if contract.final_mode() == ContractMode::Disabled {
             continue;
         }
 
@@ -111,46 +244,37 @@ pub(crate) fn extract_old_calls(contracts: &mut [Contract]) -> Vec<OldExpr> {
 }
 
 fn get_assert_macro(
-    ctype: ContractType, // only Pre/Post allowed.
+    ctype: ContractType, // only Pre/Post/Invariant allowed.
     mode: ContractMode,
+    is_const: bool,
     span: Span,
 ) -> Option<Ident> {
-    if cfg!(feature = "mirai_assertions") {
-        match (ctype, mode) {
-            (ContractType::Requires, ContractMode::Always) => {
-                Some(Ident::new("checked_precondition", span))
-            }
-            (ContractType::Requires, ContractMode::Debug) => {
-                Some(Ident::new("debug_checked_precondition", span))
-            }
-            (ContractType::Requires, ContractMode::Test) => {
-                Some(Ident::new("debug_checked_precondition", span))
+    // the macros of MIRAI call functions which aren't `const`, so contracts
+    // of a `const fn` keep using `assert!`
+    if cfg!(feature = "mirai_assertions") && !is_const {
+        // pre-conditions are assumed by the analysis, while post-conditions
+        // and invariants have to be verified
+        let kind = match ctype {
+            ContractType::Requires => "assume",
+            ContractType::Ensures | ContractType::Invariant => "verify",
+            ContractType::EnsuresOk
+            | ContractType::EnsuresErr
+            | ContractType::EnsuresSome
+            | ContractType::EnsuresNone => {
+                panic!("expected contract type to be narrowed down to Pre/Post/Invariant")
             }
-            (ContractType::Requires, ContractMode::Disabled) => {
-                Some(Ident::new("precondition", span))
-            }
-            (ContractType::Requires, ContractMode::LogOnly) => {
-                Some(Ident::new("precondition", span))
-            }
-            (ContractType::Ensures, ContractMode::Always) => {
-                Some(Ident::new("checked_postcondition", span))
-            }
-            (ContractType::Ensures, ContractMode::Debug) => {
-                Some(Ident::new("debug_checked_postcondition", span))
-            }
-            (ContractType::Ensures, ContractMode::Test) => {
-                Some(Ident::new("debug_checked_postcondition", span))
-            }
-            (ContractType::Ensures, ContractMode::Disabled) => {
-                Some(Ident::new("postcondition", span))
-            }
-            (ContractType::Ensures, ContractMode::LogOnly) => {
-                Some(Ident::new("postcondition", span))
-            }
-            (ContractType::Invariant, _) => {
-                panic!("expected Invariant to be narrowed down to Pre/Post")
-            }
-        }
+        };
+
+        let name = match mode {
+            ContractMode::Always => format!("checked_{}", kind),
+            ContractMode::Debug => format!("debug_checked_{}", kind),
+            ContractMode::Test => format!("debug_checked_{}", kind),
+            ContractMode::Disabled => kind.to_string(),
+            ContractMode::LogOnly => kind.to_string(),
+            ContractMode::ReturnError => return None,
+        };
+
+        Some(Ident::new(&name, span))
     } else {
         match mode {
             ContractMode::Always => Some(Ident::new("assert", span)),
@@ -158,224 +282,1633 @@ fn get_assert_macro(
             ContractMode::Test => Some(Ident::new("debug_assert", span)),
             ContractMode::Disabled => None,
             ContractMode::LogOnly => None,
+            ContractMode::ReturnError => None,
         }
     }
 }
 
-/// Generate the resulting code for this function by inserting assertions.
-pub(crate) fn generate(
-    mut func: FuncWithContracts,
-    docs: Vec<Attribute>,
-    olds: Vec<OldExpr>,
-) -> TokenStream {
-    let func_name = func.function.sig.ident.to_string();
-
-    // creates an assertion appropriate for the current mode
-    let make_assertion = |mode: ContractMode,
-                          ctype: ContractType,
-                          display: TokenStream,
-                          exec_expr: &Expr,
-                          desc: &str| {
-        let span = display.span();
-        let mut result = TokenStream::new();
-
-        let format_args = quote::quote_spanned! { span=>
-            concat!(concat!(#desc, ": "), stringify!(#display))
-        };
-
-        if mode == ContractMode::LogOnly {
-            result.extend(quote::quote_spanned! { span=>
-                #[allow(clippy::nonminimal_bool)]
-                {
-                    if !(#exec_expr) {
-                        log::error!("{}", #format_args);
-                    }
-                }
-            });
-        }
-
-        if let Some(assert_macro) = get_assert_macro(ctype, mode, span) {
-            result.extend(quote::quote_spanned! { span=>
-                #[allow(clippy::nonminimal_bool)] {
-                    #assert_macro!(#exec_expr, "{}", #format_args);
-                }
-            });
-        }
+/// Operands of a comparison, used to print their values when the assertion
+/// fails.
+struct ComparisonOperands {
+    op: BinOp,
+    display_left: Expr,
+    display_right: Expr,
+    left: Expr,
+    right: Expr,
+}
 
-        if mode == ContractMode::Test {
-            quote::quote_spanned! { span=>
-              #[cfg(test)] {
-                #result
-              }
-            }
-        } else {
-            result
+/// Split a comparison into its operands, both for displaying and execution.
+///
+/// The displayed expression is the one written by the user, which might differ
+/// from the executed one, for example when the return value is renamed.
+fn comparison_operands(display: &TokenStream, exec_expr: &Expr) -> Option<ComparisonOperands> {
+    fn split(expr: Expr) -> Option<(BinOp, Expr, Expr)> {
+        match expr {
+            Expr::Binary(ExprBinary {
+                left, op, right, ..
+            }) => match op {
+                BinOp::Eq(_)
+                | BinOp::Ne(_)
+                | BinOp::Lt(_)
+                | BinOp::Le(_)
+                | BinOp::Gt(_)
+                | BinOp::Ge(_) => Some((op, *left, *right)),
+                _ => None,
+            },
+            _ => None,
         }
-    };
-
-    //
-    // generate assertion code for pre-conditions
-    //
-
-    let pre = func
-        .contracts
-        .iter()
-        .filter(|c| c.ty == ContractType::Requires || c.ty == ContractType::Invariant)
-        .flat_map(|c| {
-            let contract_type_name = if c.ty == ContractType::Invariant {
-                format!("{} (as pre-condition)", c.ty.message_name())
-            } else {
-                c.ty.message_name().to_string()
-            };
-
-            let desc = if let Some(desc) = c.desc.as_ref() {
-                format!("{} of {} violated: {}", contract_type_name, func_name, desc)
-            } else {
-                format!("{} of {} violated", contract_type_name, func_name)
-            };
+    }
 
-            c.assertions
-                .iter()
-                .zip(c.streams.iter())
-                .map(move |(expr, display)| {
-                    let mode = c.mode.final_mode();
+    let (display_op, display_left, display_right) = split(syn::parse2(display.clone()).ok()?)?;
+    let (op, left, right) = split(exec_expr.clone())?;
 
-                    make_assertion(
-                        mode,
-                        ContractType::Requires,
-                        display.clone(),
-                        expr,
-                        &desc.clone(),
-                    )
-                })
-        })
-        .collect::<TokenStream>();
+    if display_op != op {
+        return None;
+    }
 
-    //
-    // generate assertion code for post-conditions
-    //
+    Some(ComparisonOperands {
+        op,
+        display_left,
+        display_right,
+        left,
+        right,
+    })
+}
 
-    let post = func
-        .contracts
-        .iter()
-        .filter(|c| c.ty == ContractType::Ensures || c.ty == ContractType::Invariant)
-        .flat_map(|c| {
-            let contract_type_name = if c.ty == ContractType::Invariant {
-                format!("{} (as post-condition)", c.ty.message_name())
-            } else {
-                c.ty.message_name().to_string()
-            };
+/// A single line JSON object describing a violated contract, printed before
+/// the violation is reported when the `json_violations` feature is enabled.
+fn json_violation(info: &ViolationInfo<'_>, display: &TokenStream) -> Option<String> {
+    if !cfg!(feature = "json_violations") || cfg!(feature = "mirai_assertions") {
+        return None;
+    }
 
-            let desc = if let Some(desc) = c.desc.as_ref() {
-                format!("{} of {} violated: {}", contract_type_name, func_name, desc)
-            } else {
-                format!("{} of {} violated", contract_type_name, func_name)
-            };
+    fn json_string(s: &str) -> String {
+        let mut escaped = String::with_capacity(s.len() + 2);
+        escaped.push('"');
+
+        for c in s.chars() {
+            match c {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                '\t' => escaped.push_str("\\t"),
+                c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+                c => escaped.push(c),
+            }
+        }
 
-            c.assertions
-                .iter()
-                .zip(c.streams.iter())
-                .map(move |(expr, display)| {
-                    let mode = c.mode.final_mode();
+        escaped.push('"');
+        escaped
+    }
 
-                    make_assertion(
-                        mode,
-                        ContractType::Ensures,
-                        display.clone(),
-                        expr,
-                        &desc.clone(),
-                    )
-                })
-        })
-        .collect::<TokenStream>();
+    let func_name = info
+        .func_name
+        .map_or_else(|| "null".to_string(), json_string);
+
+    Some(format!(
+        "{{\"contract\":{},\"fn\":{},\"expr\":{},\"desc\":{}}}",
+        json_string(info.kind),
+        func_name,
+        json_string(&display.to_string()),
+        json_string(info.desc),
+    ))
+}
 
-    //
-    // bind "old()" expressions
-    //
+/// Passes the outcome of evaluating a condition to the function named by the
+/// `audit` option of the contract, if there is one.
+fn audited(
+    info: &ViolationInfo<'_>,
+    display: &TokenStream,
+    condition: TokenStream,
+    options: &ContractOptions,
+) -> TokenStream {
+    let sink = match &options.audit {
+        Some(sink) if !cfg!(feature = "mirai_assertions") => sink,
+        _ => return condition,
+    };
 
-    let olds = {
-        let mut toks = TokenStream::new();
+    let span = display.span();
+    let kind = info.kind;
+    let func_name = info.func_name.unwrap_or_default();
 
-        for old in olds {
-            let span = old.expr.span();
+    quote::quote_spanned! { span=>
+        {
+            let __contracts_holds: bool = #condition;
+            #sink(#kind, #func_name, stringify!(#display), __contracts_holds);
+            __contracts_holds
+        }
+    }
+}
 
-            let name = syn::Ident::new(&old.name, span);
+/// Description of a contract used in the messages of violations.
+pub(crate) struct ViolationInfo<'a> {
+    /// Name of the function the contract belongs to.
+    pub(crate) func_name: Option<&'a str>,
+    /// Kind of contract, like "Pre-condition".
+    pub(crate) kind: &'a str,
+    /// Message prefix, including the kind, function and description.
+    pub(crate) desc: &'a str,
+    /// Description given by the user, if any.
+    pub(crate) description: Option<&'a str>,
+}
 
-            let expr = old.expr;
+/// Where the checks created by [`make_assertion`] are placed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum AssertionSite {
+    /// Pre- or post-conditions of a function, whose violations can be
+    /// collected.
+    Function,
+    /// Pre- or post-conditions of a `const fn`, which can only use constant
+    /// expressions. The checks only panic with a fixed message.
+    ConstFunction,
+    /// Other checks, like the ones of `assert_contract!`.
+    Other,
+}
 
-            let binding = quote::quote_spanned! { span=>
-                let #name = #expr;
-            };
+/// Creates an assertion appropriate for the current mode.
+pub(crate) fn make_assertion(
+    mode: ContractMode,
+    ctype: ContractType,
+    info: &ViolationInfo<'_>,
+    display: TokenStream,
+    exec_expr: &Expr,
+    options: &ContractOptions,
+    site: AssertionSite,
+) -> TokenStream {
+    let desc = info.desc;
+    let kind = info.kind;
+    let span = display.span();
+    let mut result = TokenStream::new();
+
+    // `override_log` can't turn the checks of a `const fn` into logging, so
+    // they are left out instead, as violations mustn't abort. Only asking
+    // for logging explicitly is an error.
+    let is_const = site == AssertionSite::ConstFunction;
+    let logs_explicitly = matches!(options.mode, Some((ContractMode::LogOnly, _)));
+    let mode = if is_const && mode == ContractMode::LogOnly && !logs_explicitly {
+        ContractMode::Disabled
+    } else {
+        mode
+    };
 
-            toks.extend(Some(binding));
+    if is_const {
+        if let Some(err) = const_fn_error(mode, info, options, span) {
+            return err;
         }
+    }
 
-        toks
+    // descriptions are only format strings if the contract opts in, so
+    // existing descriptions containing braces are kept as they are
+    let interpolated = |desc: &str| {
+        if options.interpolate {
+            interpolated_desc(desc)
+        } else {
+            None
+        }
     };
 
-    //
-    // wrap the function body in a block so that we can use its return value
-    //
-
-    let body = 'blk: {
-        let mut block = func.function.block.clone();
-        visit_block_mut(&mut ReturnReplacer, &mut block);
+    // `file!()` and `line!()` resolve to the location of the contract, as
+    // they are expanded using its span. Custom messages are left as they are.
+    let location = if options.formatter.is_some() {
+        quote::quote_spanned! { span=> "" }
+    } else {
+        quote::quote_spanned! { span=>
+            concat!(" at ", file!(), ":", line!())
+        }
+    };
 
-        let mut impl_detector = ImplDetector { found_impl: false };
-        visit_return_type(&mut impl_detector, &func.function.sig.output);
+    // interpolated descriptions are format strings, which capture the values
+    // of the named variables
+    let (message, format_args) = match (&options.formatter, interpolated(desc)) {
+        (Some(formatter), _) => {
+            let func_name = info.func_name.unwrap_or_default();
+            let description = match info.description {
+                Some(description) => match interpolated(description) {
+                    Some(format) => {
+                        let ret_arg = ret_format_arg(&format, span);
+                        let format = syn::LitStr::new(&format, span);
+                        quote::quote_spanned! { span=>
+                            ::core::option::Option::Some(::std::format!(#format #ret_arg).as_str())
+                        }
+                    }
+                    None => quote::quote_spanned! { span=>
+                        ::core::option::Option::Some(#description)
+                    },
+                },
+                None => quote::quote_spanned! { span=> ::core::option::Option::None },
+            };
 
-        if !impl_detector.found_impl {
-            if let ReturnType::Type(.., ref return_type) = func.function.sig.output {
-                break 'blk quote::quote! {
-                    let ret: #return_type = 'run: #block;
-                };
-            }
+            let message = quote::quote_spanned! { span=>
+                #formatter(#kind, #func_name, stringify!(#display), #description)
+            };
+            (message.clone(), message)
         }
-
-        quote::quote! {
-            let ret = 'run: #block;
+        (None, Some(format)) => {
+            let ret_arg = ret_format_arg(&format, span);
+            let format = syn::LitStr::new(&format!("{}: {{}}", format), span);
+            let message = quote::quote_spanned! { span=>
+                ::std::format!(#format, stringify!(#display) #ret_arg)
+            };
+            let format_args = quote::quote_spanned! { span=>
+                ::std::format!("{}{}", #message, #location)
+            };
+            (message, format_args)
+        }
+        (None, None) => {
+            let message = quote::quote_spanned! { span=>
+                concat!(concat!(#desc, ": "), stringify!(#display))
+            };
+            let format_args = quote::quote_spanned! { span=>
+                concat!(#message, #location)
+            };
+            (message, format_args)
         }
     };
 
-    //
-    // create a new function body containing all assertions
-    //
+    // printing, counting and recording the checks isn't possible in a
+    // `const fn`
+    let json = json_violation(info, &display)
+        .filter(|_| !is_const)
+        .map(|json| {
+            // contracts may be on `Display` impls, which is where the
+            // violation is reported
+            quote::quote_spanned! { span=>
+                #[allow(clippy::print_in_format_impl)]
+                {
+                    ::std::eprintln!("{}", #json);
+                }
+            }
+        });
+
+    // the handler is called before the violation is reported
+    let handler = options
+        .handler
+        .as_ref()
+        .filter(|_| !cfg!(feature = "mirai_assertions") && !is_const)
+        .map(|handler| {
+            let func_name = info.func_name.unwrap_or_default();
+            quote::quote_spanned! { span=>
+                #handler(#kind, #func_name, stringify!(#display), &::std::format!("{}", #format_args));
+            }
+        });
 
-    let new_block = quote::quote! {
+    let on_violation = match (json, handler) {
+        (None, None) => None,
+        (json, handler) => Some(quote::quote_spanned! { span=> #json #handler }),
+    };
 
-        {
-            #pre
+    let condition = if is_const {
+        exec_expr.to_token_stream()
+    } else {
+        result.extend(check_counter(mode, info, span));
+        audited(info, &display, exec_expr.to_token_stream(), options)
+    };
 
-            #olds
+    if mode == ContractMode::LogOnly {
+        let level = match &options.log_level {
+            Some(level) => level.clone(),
+            None => Ident::new("error", span),
+        };
+        let target = options
+            .log_target
+            .as_ref()
+            .map(|target| quote::quote_spanned! { span=> target: #target, });
 
-            #body
+        let func_name = info
+            .func_name
+            .map(|name| quote::quote_spanned! { span=> contract.fn = #name, });
 
-            #post
+        let log = if cfg!(feature = "tracing") {
+            quote::quote_spanned! { span=>
+                tracing::#level!(
+                    #target
+                    contract.kind = #kind,
+                    #func_name
+                    "{}",
+                    #format_args
+                );
+            }
+        } else {
+            quote::quote_spanned! { span=>
+                log::#level!(#target "{}", #format_args);
+            }
+        };
 
-            ret
-        }
+        result.extend(quote::quote_spanned! { span=>
+            #[allow(clippy::nonminimal_bool, unused_parens)]
+            {
+                if !(#condition) {
+                    #on_violation
+                    #log
+                }
+            }
+        });
+    }
 
+    // violations are collected into the vector declared by
+    // `collected_failures`, instead of being reported right away
+    let collect_failures = site == AssertionSite::Function
+        && cfg!(feature = "collect_failures")
+        && !cfg!(feature = "mirai_assertions");
+
+    // violations panic with the payload created by the `payload` function
+    // instead of a message, which can't be done in a `const fn`
+    let panic_payload =
+        options.payload.is_some() && !cfg!(feature = "mirai_assertions") && !is_const;
+
+    let operands = if cfg!(feature = "debug_values")
+        && !cfg!(feature = "mirai_assertions")
+        && options.formatter.is_none()
+        && options.via.is_none()
+        && !collect_failures
+        && !panic_payload
+        && !is_const
+    {
+        comparison_operands(&display, exec_expr)
+    } else {
+        None
     };
 
-    // insert documentation attributes
-
-    func.function.attrs.extend(docs);
+    if let (Some(operands), Some(_)) = (operands, get_assert_macro(ctype, mode, is_const, span)) {
+        let ComparisonOperands {
+            op,
+            display_left,
+            display_right,
+            left,
+            right,
+        } = operands;
+
+        let holds = audited(
+            info,
+            &display,
+            quote::quote_spanned! { span=> (*__contracts_lhs #op *__contracts_rhs) },
+            options,
+        );
+
+        // (in)equalities are reported like by `assert_eq!`, other comparisons
+        // name their operands
+        let report = match op {
+            BinOp::Eq(_) => quote::quote_spanned! { span=>
+                assert_eq!(*__contracts_lhs, *__contracts_rhs, "{}{}", #message, #location);
+            },
+            BinOp::Ne(_) => quote::quote_spanned! { span=>
+                assert_ne!(*__contracts_lhs, *__contracts_rhs, "{}{}", #message, #location);
+            },
+            _ => quote::quote_spanned! { span=>
+                panic!(
+                    "{} ({} = {:?}, {} = {:?}){}",
+                    #message,
+                    stringify!(#display_left),
+                    __contracts_lhs,
+                    stringify!(#display_right),
+                    __contracts_rhs,
+                    #location,
+                );
+            },
+        };
 
-    // replace the old function body with the new one
+        let check = quote::quote_spanned! { span=>
+            match (&(#left), &(#right)) {
+                (__contracts_lhs, __contracts_rhs) => {
+                    if !#holds {
+                        #on_violation
+                        #report
+                    }
+                }
+            }
+        };
 
-    *func.function.block = syn::parse_quote!(#new_block);
+        // debug assertions don't evaluate the operands in release builds
+        let check = debug_only(mode, check, span);
 
-    func.function.into_token_stream()
-}
+        result.extend(quote::quote_spanned! { span=>
+            #[allow(clippy::nonminimal_bool, clippy::neg_cmp_op_on_partial_ord, unused_parens)] {
+                #check
+            }
+        });
+    } else if let Some(assert_macro) = get_assert_macro(ctype, mode, is_const, span) {
+        // the condition is only evaluated once, by the assertion
+        let exec_expr = match on_violation {
+            Some(on_violation) => quote::quote_spanned! { span=>
+                {
+                    let __contracts_holds: bool = #condition;
+                    if !__contracts_holds {
+                        #on_violation
+                    }
+                    __contracts_holds
+                }
+            },
+            None => condition,
+        };
 
-struct ReturnReplacer;
+        // custom assertion macros take the condition and the message. They
+        // are left to MIRAI's own macros.
+        let assertion = match &options.via {
+            Some(via) if !cfg!(feature = "mirai_assertions") => {
+                let check = quote::quote_spanned! { span=>
+                    #via!(#exec_expr, #format_args);
+                };
 
-impl VisitMut for ReturnReplacer {
-    fn visit_expr_mut(&mut self, node: &mut Expr) {
-        if let Expr::Return(ret_expr) = node {
-            let ret_expr_expr = ret_expr.expr.clone();
-            *node = syn::parse_quote!(break 'run #ret_expr_expr);
-        }
+                debug_only(mode, check, span)
+            }
+            None if collect_failures => {
+                let failures = failures_ident();
+                let check = quote::quote_spanned! { span=>
+                    if !(#exec_expr) {
+                        #failures.push(::std::format!("{}", #format_args));
+                    }
+                };
+
+                debug_only(mode, check, span)
+            }
+            None if panic_payload => {
+                let payload = &options.payload;
+                let func_name = info.func_name.unwrap_or_default();
+                let check = quote::quote_spanned! { span=>
+                    if !(#exec_expr) {
+                        ::std::panic::panic_any(#payload(
+                            #kind,
+                            #func_name,
+                            stringify!(#display),
+                            ::std::format!("{}", #format_args),
+                        ));
+                    }
+                };
+
+                debug_only(mode, check, span)
+            }
+            _ => quote::quote_spanned! { span=>
+                #assert_macro!(#exec_expr, "{}", #format_args);
+            },
+        };
+
+        // the negated conditions of the checks not using an assertion macro
+        // would be linted otherwise
+        result.extend(quote::quote_spanned! { span=>
+            #[allow(clippy::nonminimal_bool, clippy::neg_cmp_op_on_partial_ord, unused_parens)] {
+                #assertion
+            }
+        });
+    }
+
+    let result = if mode == ContractMode::Test {
+        quote::quote_spanned! { span=>
+          #[cfg(test)] {
+            #result
+          }
+        }
+    } else {
+        result
+    };
+
+    feature_gate(result, options, span)
+}
+
+/// Only runs a check in debug builds, unless the contract is always checked,
+/// like `debug_assert!` does.
+pub(crate) fn debug_only(mode: ContractMode, check: TokenStream, span: Span) -> TokenStream {
+    if mode == ContractMode::Always {
+        check
+    } else {
+        quote::quote_spanned! { span=>
+            if cfg!(debug_assertions) {
+                #check
+            }
+        }
+    }
+}
+
+/// Replaces the `{fn}` placeholders of a description with the name of the
+/// function. Braces are only escaped in descriptions which are used as format
+/// strings, where the escaped braces are kept for `format!`.
+pub(crate) fn with_fn_name(desc: &str, func_name: &str, interpolate: bool) -> String {
+    if !interpolate {
+        return desc.replace("{fn}", func_name);
+    }
+
+    let mut replaced = String::with_capacity(desc.len());
+    let mut rest = desc;
+
+    while let Some(idx) = rest.find(['{', '}']) {
+        replaced.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        let skip = if rest.starts_with("{fn}") {
+            replaced.push_str(func_name);
+            4
+        } else if rest.starts_with("{{") || rest.starts_with("}}") {
+            replaced.push_str(&rest[..2]);
+            2
+        } else {
+            replaced.push_str(&rest[..1]);
+            1
+        };
+
+        rest = &rest[skip..];
+    }
+
+    replaced.push_str(rest);
+    replaced
+}
+
+/// The description of a contract as a format string, if it contains
+/// placeholders like `{x}`. Placeholders naming `ret` refer to the return
+/// value.
+fn interpolated_desc(desc: &str) -> Option<String> {
+    let mut format = String::with_capacity(desc.len());
+    let mut has_placeholders = false;
+    let mut chars = desc.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        format.push(c);
+
+        match c {
+            '{' if chars.peek() == Some(&'{') => format.extend(chars.next()),
+            '{' => {
+                has_placeholders = true;
+
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+
+                if name == "ret" {
+                    format.push_str(RET_BINDING);
+                } else {
+                    format.push_str(&name);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    has_placeholders.then_some(format)
+}
+
+/// Reports contracts of a `const fn` which need to run code that isn't
+/// constant, like formatting the message.
+fn const_fn_error(
+    mode: ContractMode,
+    info: &ViolationInfo<'_>,
+    options: &ContractOptions,
+    span: Span,
+) -> Option<TokenStream> {
+    let msg = if mode == ContractMode::LogOnly {
+        "contracts of a `const fn` can't be logged"
+    } else if options.interpolate
+        && info
+            .description
+            .is_some_and(|desc| interpolated_desc(desc).is_some())
+    {
+        "descriptions of contracts of a `const fn` can't be interpolated"
+    } else if options.formatter.is_some() {
+        "contracts of a `const fn` can't use a `formatter`"
+    } else if options.payload.is_some() {
+        "contracts of a `const fn` can't use a `payload`"
+    } else if options.audit.is_some() {
+        "contracts of a `const fn` can't be audited"
+    } else if options.handler.is_some() {
+        "contracts of a `const fn` can't have a `handler`"
+    } else if options.category.is_some() {
+        "contracts of a `const fn` can't have a `category`"
+    } else {
+        return None;
+    };
+
+    Some(syn::Error::new(span, msg).into_compile_error())
+}
+
+/// Name of the vector collecting the violations of a function with the
+/// `collect_failures` feature.
+fn failures_ident() -> Ident {
+    Ident::new("__contracts_failures", Span::mixed_site())
+}
+
+/// Declares the vector the violations of `checks` are collected into, and
+/// reports all of them at once after the checks, if the `collect_failures`
+/// feature is enabled.
+fn collected_failures(checks: TokenStream, site: AssertionSite) -> TokenStream {
+    if site != AssertionSite::Function
+        || !cfg!(feature = "collect_failures")
+        || cfg!(feature = "mirai_assertions")
+        || checks.is_empty()
+    {
+        return checks;
+    }
+
+    let failures = failures_ident();
+
+    quote::quote! {
+        #[allow(unused_mut)]
+        let mut #failures: ::std::vec::Vec<::std::string::String> = ::std::vec::Vec::new();
+
+        #checks
+
+        if !#failures.is_empty() {
+            panic!("{}", #failures.join("\n"));
+        }
+    }
+}
+
+/// The return value is passed to `format!` explicitly, as the binding is
+/// hidden from the names captured by the format string.
+fn ret_format_arg(format: &str, span: Span) -> Option<TokenStream> {
+    let ret_ident = ret_binding(span);
+
+    format
+        .contains(&format!("{{{}", RET_BINDING))
+        .then(|| quote::quote_spanned! { span=> , #ret_ident = #ret_ident })
+}
+
+/// Counts the evaluation of a contract using the [`metrics`] crate, if the
+/// `metrics` feature is enabled.
+///
+/// [`metrics`]: https://docs.rs/metrics
+fn check_counter(mode: ContractMode, info: &ViolationInfo<'_>, span: Span) -> Option<TokenStream> {
+    if !cfg!(feature = "metrics") {
+        return None;
+    }
+
+    let kind = info.kind;
+    let func_name = info.func_name.map(|name| quote::quote! { , "fn" => #name });
+
+    // not spanned to the condition, or lints on the literals would point at it
+    let increment = quote::quote! {
+        metrics::counter!("contracts.checks", "kind" => #kind #func_name).increment(1);
+    };
+
+    match mode {
+        ContractMode::Disabled => None,
+        // debug contracts are only evaluated in debug builds
+        ContractMode::Debug => Some(quote::quote_spanned! { span=>
+            if cfg!(debug_assertions) {
+                #increment
+            }
+        }),
+        _ => Some(increment),
+    }
+}
+
+/// Only compiles the checks of a contract if the cargo feature given using the
+/// `cfg` option is enabled, and only runs them if the category given using the
+/// `category` option is enabled.
+pub(crate) fn feature_gate(
+    checks: TokenStream,
+    options: &ContractOptions,
+    span: Span,
+) -> TokenStream {
+    let checks = match &options.category {
+        Some(category) => category_gate(checks, category, span),
+        None => checks,
+    };
+
+    match &options.cfg_feature {
+        Some(feature) => quote::quote_spanned! { span=>
+            #[cfg(feature = #feature)] {
+                #checks
+            }
+        },
+        None => checks,
+    }
+}
+
+/// Only runs the checks if `CONTRACTS_CATEGORIES` lists the category. The
+/// variable is read when compiling the crate if it is set then, and once when
+/// the checks are first reached otherwise. All categories are enabled if it
+/// is set neither time.
+fn category_gate(checks: TokenStream, category: &syn::LitStr, span: Span) -> TokenStream {
+    quote::quote_spanned! { span=>
+        {
+            static __CONTRACTS_CATEGORY_ENABLED: ::std::sync::OnceLock<bool> =
+                ::std::sync::OnceLock::new();
+
+            let __contracts_enabled = *__CONTRACTS_CATEGORY_ENABLED.get_or_init(|| {
+                let categories = match option_env!("CONTRACTS_CATEGORIES") {
+                    Some(categories) => ::std::string::String::from(categories),
+                    None => match ::std::env::var("CONTRACTS_CATEGORIES") {
+                        Ok(categories) => categories,
+                        Err(_) => return true,
+                    },
+                };
+
+                categories.split(',').any(|enabled| enabled.trim() == #category)
+            });
+
+            if __contracts_enabled {
+                #checks
+            }
+        }
+    }
+}
+
+/// Generate the resulting code for this function by inserting assertions.
+pub(crate) fn generate(
+    mut func: FuncWithContracts,
+    docs: Vec<Attribute>,
+    olds: Vec<OldExpr>,
+) -> TokenStream {
+    let func_name = func.function.sig.ident.to_string();
+    let func_name = func_name.as_str();
+
+    // only panicking pre-conditions are broken by the caller, and each of
+    // them can opt out
+    let tracks_caller = func.contracts.iter().any(|c| {
+        c.ty == ContractType::Requires
+            && !c.options.no_track_caller
+            && matches!(
+                c.final_mode(),
+                ContractMode::Always | ContractMode::Debug | ContractMode::Test
+            )
+    });
+
+    // creates an early return of the error value for `ReturnError` contracts
+    let make_error_return = |display: TokenStream, exec_expr: &Expr, fallback: &Expr| {
+        let span = display.span();
+
+        let error = match fallback {
+            Expr::Closure(_) => quote::quote_spanned! { span=> (#fallback)() },
+            _ => quote::quote_spanned! { span=> #fallback },
+        };
+
+        quote::quote_spanned! { span=>
+            #[allow(clippy::nonminimal_bool, clippy::redundant_closure_call, unused_parens)]
+            {
+                if !(#exec_expr) {
+                    return ::core::result::Result::Err(#error);
+                }
+            }
+        }
+    };
+
+    let returns_type = |name: &str| match &func.function.sig.output {
+        ReturnType::Type(_, ty) => match &**ty {
+            Type::Path(path) => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|seg| seg.ident == name),
+            _ => false,
+        },
+        ReturnType::Default => false,
+    };
+
+    // returning an error is only possible for functions returning a `Result`
+    let returns_result = returns_type("Result");
+    let returns_option = returns_type("Option");
+
+    let not_result_error = if returns_result {
+        None
+    } else {
+        let msg = "returning an error on violation requires the function to return a `Result`";
+        let err = match &func.function.sig.output {
+            ReturnType::Default => syn::Error::new_spanned(&func.function.sig.ident, msg),
+            output => syn::Error::new_spanned(output, msg),
+        };
+        Some(err.into_compile_error())
+    };
+
+    let return_type_error = |msg: &str| {
+        let err = match &func.function.sig.output {
+            ReturnType::Default => syn::Error::new_spanned(&func.function.sig.ident, msg),
+            output => syn::Error::new_spanned(output, msg),
+        };
+        err.into_compile_error()
+    };
+
+    let not_result_post_error = if returns_result {
+        None
+    } else {
+        Some(return_type_error(
+            "checking the `Ok` or `Err` value requires the function to return a `Result`",
+        ))
+    };
+
+    let not_option_post_error = if returns_option {
+        None
+    } else {
+        Some(return_type_error(
+            "checking the `Some` value or `None` requires the function to return an `Option`",
+        ))
+    };
+
+    let site = if func.function.sig.constness.is_some() {
+        AssertionSite::ConstFunction
+    } else {
+        AssertionSite::Function
+    };
+
+    //
+    // generate assertion code for pre-conditions
+    //
+
+    let pre = func
+        .contracts
+        .iter()
+        .filter(|c| c.checked_on_entry())
+        .flat_map(|c| {
+            let not_result_error = &not_result_error;
+
+            let contract_type_name = if c.ty == ContractType::Invariant {
+                format!("{} (as pre-condition)", c.ty.message_name())
+            } else {
+                c.ty.message_name().to_string()
+            };
+
+            let desc = if let Some(desc) = c.desc.as_ref() {
+                format!("{} of {} violated: {}", contract_type_name, func_name, desc)
+            } else {
+                format!("{} of {} violated", contract_type_name, func_name)
+            };
+
+            c.assertions
+                .iter()
+                .zip(c.streams.iter())
+                .map(move |(expr, display)| {
+                    let mode = c.final_mode();
+
+                    if let (ContractMode::ReturnError, Some(fallback)) = (mode, &c.fallback) {
+                        if let Some(err) = not_result_error {
+                            return err.clone();
+                        }
+
+                        let span = display.span();
+                        let error_return = make_error_return(display.clone(), expr, fallback);
+                        return feature_gate(error_return, &c.options, span);
+                    }
+
+                    make_assertion(
+                        mode,
+                        c.ty,
+                        &ViolationInfo {
+                            func_name: Some(func_name),
+                            kind: &contract_type_name,
+                            desc: &desc,
+                            description: c.desc.as_deref(),
+                        },
+                        display.clone(),
+                        expr,
+                        &c.options,
+                        site,
+                    )
+                })
+        })
+        .collect::<TokenStream>();
+    let pre = collected_failures(pre, site);
+
+    //
+    // generate assertion code for post-conditions
+    //
+
+    let post_assertions = |c: &Contract| {
+        let assertion_type = if c.ty == ContractType::Invariant {
+            ContractType::Invariant
+        } else {
+            ContractType::Ensures
+        };
+
+        let contract_type_name = if c.ty == ContractType::Invariant {
+            format!("{} (as post-condition)", c.ty.message_name())
+        } else {
+            c.ty.message_name().to_string()
+        };
+
+        let desc = if let Some(desc) = c.desc.as_ref() {
+            format!("{} of {} violated: {}", contract_type_name, func_name, desc)
+        } else {
+            format!("{} of {} violated", contract_type_name, func_name)
+        };
+
+        let assertions = c
+            .assertions
+            .iter()
+            .zip(c.streams.iter())
+            .map(move |(expr, display)| {
+                let mode = c.final_mode();
+
+                // the return value is only borrowed by the assertions, so it
+                // can still be returned afterwards. This includes the value
+                // bound from the `Ok`, `Err` or `Some` variant.
+                let mut expr = expr.clone();
+                if c.ty.is_post() {
+                    BindingDeref::ret().visit_expr_mut(&mut expr);
+                }
+
+                make_assertion(
+                    mode,
+                    assertion_type,
+                    &ViolationInfo {
+                        func_name: Some(func_name),
+                        kind: &contract_type_name,
+                        desc: &desc,
+                        description: c.desc.as_deref(),
+                    },
+                    display.clone(),
+                    &expr,
+                    &c.options,
+                    site,
+                )
+            })
+            .collect::<TokenStream>();
+
+        let ret_ident = ret_binding(Span::call_site());
+
+        // the return value is narrowed down to the value inside of the
+        // `Ok`, `Err` or `Some` variant
+        let (condition, type_error) = match c.ty {
+            ContractType::EnsuresOk => (
+                quote::quote!(let ::core::result::Result::Ok(#ret_ident) = #ret_ident),
+                &not_result_post_error,
+            ),
+            ContractType::EnsuresErr => (
+                quote::quote!(let ::core::result::Result::Err(#ret_ident) = #ret_ident),
+                &not_result_post_error,
+            ),
+            ContractType::EnsuresSome => (
+                quote::quote!(let ::core::option::Option::Some(#ret_ident) = #ret_ident),
+                &not_option_post_error,
+            ),
+            ContractType::EnsuresNone => (
+                quote::quote!(::core::option::Option::is_none(#ret_ident)),
+                &not_option_post_error,
+            ),
+            _ => return assertions,
+        };
+
+        if let Some(err) = type_error {
+            return err.clone();
+        }
+
+        quote::quote! {
+            if #condition {
+                #assertions
+            }
+        }
+    };
+
+    // a returned `impl Iterator` can't be consumed by post-conditions, as it
+    // is still returned afterwards. Report this using the method name instead
+    // of letting the compiler complain about the internal binding.
+    let consumed_iterator_errors = if returns_impl_iterator(&func.function.sig.output) {
+        let mut consumer = IteratorConsumer {
+            errors: TokenStream::new(),
+        };
+
+        for c in func.contracts.iter().filter(|c| c.ty.is_post()) {
+            for assertion in &c.assertions {
+                consumer.visit_expr(assertion);
+            }
+        }
+
+        consumer.errors
+    } else {
+        TokenStream::new()
+    };
+
+    let post = if consumed_iterator_errors.is_empty() {
+        let assertions = func
+            .contracts
+            .iter()
+            .filter(|c| c.checked_on_exit())
+            .map(post_assertions)
+            .collect::<TokenStream>();
+        let assertions = collected_failures(assertions, site);
+
+        let ret_ident = ret_binding(Span::call_site());
+
+        quote::quote! {
+            {
+                let #ret_ident = &#ret_ident;
+
+                #assertions
+            }
+        }
+    } else {
+        consumed_iterator_errors
+    };
+
+    // invariants are re-checked when the function body panics, so that the
+    // panic does not hide an inconsistent state. This is not possible for
+    // async functions, as the body can't be moved into a closure, and for
+    // `const fn`s, which can't catch panics.
+    let unwind_checks = if cfg!(feature = "strict_invariants")
+        && func.function.sig.asyncness.is_none()
+        && func.function.sig.constness.is_none()
+    {
+        collected_failures(
+            func.contracts
+                .iter()
+                .filter(|c| c.ty == ContractType::Invariant && c.checked_on_exit())
+                .map(post_assertions)
+                .collect::<TokenStream>(),
+            site,
+        )
+    } else {
+        TokenStream::new()
+    };
+
+    //
+    // bind "old()" expressions
+    //
+
+    let olds = {
+        let mut toks = TokenStream::new();
+
+        for old in olds {
+            let span = old.expr.span();
+
+            let name = syn::Ident::new(&old.name, span);
+
+            let expr = old.expr;
+
+            let binding = quote::quote_spanned! { span=>
+                let #name = #expr;
+            };
+
+            toks.extend(Some(binding));
+        }
+
+        toks
+    };
+
+    //
+    // wrap the function body in a block so that we can use its return value
+    //
+    // This is a labeled block rather than a closure, so the body stays part
+    // of the function and doesn't get in the way of inlining. Only checking
+    // invariants on panics (`strict_invariants`) requires a closure.
+    //
+
+    // the return value is located at the original body, so diagnostics
+    // about it don't point at the attribute
+    let ret_ident = ret_binding(func.function.block.brace_token.span.join());
+
+    let mut replacer = ReturnReplacer::new(&func.function.sig.output);
+
+    let body = 'blk: {
+        let mut block = func.function.block.clone();
+        visit_block_mut(&mut replacer, &mut block);
+
+        let return_type = binding_return_type(&func.function.sig.output);
+
+        if !unwind_checks.is_empty() {
+            let closure_type = return_type.map(|ty| quote::quote! { -> #ty });
+            let binding_type = return_type.map(|ty| quote::quote! { : #ty });
+
+            break 'blk quote::quote! {
+                let #ret_ident #binding_type = match ::std::panic::catch_unwind(
+                    ::std::panic::AssertUnwindSafe(|| #closure_type { '__contracts_run: #block }),
+                ) {
+                    ::core::result::Result::Ok(__contracts_ret) => __contracts_ret,
+                    ::core::result::Result::Err(__contracts_payload) => {
+                        #unwind_checks
+
+                        ::std::panic::resume_unwind(__contracts_payload)
+                    }
+                };
+            };
+        }
+
+        if let Some(return_type) = return_type {
+            break 'blk quote::quote! {
+                let #ret_ident: #return_type = '__contracts_run: #block;
+            };
+        }
+
+        quote::quote! {
+            let #ret_ident = '__contracts_run: #block;
+        }
+    };
+    let try_trait = replacer.try_trait();
+
+    //
+    // create a new function body containing all assertions
+    //
+
+    // functions returning `!` never reach their post-conditions, and their
+    // return value can't be bound, so only the pre-conditions are checked
+    let new_block = if returns_never(&func.function.sig.output) {
+        let block = &func.function.block;
+
+        quote::quote! {
+            {
+                #pre
+
+                #block
+            }
+        }
+    } else {
+        quote::quote! {
+
+            {
+                #try_trait
+
+                #pre
+
+                #olds
+
+                #body
+
+                #post
+
+                #ret_ident
+            }
+
+        }
+    };
+
+    // insert documentation attributes
+
+    func.function.attrs.extend(docs);
+
+    // violations are reported at the caller, which is the one breaking the
+    // contract in case of pre-conditions
+    if tracks_caller && can_track_caller(&func.function) {
+        func.function.attrs.push(syn::parse_quote!(#[track_caller]));
+    }
+
+    // replace the old function body with the new one
+
+    *func.function.block = syn::parse_quote!(#new_block);
+
+    func.function.into_token_stream()
+}
+
+/// Creates the function named by the `checker` option of a pre-condition,
+/// or `__check_pre_<function>` for the `checker` flag, which evaluates its
+/// conditions. It borrows the arguments of the function, including `self`,
+/// and is private.
+pub(crate) fn condition_checker(func: &ItemFn, contract: &Contract) -> Option<TokenStream> {
+    let name = match &contract.options.checker {
+        Some(name) => name.clone(),
+        None if contract.options.default_checker => {
+            quote::format_ident!("__check_pre_{}", func.sig.ident)
+        }
+        None => return None,
+    };
+
+    if contract.ty != ContractType::Requires {
+        let err = syn::Error::new(
+            name.span(),
+            "checker functions are only supported for pre-conditions",
+        );
+        return Some(err.into_compile_error());
+    }
+
+    let mut sig = func.sig.clone();
+    sig.ident = name;
+    sig.asyncness = None;
+    sig.constness = None;
+    sig.output = syn::parse_quote!(-> bool);
+
+    // the arguments are only read by the conditions, which use the bindings
+    // of the borrowed arguments like the values
+    let mut bindings = BindingCollector::default();
+    for input in sig.inputs.iter_mut() {
+        match input {
+            FnArg::Receiver(receiver) if !matches!(receiver.kind, ReceiverKind::Typed(..)) => {
+                *receiver = syn::parse_quote!(&self);
+            }
+            FnArg::Receiver(_) => {}
+            FnArg::Typed(arg) => {
+                if let Pat::Ident(pat) = &mut *arg.pat {
+                    pat.mutability = None;
+                }
+                bindings.visit_pat(&arg.pat);
+
+                let ty = &arg.ty;
+                *arg.ty = syn::parse_quote!(&#ty);
+            }
+        }
+    }
+
+    let mut deref = BindingDeref {
+        names: bindings.names,
+    };
+    let conditions = contract.assertions.iter().map(|cond| {
+        let mut cond = cond.clone();
+        deref.visit_expr_mut(&mut cond);
+        cond
+    });
+    let doc = format!("Checks the pre-condition of `{}`.", func.sig.ident);
+
+    Some(quote::quote! {
+        #[doc = #doc]
+        #[allow(
+            dead_code,
+            unused_variables,
+            clippy::nonminimal_bool,
+            clippy::manual_range_contains,
+            unused_parens
+        )]
+        #sig {
+            true #(&& (#conditions))*
+        }
+    })
+}
+
+/// Collects the names bound by the patterns of arguments.
+#[derive(Default)]
+struct BindingCollector {
+    names: Vec<String>,
+}
+
+impl<'a> Visit<'a> for BindingCollector {
+    fn visit_pat_ident(&mut self, node: &'a syn::PatIdent) {
+        self.names.push(node.ident.to_string());
+        visit::visit_pat_ident(self, node);
+    }
+}
+
+/// Creates the function named by the `unchecked` option of a contract, which
+/// has the same signature and body as the function, but no contracts.
+pub(crate) fn unchecked_variant(func: &ItemFn, name: &Ident) -> TokenStream {
+    let mut variant = func.clone();
+    variant.sig.ident = name.clone();
+
+    let doc = format!(
+        "Variant of `{}` which doesn't check its contracts.",
+        func.sig.ident
+    );
+    variant.attrs.retain(|attr| !attr.path().is_ident("doc"));
+    variant.attrs.insert(0, syn::parse_quote!(#[doc = #doc]));
+
+    variant.into_token_stream()
+}
+
+/// Whether `#[track_caller]` can be added to a function without changing its
+/// meaning. It is not allowed on `main`, functions with a foreign ABI or
+/// `async` functions, and functions might already have it.
+fn can_track_caller(func: &ItemFn) -> bool {
+    if cfg!(feature = "disable_contracts") {
+        return false;
+    }
+
+    let sig = &func.sig;
+
+    let rust_abi = match &sig.abi {
+        Some(abi) => abi.name.as_ref().is_some_and(|name| name.value() == "Rust"),
+        None => true,
+    };
+
+    let tracked = func
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("track_caller"));
+
+    rust_abi && sig.asyncness.is_none() && sig.ident != "main" && !tracked
+}
+
+/// The return type of a function, if it can be used to annotate the binding of
+/// the return value. This is not possible for `impl Trait` types.
+pub(crate) fn binding_return_type(output: &ReturnType) -> Option<&Type> {
+    match output {
+        ReturnType::Type(.., return_type) if !contains_impl_trait(return_type) => Some(return_type),
+        _ => None,
+    }
+}
+
+/// Whether a type contains an `impl Trait` type, which can't be named in
+/// bindings.
+pub(crate) fn contains_impl_trait(ty: &Type) -> bool {
+    let mut impl_detector = ImplDetector { found_impl: false };
+    impl_detector.visit_type(ty);
+    impl_detector.found_impl
+}
+
+/// Whether the function diverges, as its return type is `!`.
+fn returns_never(output: &ReturnType) -> bool {
+    matches!(output, ReturnType::Type(_, ty) if matches!(**ty, Type::Never(_)))
+}
+
+/// Whether the function returns an `impl Iterator` or a similar type.
+fn returns_impl_iterator(output: &ReturnType) -> bool {
+    struct IteratorDetector {
+        found: bool,
+    }
+
+    impl<'a> Visit<'a> for IteratorDetector {
+        fn visit_type_impl_trait(&mut self, node: &'a TypeImplTrait) {
+            self.found |= node.bounds.iter().any(|bound| match bound {
+                TypeParamBound::Trait(bound) => bound.path.segments.last().is_some_and(|seg| {
+                    seg.ident == "Iterator"
+                        || seg.ident == "DoubleEndedIterator"
+                        || seg.ident == "ExactSizeIterator"
+                        || seg.ident == "IntoIterator"
+                }),
+                _ => false,
+            });
+        }
+    }
+
+    let mut detector = IteratorDetector { found: false };
+    visit_return_type(&mut detector, output);
+    detector.found
+}
+
+/// Reports method calls on the return value which consume an iterator.
+struct IteratorConsumer {
+    errors: TokenStream,
+}
+
+impl<'a> Visit<'a> for IteratorConsumer {
+    fn visit_expr_method_call(&mut self, node: &'a ExprMethodCall) {
+        let on_ret = match &*node.receiver {
+            Expr::Path(path) => path.path.is_ident(RET_BINDING),
+            _ => false,
+        };
+
+        let method = node.method.to_string();
+        let inspects = ["clone", "len", "is_empty", "size_hint"].contains(&method.as_str());
+
+        if on_ret && !inspects {
+            let msg = format!(
+                "calling `{}` consumes the returned iterator, inspect a clone of it instead",
+                method
+            );
+            let err = syn::Error::new(node.method.span(), msg);
+            self.errors.extend(err.into_compile_error());
+        }
+
+        visit::visit_expr_method_call(self, node);
+    }
+}
+
+/// Dereferences uses of bindings in conditions which are bound as references
+/// while checking them, like the return value in post-conditions.
+struct BindingDeref {
+    names: Vec<String>,
+}
+
+impl BindingDeref {
+    /// Dereferences the binding of the return value.
+    fn ret() -> Self {
+        Self {
+            names: vec![RET_BINDING.to_string()],
+        }
+    }
+
+    fn is_binding(&self, ident: &Ident) -> bool {
+        self.names.iter().any(|name| ident == name)
+    }
+
+    fn is_binding_path(&self, path: &syn::Path) -> bool {
+        path.get_ident().is_some_and(|ident| self.is_binding(ident))
+    }
+
+    fn deref_tokens(&self, tokens: TokenStream) -> TokenStream {
+        tokens
+            .into_iter()
+            .map(|tt| match tt {
+                TokenTree::Ident(ident) if self.is_binding(&ident) => {
+                    let span = ident.span();
+                    let mut group = Group::new(
+                        Delimiter::Parenthesis,
+                        quote::quote_spanned!(span=> *#ident),
+                    );
+                    group.set_span(span);
+                    TokenTree::Group(group)
+                }
+                TokenTree::Group(group) => {
+                    let mut new_group =
+                        Group::new(group.delimiter(), self.deref_tokens(group.stream()));
+                    new_group.set_span(group.span());
+                    TokenTree::Group(new_group)
+                }
+                tt => tt,
+            })
+            .collect()
+    }
+}
+
+impl VisitMut for BindingDeref {
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        match node {
+            Expr::Path(path) if path.qself.is_none() && self.is_binding_path(&path.path) => {
+                let span = path.span();
+                *node = syn::parse_quote_spanned! { span=> (*#path) };
+            }
+            // arguments of macros are only available as tokens
+            Expr::Macro(mac) => {
+                let tokens = std::mem::take(&mut mac.mac.tokens);
+                mac.mac.tokens = self.deref_tokens(tokens);
+            }
+            _ => visit_expr_mut(self, node),
+        }
+    }
+}
+
+/// Replaces `return` and `?` in a function body by breaking out of the
+/// labeled block wrapping the body, so the post-conditions are checked.
+pub(crate) struct ReturnReplacer {
+    /// The type `?` returns from the function, if it is known.
+    question_mark: Option<QuestionMark>,
+    /// Whether a `?` was rewritten without knowing the returned type.
+    uses_try_trait: bool,
+}
+
+/// Types supported by `?` whose early returns can be rewritten.
+#[derive(Clone, Copy)]
+enum QuestionMark {
+    Result,
+    Option,
+}
+
+impl ReturnReplacer {
+    pub(crate) fn new(output: &ReturnType) -> Self {
+        let last_segment = match output {
+            ReturnType::Type(_, ty) => match &**ty {
+                Type::Path(path) => path.path.segments.last().map(|seg| seg.ident.to_string()),
+                _ => None,
+            },
+            ReturnType::Default => None,
+        };
+
+        let question_mark = match last_segment.as_deref() {
+            Some("Result") => Some(QuestionMark::Result),
+            Some("Option") => Some(QuestionMark::Option),
+            _ => None,
+        };
+
+        Self {
+            question_mark,
+            uses_try_trait: false,
+        }
+    }
+
+    /// The helper trait used by `?` in functions returning a type which isn't
+    /// named `Result` or `Option`, like a type alias. It has to be defined in
+    /// the block wrapping the function body.
+    ///
+    /// `Try` is unstable, so the trait is implemented for the types `?`
+    /// supports in functions on stable: `Result`, `Option`, `Poll` of a
+    /// `Result` or of an `Option` of a `Result`, and `ControlFlow`.
+    pub(crate) fn try_trait(&self) -> Option<TokenStream> {
+        if !self.uses_try_trait {
+            return None;
+        }
+
+        let result = quote::quote!(::core::result::Result);
+        let option = quote::quote!(::core::option::Option);
+        let poll = quote::quote!(::core::task::Poll);
+        let flow = quote::quote!(::core::ops::ControlFlow);
+
+        // `?` on the fallible types returns their error as `ret`, which
+        // builds the return value of the function from the converted `err`
+        let returns_err = |ret: TokenStream, wrap: TokenStream| {
+            quote::quote! {
+                impl<T, E, U, F: ::core::convert::From<E>>
+                    __ContractsTry<#ret> for #result<T, E>
+                {
+                    type Output = T;
+
+                    fn __contracts_branch(self) -> #flow<#ret, T> {
+                        match self {
+                            #result::Ok(val) => #flow::Continue(val),
+                            #result::Err(err) => {
+                                #flow::Break(#wrap)
+                            }
+                        }
+                    }
+                }
+
+                impl<T, E, U, F: ::core::convert::From<E>>
+                    __ContractsTry<#ret> for #poll<#result<T, E>>
+                {
+                    type Output = #poll<T>;
+
+                    fn __contracts_branch(self) -> #flow<#ret, #poll<T>> {
+                        match self {
+                            #poll::Ready(#result::Ok(val)) => #flow::Continue(#poll::Ready(val)),
+                            #poll::Ready(#result::Err(err)) => {
+                                #flow::Break(#wrap)
+                            }
+                            #poll::Pending => #flow::Continue(#poll::Pending),
+                        }
+                    }
+                }
+
+                impl<T, E, U, F: ::core::convert::From<E>>
+                    __ContractsTry<#ret> for #poll<#option<#result<T, E>>>
+                {
+                    type Output = #poll<#option<T>>;
+
+                    fn __contracts_branch(self) -> #flow<#ret, #poll<#option<T>>> {
+                        match self {
+                            #poll::Ready(#option::Some(#result::Ok(val))) => {
+                                #flow::Continue(#poll::Ready(#option::Some(val)))
+                            }
+                            #poll::Ready(#option::Some(#result::Err(err))) => {
+                                #flow::Break(#wrap)
+                            }
+                            #poll::Ready(#option::None) => #flow::Continue(#poll::Ready(#option::None)),
+                            #poll::Pending => #flow::Continue(#poll::Pending),
+                        }
+                    }
+                }
+            }
+        };
+
+        let err = quote::quote!(#result::Err(::core::convert::From::from(err)));
+        let into_result = returns_err(quote::quote!(#result<U, F>), err.clone());
+        let into_poll = returns_err(
+            quote::quote!(#poll<#result<U, F>>),
+            quote::quote!(#poll::Ready(#err)),
+        );
+        let into_poll_option = returns_err(
+            quote::quote!(#poll<#option<#result<U, F>>>),
+            quote::quote!(#poll::Ready(#option::Some(#err))),
+        );
+
+        Some(quote::quote! {
+            trait __ContractsTry<R> {
+                type Output;
+
+                fn __contracts_branch(self) -> #flow<R, Self::Output>;
+            }
+
+            #into_result
+            #into_poll
+            #into_poll_option
+
+            impl<T, U> __ContractsTry<#option<U>> for #option<T> {
+                type Output = T;
+
+                fn __contracts_branch(self) -> #flow<#option<U>, T> {
+                    match self {
+                        #option::Some(val) => #flow::Continue(val),
+                        #option::None => #flow::Break(#option::None),
+                    }
+                }
+            }
+
+            impl<B, C, D> __ContractsTry<#flow<B, D>> for #flow<B, C> {
+                type Output = C;
+
+                fn __contracts_branch(self) -> #flow<#flow<B, D>, C> {
+                    match self {
+                        #flow::Continue(val) => #flow::Continue(val),
+                        #flow::Break(val) => #flow::Break(#flow::Break(val)),
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl VisitMut for ReturnReplacer {
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        match node {
+            // the rewritten expressions are located at the `return` or `?`,
+            // so type errors of the returned value point at them. They are
+            // still generated code, which isn't linted.
+            Expr::Return(ret_expr) => {
+                let span = Span::call_site().located_at(ret_expr.return_token.span);
+                let ret_expr_expr = ret_expr.expr.clone();
+                *node = syn::parse_quote_spanned!(span=> break '__contracts_run #ret_expr_expr);
+            }
+            Expr::Try(try_expr) => {
+                let span = Span::call_site().located_at(try_expr.question_token.span);
+                let expr = &try_expr.expr;
+
+                match self.question_mark {
+                    Some(QuestionMark::Result) => {
+                        *node = syn::parse_quote_spanned! { span=>
+                            match #expr {
+                                ::core::result::Result::Ok(val) => val,
+                                ::core::result::Result::Err(err) => {
+                                    break '__contracts_run ::core::result::Result::Err(
+                                        ::core::convert::From::from(err),
+                                    )
+                                }
+                            }
+                        };
+                    }
+                    Some(QuestionMark::Option) => {
+                        *node = syn::parse_quote_spanned! { span=>
+                            match #expr {
+                                ::core::option::Option::Some(val) => val,
+                                ::core::option::Option::None => {
+                                    break '__contracts_run ::core::option::Option::None
+                                }
+                            }
+                        };
+                    }
+                    // the function returns an alias or another type `?`
+                    // might work with, which is left to the helper trait
+                    None => {
+                        self.uses_try_trait = true;
+                        *node = syn::parse_quote_spanned! { span=>
+                            match __ContractsTry::__contracts_branch(#expr) {
+                                ::core::ops::ControlFlow::Continue(val) => val,
+                                ::core::ops::ControlFlow::Break(ret) => break '__contracts_run ret,
+                            }
+                        };
+                    }
+                }
+            }
+            _ => {}
+        }
 
         visit_expr_mut(self, node);
     }
@@ -383,6 +1916,14 @@ impl VisitMut for ReturnReplacer {
     fn visit_expr_closure_mut(&mut self, _node: &mut ExprClosure) {
         // Do not replace return statements inside closures.  Skip calling the base visitor.
     }
+
+    fn visit_expr_async_mut(&mut self, _node: &mut ExprAsync) {
+        // `return` and `?` inside of async blocks belong to the block.
+    }
+
+    fn visit_item_mut(&mut self, _node: &mut Item) {
+        // Nested items like functions have their own returns.
+    }
 }
 
 struct ImplDetector {
@@ -394,3 +1935,124 @@ impl<'a> Visit<'a> for ImplDetector {
         self.found_impl = true;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_old_calls, interpolated_desc, with_fn_name};
+    use crate::implementation::{Contract, ContractMode, ContractType};
+
+    #[test]
+    fn identical_olds_are_bound_once() {
+        // test contracts are kept by all features disabling contracts
+        let mut contracts = vec![
+            Contract::from_toks(
+                ContractType::Ensures,
+                ContractMode::Test,
+                quote::quote! {
+                    self.len() == old(self.len()) + 1,
+                    old(self.len()) < self.len()
+                },
+            ),
+            Contract::from_toks(
+                ContractType::Ensures,
+                ContractMode::Test,
+                quote::quote! {
+                    self.len() > old(self.len()),
+                    self.cap() >= old(self.cap()),
+                    self.cap() >= old_clone(self.cap())
+                },
+            ),
+        ];
+
+        let olds = extract_old_calls(&mut contracts);
+
+        let names = olds.iter().map(|old| old.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "__contracts_old_0",
+                "__contracts_old_1",
+                "__contracts_old_2"
+            ]
+        );
+    }
+
+    #[test]
+    fn desc_placeholders() {
+        assert_eq!(interpolated_desc("x is positive"), None);
+        assert_eq!(interpolated_desc("no {{placeholder}}"), None);
+        assert_eq!(
+            interpolated_desc("x was {x}, {{x}}"),
+            Some("x was {x}, {{x}}".to_string())
+        );
+        assert_eq!(
+            interpolated_desc("got {ret:?}"),
+            Some("got {__contracts_ret:?}".to_string())
+        );
+    }
+
+    #[test]
+    fn desc_fn_name() {
+        assert_eq!(
+            with_fn_name("{fn} needs {x}", "incr", true),
+            "incr needs {x}"
+        );
+        assert_eq!(
+            with_fn_name("{{fn}} is {fn}", "incr", true),
+            "{{fn}} is incr"
+        );
+        assert_eq!(with_fn_name("{{{fn}}}", "incr", true), "{{incr}}");
+
+        // braces aren't escaped in descriptions shown as they are
+        assert_eq!(
+            with_fn_name("{{fn}} is {fn}", "incr", false),
+            "{incr} is incr"
+        );
+        assert_eq!(with_fn_name("{{x}}", "incr", false), "{{x}}");
+    }
+
+    #[cfg(not(feature = "strict_invariants"))]
+    #[test]
+    fn body_is_not_a_closure() {
+        use syn::visit::Visit;
+
+        // the body is run in a labeled block, which keeps it inlinable
+        #[derive(Default)]
+        struct BodyFinder {
+            closures: usize,
+            labeled_blocks: usize,
+        }
+
+        impl<'a> Visit<'a> for BodyFinder {
+            fn visit_expr_closure(&mut self, node: &'a syn::ExprClosure) {
+                self.closures += 1;
+                syn::visit::visit_expr_closure(self, node);
+            }
+
+            fn visit_expr_block(&mut self, node: &'a syn::ExprBlock) {
+                if node.label.is_some() {
+                    self.labeled_blocks += 1;
+                }
+                syn::visit::visit_expr_block(self, node);
+            }
+        }
+
+        let generated = crate::implementation::ensures(
+            ContractMode::Always,
+            quote::quote!(ret > 0),
+            quote::quote! {
+                #[inline]
+                fn one() -> u32 {
+                    1
+                }
+            },
+        );
+        let func = syn::parse2::<syn::ItemFn>(generated).unwrap();
+
+        let mut finder = BodyFinder::default();
+        finder.visit_item_fn(&func);
+
+        assert_eq!(finder.closures, 0);
+        assert_eq!(finder.labeled_blocks, 1);
+    }
+}
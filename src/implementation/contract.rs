@@ -3,13 +3,16 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use proc_macro2::{Ident, TokenStream};
+use quote::ToTokens;
 use syn::{
     parenthesized,
     parse::{Parse, ParseStream},
-    ItemFn, Token,
+    FnArg, ImplItem, ItemImpl, Token,
 };
 
-use crate::implementation::{emit_error, Contract, ContractType, FuncWithContracts};
+use crate::implementation::{
+    emit_error, generate_function, mark_trait_method, Contract, ContractType, FuncWithContracts,
+};
 
 pub(crate) fn contract(attr: TokenStream, toks: TokenStream) -> TokenStream {
     let clauses = match syn::parse2::<ContractClauses>(attr) {
@@ -17,16 +20,55 @@ pub(crate) fn contract(attr: TokenStream, toks: TokenStream) -> TokenStream {
         Err(err) => return err.to_compile_error(),
     };
 
-    let func: ItemFn = match syn::parse2(toks.clone()) {
-        Ok(func) => func,
-        Err(err) => return emit_error(err, toks),
+    generate_function(toks, |func| {
+        FuncWithContracts::new_with_contracts(func, clauses.contracts).generate()
+    })
+}
+
+/// Add the contracts to all methods of an `impl` block which take `self`.
+pub(crate) fn contract_impl(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let mut impl_def = match syn::parse2::<ItemImpl>(toks.clone()) {
+        Ok(impl_def) => impl_def,
+        Err(_) => {
+            let err = syn::Error::new_spanned(
+                &toks,
+                "the #[contract_impl] attribute only works on impl blocks",
+            );
+            return emit_error(err, toks);
+        }
     };
 
-    FuncWithContracts::new_with_contracts(func, clauses.contracts).generate()
+    // the clauses are checked here, so errors are reported once
+    if let Err(err) = syn::parse2::<ContractClauses>(attr.clone()) {
+        return emit_error(err, impl_def);
+    }
+
+    // the contracts are added as the first attribute of each method, so
+    // `#[contract]` picks up the other contracts of the method and all of
+    // them are checked by the same wrapper
+    let is_trait_impl = impl_def.trait_.is_some();
+
+    for item in &mut impl_def.items {
+        if let ImplItem::Fn(method) = item {
+            if !matches!(method.sig.inputs.first(), Some(FnArg::Receiver(_))) {
+                continue;
+            }
+
+            method
+                .attrs
+                .insert(0, syn::parse_quote!(#[contract(#attr)]));
+            if is_trait_impl {
+                mark_trait_method(&mut method.attrs);
+            }
+        }
+    }
+
+    impl_def.into_token_stream()
 }
 
-struct ContractClauses {
-    contracts: Vec<Contract>,
+/// Contract clauses like `requires(x > 0), ensures(ret > x)`.
+pub(crate) struct ContractClauses {
+    pub(crate) contracts: Vec<Contract>,
 }
 
 impl Parse for ContractClauses {
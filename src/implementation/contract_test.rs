@@ -0,0 +1,127 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use proc_macro2::{Ident, TokenStream};
+use quote::ToTokens;
+use syn::{FnArg, Meta, Pat};
+
+use crate::implementation::{emit_error, parse_function, Contract, ContractType};
+
+pub(crate) fn contract_test(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let func = match parse_function(toks.clone()) {
+        Ok(func) => func,
+        Err(err) => return emit_error(err, toks),
+    };
+
+    if !attr.is_empty() {
+        let err = syn::Error::new_spanned(attr, "`#[contract_test]` does not take any arguments");
+        return emit_error(err, func);
+    }
+
+    let sig = &func.sig;
+
+    let unsupported = if sig.asyncness.is_some() {
+        Some(syn::Error::new_spanned(
+            sig.asyncness,
+            "`#[contract_test]` does not support `async` functions",
+        ))
+    } else if !sig.generics.params.is_empty() {
+        Some(syn::Error::new_spanned(
+            &sig.generics,
+            "`#[contract_test]` does not support generic functions",
+        ))
+    } else {
+        None
+    };
+
+    if let Some(err) = unsupported {
+        return emit_error(err, func);
+    }
+
+    // the generated inputs are bound to the names of the arguments, so the
+    // pre-conditions can refer to them
+    let mut names = vec![];
+    let mut types = vec![];
+
+    for input in &sig.inputs {
+        let arg = match input {
+            FnArg::Typed(arg) => arg,
+            FnArg::Receiver(receiver) => {
+                let err = syn::Error::new_spanned(
+                    receiver,
+                    "`#[contract_test]` only supports free functions",
+                );
+                return emit_error(err, func);
+            }
+        };
+
+        match &*arg.pat {
+            Pat::Ident(pat) if pat.subpat.is_none() => names.push(pat.ident.clone()),
+            pat => {
+                let err = syn::Error::new_spanned(
+                    pat,
+                    "`#[contract_test]` only supports arguments bound to a name",
+                );
+                return emit_error(err, func);
+            }
+        }
+
+        types.push(&arg.ty);
+    }
+
+    // only inputs satisfying the pre-conditions are used. The contracts are
+    // still on the function, so they have to follow this attribute.
+    let preconditions = func
+        .attrs
+        .iter()
+        .filter_map(|attr| {
+            let name = attr.path().segments.last()?.ident.to_string();
+            let (ty, mode) = ContractType::contract_type_and_mode(&name)?;
+
+            let toks = match &attr.meta {
+                Meta::List(list) => list.tokens.clone(),
+                _ => return None,
+            };
+
+            (ty == ContractType::Requires).then(|| Contract::from_toks(ty, mode, toks))
+        })
+        .flat_map(|contract| contract.assertions)
+        .collect::<Vec<_>>();
+
+    let discard = if preconditions.is_empty() {
+        None
+    } else {
+        Some(quote::quote! {
+            #[allow(clippy::nonminimal_bool, unused_parens)]
+            if #(!(#preconditions))||* {
+                return quickcheck::TestResult::discard();
+            }
+        })
+    };
+
+    let name = &sig.ident;
+    let test_name = Ident::new(&format!("contract_test_{}", name), name.span());
+
+    let test = quote::quote! {
+        #[cfg(test)]
+        #[test]
+        fn #test_name() {
+            fn property(#(#names: #types),*) -> quickcheck::TestResult {
+                #discard
+
+                let result = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+                    let _ = #name(#(#names),*);
+                }));
+
+                quickcheck::TestResult::from_bool(result.is_ok())
+            }
+
+            quickcheck::quickcheck(property as fn(#(#types),*) -> quickcheck::TestResult);
+        }
+    };
+
+    let mut output = func.into_token_stream();
+    output.extend(test);
+    output
+}
@@ -0,0 +1,179 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::ToTokens;
+use syn::{
+    punctuated::Punctuated,
+    spanned::Spanned,
+    visit_mut::{self, VisitMut},
+    Expr, ExprCall, FnArg, Item, Pat, Token,
+};
+
+use crate::implementation::{
+    codegen::{contains_impl_trait, make_assertion, AssertionSite, ViolationInfo},
+    emit_error, parse_function, ContractMode, ContractOptions, ContractType,
+};
+
+/// Name of the variable that holds the measure on entry of the function.
+const MEASURE_BINDING: &str = "__contracts_measure";
+
+pub(crate) fn decreases(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let mut func = match parse_function(toks.clone()) {
+        Ok(func) => func,
+        Err(err) => return emit_error(err, toks),
+    };
+
+    let measure: Expr = match syn::parse2(attr) {
+        Ok(measure) => measure,
+        Err(err) => return emit_error(err, func),
+    };
+
+    let mode = ContractMode::Debug.final_mode();
+
+    if mode == ContractMode::Disabled {
+        return func.into_token_stream();
+    }
+
+    let func_name = func.sig.ident.to_string();
+    let measure_ident = Ident::new(MEASURE_BINDING, Span::call_site());
+
+    let make_check = |display: TokenStream, exec_expr: Expr, problem: &str| {
+        let desc = format!("Termination measure of {} violated: {}", func_name, problem);
+
+        let assertion = make_assertion(
+            mode,
+            ContractType::Requires,
+            &ViolationInfo {
+                func_name: Some(&func_name),
+                kind: "Termination measure",
+                desc: &desc,
+                description: Some(problem),
+            },
+            display,
+            &exec_expr,
+            &ContractOptions::default(),
+            AssertionSite::Other,
+        );
+
+        // the measure is only bound in debug builds
+        quote::quote! {
+            #[cfg(debug_assertions)]
+            #[allow(unused_comparisons, clippy::absurd_extreme_comparisons)]
+            {
+                #assertion
+            }
+        }
+    };
+
+    // recursive calls can only be checked if all parameters can be rebound
+    // to the arguments of the call
+    let params = func
+        .sig
+        .inputs
+        .iter()
+        .map(|input| match input {
+            FnArg::Typed(arg) => match &*arg.pat {
+                Pat::Ident(pat) if pat.by_ref.is_none() && pat.subpat.is_none() => {
+                    let ty = if contains_impl_trait(&arg.ty) {
+                        quote::quote!(_)
+                    } else {
+                        arg.ty.to_token_stream()
+                    };
+
+                    Some((pat.ident.clone(), ty))
+                }
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect::<Option<Vec<_>>>();
+
+    if let Some(params) = params {
+        let span = measure.span();
+        let display = quote::quote_spanned! { span=> #measure };
+        let exec_expr = syn::parse_quote_spanned! { span=> (#measure) < #measure_ident };
+        let check = make_check(display, exec_expr, "measure does not decrease");
+
+        let mut rewriter = RecursiveCallRewriter {
+            func_name: func.sig.ident.clone(),
+            params,
+            check,
+        };
+        rewriter.visit_block_mut(&mut func.block);
+    }
+
+    let span = measure.span();
+    let display = quote::quote_spanned! { span=> #measure >= 0 };
+    let exec_expr = syn::parse_quote_spanned! { span=> #measure_ident >= 0 };
+    let entry_check = make_check(display, exec_expr, "measure is negative");
+
+    let block = &func.block;
+
+    let new_block = quote::quote! {
+        {
+            #[cfg(debug_assertions)]
+            let #measure_ident = #measure;
+
+            #entry_check
+
+            #block
+        }
+    };
+
+    *func.block = syn::parse_quote!(#new_block);
+
+    func.into_token_stream()
+}
+
+/// Checks the measure of recursive calls of a function, by binding the
+/// arguments to the parameter names and evaluating the measure with them
+/// before calling the function.
+struct RecursiveCallRewriter {
+    func_name: Ident,
+    params: Vec<(Ident, TokenStream)>,
+    check: TokenStream,
+}
+
+impl VisitMut for RecursiveCallRewriter {
+    fn visit_expr_mut(&mut self, node: &mut Expr) {
+        visit_mut::visit_expr_mut(self, node);
+
+        let call = match node {
+            Expr::Call(call) => call,
+            _ => return,
+        };
+
+        let is_recursive = match &*call.func {
+            Expr::Path(path) => path.qself.is_none() && path.path.is_ident(&self.func_name),
+            _ => false,
+        };
+
+        if !is_recursive || call.args.len() != self.params.len() {
+            return;
+        }
+
+        let ExprCall { func, args, .. } = call;
+        let names = self.params.iter().map(|(name, _)| name);
+        let types = self.params.iter().map(|(_, ty)| ty);
+        let check = &self.check;
+
+        let args = args.iter();
+        let new_args: Punctuated<Ident, Token![,]> = names.clone().cloned().collect();
+
+        *node = syn::parse_quote! {
+            {
+                let (#(#names,)*): (#(#types,)*) = (#(#args,)*);
+
+                #check
+
+                #func(#new_args)
+            }
+        };
+    }
+
+    fn visit_item_mut(&mut self, _node: &mut Item) {
+        // nested items can't refer to the measure
+    }
+}
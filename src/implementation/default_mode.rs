@@ -0,0 +1,143 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use proc_macro2::{Delimiter, Ident, TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::{visit_mut::VisitMut, Attribute, Item, Meta};
+
+use crate::implementation::emit_error;
+
+/// Contracts which have `debug_` and `test_` versions.
+const CONTRACTS: &[&str] = &["requires", "ensures", "invariant"];
+
+pub(crate) fn default_mode(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let mut item: Item = match syn::parse2(toks.clone()) {
+        Ok(item) => item,
+        Err(err) => return emit_error(err, toks),
+    };
+
+    let mode: Ident = match syn::parse2(attr.clone()) {
+        Ok(mode) => mode,
+        Err(_) => {
+            let err = syn::Error::new_spanned(attr, "expected `always`, `debug` or `test`");
+            return emit_error(err, item);
+        }
+    };
+
+    let prefix = match mode.to_string().as_str() {
+        "always" => return item.into_token_stream(),
+        "debug" => "debug_",
+        "test" => "test_",
+        _ => {
+            let err = syn::Error::new_spanned(mode, "expected `always`, `debug` or `test`");
+            return emit_error(err, item);
+        }
+    };
+
+    ModeRewriter { prefix }.visit_item_mut(&mut item);
+
+    item.into_token_stream()
+}
+
+/// Adds a prefix to all contract attributes without an explicit mode.
+struct ModeRewriter {
+    prefix: &'static str,
+}
+
+impl ModeRewriter {
+    fn prefixed(&self, ident: &Ident) -> Option<Ident> {
+        if CONTRACTS.contains(&ident.to_string().as_str()) {
+            Some(Ident::new(
+                &format!("{}{}", self.prefix, ident),
+                ident.span(),
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+impl VisitMut for ModeRewriter {
+    fn visit_attribute_mut(&mut self, attr: &mut Attribute) {
+        let segment = match attr.path().segments.last() {
+            Some(segment) => segment.ident.clone(),
+            None => return,
+        };
+
+        if let Some(prefixed) = self.prefixed(&segment) {
+            if let Meta::List(list) = &mut attr.meta {
+                list.path.segments.last_mut().unwrap().ident = prefixed;
+            } else if let Meta::Path(path) = &mut attr.meta {
+                path.segments.last_mut().unwrap().ident = prefixed;
+            }
+        } else if segment == "contract" {
+            // rewrite the clauses of `#[contract(...)]`
+            if let Meta::List(list) = &mut attr.meta {
+                let mut tokens = list.tokens.clone().into_iter().peekable();
+                let mut new_tokens = TokenStream::new();
+
+                while let Some(tt) = tokens.next() {
+                    let is_clause = matches!(
+                        tokens.peek(),
+                        Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis
+                    );
+
+                    match &tt {
+                        TokenTree::Ident(ident) if is_clause => {
+                            let ident = self.prefixed(ident).unwrap_or_else(|| ident.clone());
+                            new_tokens.extend(Some(TokenTree::Ident(ident)));
+                        }
+                        _ => new_tokens.extend(Some(tt)),
+                    }
+                }
+
+                list.tokens = new_tokens;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::default_mode;
+
+    #[test]
+    fn contracts_without_mode_are_rewritten() {
+        let attr = quote::quote!(debug);
+
+        let code = quote::quote! {
+            mod library {
+                #[invariant(self.len() < 10)]
+                impl Library {
+                    #[requires(!self.contains(id))]
+                    #[contracts::ensures(self.contains(id))]
+                    #[test_ensures(self.len() > 0)]
+                    fn add(&mut self, id: u32) {}
+                }
+
+                #[contract(requires(x > 0), test_requires(x < 10))]
+                fn check(x: u32) {}
+            }
+        };
+
+        let expected = quote::quote! {
+            mod library {
+                #[debug_invariant(self.len() < 10)]
+                impl Library {
+                    #[debug_requires(!self.contains(id))]
+                    #[contracts::debug_ensures(self.contains(id))]
+                    #[test_ensures(self.len() > 0)]
+                    fn add(&mut self, id: u32) {}
+                }
+
+                #[contract(debug_requires(x > 0), test_requires(x < 10))]
+                fn check(x: u32) {}
+            }
+        };
+
+        let generated = default_mode(attr, code);
+
+        assert_eq!(generated.to_string(), expected.to_string());
+    }
+}
@@ -8,38 +8,71 @@ use syn::{parse::Parser, Attribute};
 use crate::implementation::{Contract, ContractMode};
 
 pub(crate) fn generate_attributes(contracts: &[Contract]) -> Vec<Attribute> {
-    let mut attrs = vec![];
+    let documented = contract_attributes(contracts);
+
+    // no header if all contracts are left out
+    if documented.is_empty() {
+        return documented;
+    }
+
+    // header
+    let mut attrs = vec![make_attribute("# Contracts")];
+
+    attrs.extend(documented);
+
+    attrs
+}
+
+pub(crate) fn make_attribute(content: &str) -> Attribute {
+    let span = Span::call_site();
 
-    fn make_attribute(content: &str) -> Attribute {
-        let span = Span::call_site();
+    let content_str = syn::LitStr::new(content, span);
 
-        let content_str = syn::LitStr::new(content, span);
+    let toks: TokenStream = quote::quote_spanned!( span=> #[doc = #content_str] );
 
-        let toks: TokenStream = quote::quote_spanned!( span=> #[doc = #content_str] );
+    let parser = Attribute::parse_outer;
 
-        let parser = Attribute::parse_outer;
+    let mut attributes = parser.parse2(toks).unwrap();
 
-        let mut attributes = parser.parse2(toks).unwrap();
+    attributes.remove(0)
+}
 
-        attributes.remove(0)
+/// Documentation of the contracts, as a table with the `doc_tables` feature
+/// and one paragraph each otherwise.
+pub(crate) fn contract_attributes(contracts: &[Contract]) -> Vec<Attribute> {
+    let contracts = contracts
+        .iter()
+        .filter(|contract| !contract.options.no_doc)
+        .collect::<Vec<_>>();
+
+    if cfg!(feature = "doc_tables") {
+        table_attributes(&contracts)
+    } else {
+        list_attributes(&contracts)
     }
+}
 
-    fn print_stream(stream: &TokenStream) -> String {
-        stream.to_string()
+fn print_stream(stream: &TokenStream) -> String {
+    stream.to_string()
+}
+
+fn mode_name(mode: ContractMode) -> Option<&'static str> {
+    match mode {
+        ContractMode::Always => None,
+        ContractMode::Disabled => None,
+        ContractMode::Debug => Some("debug"),
+        ContractMode::Test => Some("test"),
+        ContractMode::LogOnly => Some("log"),
+        ContractMode::ReturnError => Some("returns error"),
     }
+}
 
-    // header
-    attrs.push(make_attribute("# Contracts"));
+fn list_attributes(contracts: &[&Contract]) -> Vec<Attribute> {
+    let mut attrs = vec![];
 
     for contract in contracts {
         let ty = contract.ty;
-        let mode = match contract.mode {
-            ContractMode::Always => None,
-            ContractMode::Disabled => None,
-            ContractMode::Debug => Some("debug"),
-            ContractMode::Test => Some("test"),
-            ContractMode::LogOnly => None,
-        };
+        let mode = mode_name(contract.mode);
 
         if let Some(desc) = &contract.desc {
             // document all assertions under the description
@@ -80,3 +113,112 @@ pub(crate) fn generate_attributes(contracts: &[Contract]) -> Vec<Attribute> {
 
     attrs
 }
+
+/// A single table with a row for each condition.
+fn table_attributes(contracts: &[&Contract]) -> Vec<Attribute> {
+    if contracts.is_empty() {
+        return vec![];
+    }
+
+    // pipes would end the cell, even inside of code spans
+    fn escape(cell: &str) -> String {
+        cell.replace('|', "\\|")
+    }
+
+    let mut attrs = vec![
+        make_attribute("| Kind | Mode | Condition | Description |"),
+        make_attribute("|------|------|-----------|-------------|"),
+    ];
+
+    for contract in contracts {
+        let mode = mode_name(contract.mode).unwrap_or("always");
+        let desc = contract.desc.as_deref().unwrap_or("");
+
+        for stream in &contract.streams {
+            attrs.push(make_attribute(&format!(
+                "| {} | {} | `{}` | {} |",
+                contract.ty.message_name(),
+                mode,
+                escape(&print_stream(stream)),
+                escape(desc),
+            )));
+        }
+    }
+
+    attrs.push(make_attribute(""));
+
+    attrs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::implementation::ContractType;
+
+    fn doc_strings(attrs: &[Attribute]) -> Vec<String> {
+        attrs
+            .iter()
+            .map(|attr| quote::quote!(#attr).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn suppressed_docs() {
+        let documented = Contract::from_toks(
+            ContractType::Requires,
+            ContractMode::Always,
+            quote::quote!(x > 0),
+        );
+        let hidden = Contract::from_toks(
+            ContractType::Ensures,
+            ContractMode::Always,
+            quote::quote!(ret > x, no_doc),
+        );
+
+        let docs = doc_strings(&generate_attributes(&[documented, hidden]));
+        assert_eq!(docs[0], "# [doc = \"# Contracts\"]");
+        assert!(docs.iter().any(|doc| doc.contains("x > 0")));
+        assert!(!docs.iter().any(|doc| doc.contains("ret > x")));
+
+        let hidden = Contract::from_toks(
+            ContractType::Requires,
+            ContractMode::Always,
+            quote::quote!(x > 0, no_doc),
+        );
+
+        assert!(generate_attributes(&[hidden]).is_empty());
+    }
+
+    #[test]
+    fn table_docs() {
+        let contracts = [
+            Contract::from_toks(
+                ContractType::Requires,
+                ContractMode::Debug,
+                quote::quote!(x > 0 || y > 0, "one is positive"),
+            ),
+            Contract::from_toks(
+                ContractType::Ensures,
+                ContractMode::Always,
+                quote::quote!(ret > x),
+            ),
+        ];
+        let contracts = contracts.iter().collect::<Vec<_>>();
+
+        let docs = doc_strings(&table_attributes(&contracts));
+        assert_eq!(docs.len(), 5);
+        assert_eq!(
+            docs[0],
+            "# [doc = \"| Kind | Mode | Condition | Description |\"]"
+        );
+        assert_eq!(
+            docs[2],
+            "# [doc = \"| Pre-condition | debug | `x > 0 \\\\|\\\\| y > 0` | one is positive |\"]"
+        );
+        assert_eq!(
+            docs[3],
+            "# [doc = \"| Post-condition | always | `ret > x` |  |\"]"
+        );
+    }
+}
@@ -4,17 +4,43 @@
 
 use proc_macro2::TokenStream;
 
-use crate::implementation::{emit_error, ContractMode, ContractType, FuncWithContracts};
+use crate::implementation::{generate_function, ContractMode, ContractType, FuncWithContracts};
 
 pub(crate) fn ensures(mode: ContractMode, attr: TokenStream, toks: TokenStream) -> TokenStream {
-    let ty = ContractType::Ensures;
+    ensures_with_type(ContractType::Ensures, mode, attr, toks)
+}
+
+pub(crate) fn ensures_ok(mode: ContractMode, attr: TokenStream, toks: TokenStream) -> TokenStream {
+    ensures_with_type(ContractType::EnsuresOk, mode, attr, toks)
+}
 
-    let func = match syn::parse2(toks.clone()) {
-        Ok(func) => func,
-        Err(err) => return emit_error(err, toks),
-    };
+pub(crate) fn ensures_err(mode: ContractMode, attr: TokenStream, toks: TokenStream) -> TokenStream {
+    ensures_with_type(ContractType::EnsuresErr, mode, attr, toks)
+}
 
-    let f = FuncWithContracts::new_with_initial_contract(func, ty, mode, attr);
+pub(crate) fn ensures_some(
+    mode: ContractMode,
+    attr: TokenStream,
+    toks: TokenStream,
+) -> TokenStream {
+    ensures_with_type(ContractType::EnsuresSome, mode, attr, toks)
+}
+
+pub(crate) fn ensures_none(
+    mode: ContractMode,
+    attr: TokenStream,
+    toks: TokenStream,
+) -> TokenStream {
+    ensures_with_type(ContractType::EnsuresNone, mode, attr, toks)
+}
 
-    f.generate()
+fn ensures_with_type(
+    ty: ContractType,
+    mode: ContractMode,
+    attr: TokenStream,
+    toks: TokenStream,
+) -> TokenStream {
+    generate_function(toks, |func| {
+        FuncWithContracts::new_with_initial_contract(func, ty, mode, attr).generate()
+    })
 }
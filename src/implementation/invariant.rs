@@ -4,9 +4,16 @@
 
 use proc_macro2::TokenStream;
 use quote::ToTokens;
-use syn::{FnArg, ImplItem, ImplItemFn, Item, ItemFn, ItemImpl};
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, FnArg, Generics, Ident, ImplItem, ImplItemFn, Item, ItemFn, ItemImpl, LitStr, Meta,
+    ReturnType, Type,
+};
 
-use crate::implementation::{emit_error, ContractMode, ContractType, FuncWithContracts};
+use crate::implementation::{
+    codegen::{debug_only, feature_gate, make_assertion, AssertionSite, ViolationInfo},
+    emit_error, mark_trait_method, parse, Contract, ContractMode, ContractType, FuncWithContracts,
+};
 
 pub(crate) fn invariant(mode: ContractMode, attr: TokenStream, toks: TokenStream) -> TokenStream {
     let name = mode.name().unwrap().to_string() + "invariant";
@@ -19,11 +26,23 @@ pub(crate) fn invariant(mode: ContractMode, attr: TokenStream, toks: TokenStream
     match item {
         Item::Fn(fn_) => invariant_fn(mode, attr, fn_),
         Item::Impl(impl_) => invariant_impl(mode, attr, impl_),
+        Item::Struct(mut struct_) => {
+            let mut contracts = vec![Contract::from_toks(ContractType::Invariant, mode, attr)];
+            contracts.extend(take_type_invariants(&mut struct_.attrs));
+            let check = invariant_check_method(contracts, &struct_.ident, &struct_.generics);
+            quote::quote! { #struct_ #check }
+        }
+        Item::Enum(mut enum_) => {
+            let mut contracts = vec![Contract::from_toks(ContractType::Invariant, mode, attr)];
+            contracts.extend(take_type_invariants(&mut enum_.attrs));
+            let check = invariant_check_method(contracts, &enum_.ident, &enum_.generics);
+            quote::quote! { #enum_ #check }
+        }
         item => {
             let error = syn::Error::new_spanned(
                 &item,
                 format!(
-                    "the #[{}] attribute only works on functions and impl blocks",
+                    "the #[{}] attribute only works on functions, impl blocks, structs and enums",
                     name
                 ),
             );
@@ -63,6 +82,41 @@ fn invariant_impl(
 
     let invariant_ident = syn::Ident::new(&name, proc_macro2::Span::call_site());
 
+    // constructors don't take `self`, so the invariant is checked on the
+    // returned value instead
+    let (invariant, check_constructors) = parse::take_flag(invariant, "check_constructors");
+
+    // constructors only have an exit, and `ensures` doesn't know the option.
+    // Invalid values are reported by the invariants of the other methods.
+    let (constructor_invariant, when) = parse::take_option(invariant.clone(), "when");
+    let check_constructors = check_constructors
+        && !when
+            .and_then(|when| syn::parse2::<LitStr>(when).ok())
+            .is_some_and(|when| when.value() == "entry");
+
+    let ensures_ident = syn::Ident::new(
+        &(mode.name().unwrap_or_default().to_string() + "ensures"),
+        proc_macro2::Span::call_site(),
+    );
+
+    let self_ident = match &*impl_def.self_ty {
+        Type::Path(path) => path.path.segments.last().map(|seg| seg.ident.clone()),
+        _ => None,
+    };
+
+    let is_constructor = |method: &ImplItemFn| match &method.sig.output {
+        ReturnType::Type(_, ty) => match &**ty {
+            Type::Path(path) if path.qself.is_none() => {
+                path.path.is_ident("Self")
+                    || self_ident
+                        .as_ref()
+                        .is_some_and(|ident| path.path.is_ident(ident))
+            }
+            _ => false,
+        },
+        ReturnType::Default => false,
+    };
+
     fn method_uses_self(method: &ImplItemFn) -> bool {
         let inputs = &method.sig.inputs;
 
@@ -73,8 +127,38 @@ fn invariant_impl(
         }
     }
 
+    // methods may leave the value in a transient state on purpose, like
+    // private helpers of other methods
+    fn opted_out(method: &ImplItemFn) -> bool {
+        method.attrs.iter().any(|attr| {
+            attr.path()
+                .segments
+                .last()
+                .is_some_and(|seg| seg.ident == "no_invariant")
+        })
+    }
+
+    let is_trait_impl = impl_def.trait_.is_some();
+
     for item in &mut impl_def.items {
         if let ImplItem::Fn(method) = item {
+            if opted_out(method) {
+                continue;
+            }
+
+            if check_constructors && !method_uses_self(method) && is_constructor(method) {
+                let method_toks = quote::quote! {
+                    #[#ensures_ident(@self: #constructor_invariant)]
+                    #method
+                };
+
+                *method = syn::parse_quote!(#method_toks);
+                if is_trait_impl {
+                    mark_trait_method(&mut method.attrs);
+                }
+                continue;
+            }
+
             // only implement invariants for methods that take `self`
             if !method_uses_self(method) {
                 continue;
@@ -85,7 +169,10 @@ fn invariant_impl(
                 #method
             };
 
-            let met: ImplItemFn = syn::parse_quote!(#method_toks);
+            let mut met: ImplItemFn = syn::parse_quote!(#method_toks);
+            if is_trait_impl {
+                mark_trait_method(&mut met.attrs);
+            }
 
             *method = met;
         }
@@ -93,3 +180,145 @@ fn invariant_impl(
 
     impl_def.into_token_stream()
 }
+
+/// Mark a method to be skipped by the invariants of its `impl` block. The
+/// marker itself is left as it is.
+pub(crate) fn no_invariant(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        let err = syn::Error::new_spanned(attr, "`#[no_invariant]` does not take any arguments");
+        return emit_error(err, toks);
+    }
+
+    toks
+}
+
+/// Remove the invariants stacked below the expanded one on a type
+/// definition, so all of them end up in the same check methods.
+fn take_type_invariants(attrs: &mut Vec<Attribute>) -> Vec<Contract> {
+    let mut contracts = Vec::new();
+
+    attrs.retain(|attr| {
+        let name = attr.path().segments.last().unwrap().ident.to_string();
+        match ContractType::contract_type_and_mode(&name) {
+            Some((ContractType::Invariant, mode)) => {
+                let toks = match &attr.meta {
+                    Meta::List(list) => list.tokens.clone(),
+                    meta => meta.to_token_stream(),
+                };
+                contracts.push(Contract::from_toks(ContractType::Invariant, mode, toks));
+                false
+            }
+            _ => true,
+        }
+    });
+
+    contracts
+}
+
+/// Generate the methods checking the invariants of a type definition.
+///
+/// The attribute can't see the `impl` blocks of the type, so the invariants
+/// can't be added to its methods. Instead the generated
+/// `__check_invariant` method can be called where the invariants should hold.
+///
+/// The crate can't export a violation type, so `contracts_check` reports the
+/// kind, the condition and the description of each violated condition as a
+/// tuple. It only evaluates the conditions `__check_invariant` checks.
+fn invariant_check_method(
+    contracts: Vec<Contract>,
+    ident: &Ident,
+    generics: &Generics,
+) -> TokenStream {
+    let kind = ContractType::Invariant.message_name();
+
+    let mut assertions = TokenStream::new();
+    let mut failures = TokenStream::new();
+
+    for contract in &contracts {
+        let mode = contract.final_mode();
+
+        let desc = if let Some(desc) = contract.desc.as_ref() {
+            format!("{} of {} violated: {}", kind, ident, desc)
+        } else {
+            format!("{} of {} violated", kind, ident)
+        };
+
+        let info = ViolationInfo {
+            func_name: None,
+            kind,
+            desc: &desc,
+            description: contract.desc.as_deref(),
+        };
+
+        let description = match &contract.desc {
+            Some(desc) => quote::quote!(::core::option::Option::Some(#desc)),
+            None => quote::quote!(::core::option::Option::None),
+        };
+
+        for (expr, display) in contract.assertions.iter().zip(contract.streams.iter()) {
+            assertions.extend(make_assertion(
+                mode,
+                ContractType::Invariant,
+                &info,
+                display.clone(),
+                expr,
+                &contract.options,
+                AssertionSite::Other,
+            ));
+
+            if mode == ContractMode::Disabled {
+                continue;
+            }
+
+            // `contracts_check` reports the failing conditions instead of
+            // panicking, in the same builds `__check_invariant` checks them
+            let span = display.span();
+            let check = quote::quote_spanned! { span=>
+                #[allow(clippy::nonminimal_bool, clippy::neg_cmp_op_on_partial_ord, unused_parens)]
+                if !(#expr) {
+                    __contracts_failed.push((#kind, stringify!(#display), #description));
+                }
+            };
+            let check = debug_only(mode, check, span);
+            failures.extend(feature_gate(check, &contract.options, span));
+        }
+    }
+
+    let doc = format!("Checks the invariants of [`{}`].", ident);
+    let check_doc = format!(
+        "Evaluates the invariants of [`{}`] without panicking.\n\n\
+         Returns the kind, the condition and the description of every \
+         violated condition.",
+        ident
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote::quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #[doc = #doc]
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            pub fn __check_invariant(&self) {
+                #assertions
+            }
+
+            #[doc = #check_doc]
+            #[allow(dead_code)]
+            pub fn contracts_check(
+                &self,
+            ) -> ::core::result::Result<
+                (),
+                ::std::vec::Vec<(&'static str, &'static str, ::core::option::Option<&'static str>)>,
+            > {
+                #[allow(unused_mut)]
+                let mut __contracts_failed = ::std::vec::Vec::new();
+                #failures
+                if __contracts_failed.is_empty() {
+                    ::core::result::Result::Ok(())
+                } else {
+                    ::core::result::Result::Err(__contracts_failed)
+                }
+            }
+        }
+    }
+}
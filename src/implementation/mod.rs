@@ -2,23 +2,47 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+pub(crate) mod assert_contract;
+pub(crate) mod closure;
 pub(crate) mod codegen;
 pub(crate) mod contract;
+pub(crate) mod contract_test;
+pub(crate) mod decreases;
+pub(crate) mod default_mode;
 pub(crate) mod doc;
 pub(crate) mod ensures;
 pub(crate) mod invariant;
 pub(crate) mod parse;
+pub(crate) mod pure;
 pub(crate) mod requires;
 pub(crate) mod traits;
+pub(crate) mod wrap;
 
-pub(crate) use contract::contract;
-pub(crate) use ensures::ensures;
-pub(crate) use invariant::invariant;
-use proc_macro2::{Span, TokenStream};
+pub(crate) use assert_contract::assert_contract;
+pub(crate) use contract::{contract, contract_impl};
+pub(crate) use contract_test::contract_test;
+pub(crate) use decreases::decreases;
+pub(crate) use default_mode::default_mode;
+pub(crate) use ensures::{ensures, ensures_err, ensures_none, ensures_ok, ensures_some};
+pub(crate) use invariant::{invariant, no_invariant};
+use proc_macro2::{Ident, Span, TokenStream};
+pub(crate) use pure::pure;
 use quote::ToTokens;
 pub(crate) use requires::requires;
-use syn::{Expr, ItemFn};
-pub(crate) use traits::{contract_trait_item_impl, contract_trait_item_trait};
+use syn::{Attribute, Expr, ItemFn, Visibility};
+pub(crate) use traits::contract_trait;
+pub(crate) use wrap::wrap;
+
+/// Name of the variable that holds the return value of a function.
+pub(crate) const RET_BINDING: &str = "__contracts_ret";
+
+/// Identifier of the variable holding the return value. It has mixed-site
+/// hygiene, so the binding and the uses in conditions refer to each other even
+/// if the tokens of a contract come from another macro expansion, while code
+/// of the function can't see it.
+pub(crate) fn ret_binding(span: Span) -> Ident {
+    Ident::new(RET_BINDING, Span::mixed_site().located_at(span))
+}
 
 pub(crate) fn emit_error(err: syn::Error, tokens: impl ToTokens) -> TokenStream {
     let error = err.into_compile_error();
@@ -28,6 +52,39 @@ pub(crate) fn emit_error(err: syn::Error, tokens: impl ToTokens) -> TokenStream
     }
 }
 
+/// Parse the function a contract is attached to.
+///
+/// Declarations without a body, like functions in `extern` blocks or required
+/// trait methods, are reported with an explanation instead of a parse error.
+pub(crate) fn parse_function(toks: TokenStream) -> syn::Result<ItemFn> {
+    syn::parse2::<ItemFn>(toks.clone()).map_err(|err| {
+        match syn::parse2::<syn::ForeignItemFn>(toks) {
+            Ok(decl) => syn::Error::new_spanned(
+                decl.sig,
+                "contracts can only be checked on functions with a body, add them to \
+                 a wrapper function or use `#[contract_trait]` for trait methods",
+            ),
+            Err(_) => err,
+        }
+    })
+}
+
+/// Generate the contracts of a function, or of a closure bound by a `let`
+/// statement.
+pub(crate) fn generate_function(
+    toks: TokenStream,
+    generate: impl FnOnce(ItemFn) -> TokenStream,
+) -> TokenStream {
+    if let Some((local, func)) = closure::parse_closure(&toks) {
+        return closure::into_closure(local, generate(func));
+    }
+
+    match parse_function(toks.clone()) {
+        Ok(func) => generate(func),
+        Err(err) => emit_error(err, toks),
+    }
+}
+
 /// Checking-mode of a contract.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub(crate) enum ContractMode {
@@ -42,6 +99,9 @@ pub(crate) enum ContractMode {
     /// Check the contract and print information upon violation, but don't abort
     /// the program.
     LogOnly,
+    /// Check the contract and return an error from the function upon
+    /// violation.
+    ReturnError,
 }
 
 impl ContractMode {
@@ -53,14 +113,19 @@ impl ContractMode {
             ContractMode::Debug => Some("debug_"),
             ContractMode::Test => Some("test_"),
             ContractMode::LogOnly => None,
+            ContractMode::ReturnError => None,
         }
     }
 
     /// Computes the contract type based on feature flags.
     pub(crate) fn final_mode(self) -> Self {
         // disabled ones can't be "forced", test ones should stay test, no
-        // matter what.
-        if self == ContractMode::Disabled || self == ContractMode::Test {
+        // matter what. Returning an error is part of the function's behaviour,
+        // so it is never changed either.
+        if self == ContractMode::Disabled
+            || self == ContractMode::Test
+            || self == ContractMode::ReturnError
+        {
             return self;
         }
 
@@ -86,6 +151,14 @@ impl ContractMode {
 pub(crate) enum ContractType {
     Requires,
     Ensures,
+    /// Post-condition that is only checked if an `Ok` value is returned.
+    EnsuresOk,
+    /// Post-condition that is only checked if an `Err` value is returned.
+    EnsuresErr,
+    /// Post-condition that is only checked if a `Some` value is returned.
+    EnsuresSome,
+    /// Post-condition that is only checked if `None` is returned.
+    EnsuresNone,
     Invariant,
 }
 
@@ -96,10 +169,37 @@ impl ContractType {
         match self {
             ContractType::Requires => "Pre-condition",
             ContractType::Ensures => "Post-condition",
+            ContractType::EnsuresOk => "Post-condition (on `Ok`)",
+            ContractType::EnsuresErr => "Post-condition (on `Err`)",
+            ContractType::EnsuresSome => "Post-condition (on `Some`)",
+            ContractType::EnsuresNone => "Post-condition (on `None`)",
             ContractType::Invariant => "Invariant",
         }
     }
 
+    /// Whether the contract is checked after the function returned and can
+    /// refer to the return value.
+    pub(crate) fn is_post(self) -> bool {
+        matches!(
+            self,
+            ContractType::Ensures
+                | ContractType::EnsuresOk
+                | ContractType::EnsuresErr
+                | ContractType::EnsuresSome
+                | ContractType::EnsuresNone
+        )
+    }
+
+    /// Whether contracts of this type are turned off by one of the
+    /// `disable_pre`, `disable_post` or `disable_invariants` features.
+    pub(crate) fn is_disabled(self) -> bool {
+        match self {
+            ContractType::Requires => cfg!(feature = "disable_pre"),
+            ContractType::Invariant => cfg!(feature = "disable_invariants"),
+            _ => cfg!(feature = "disable_post"),
+        }
+    }
+
     /// Determine the type and mode of an identifier.
     pub(crate) fn contract_type_and_mode(ident: &str) -> Option<(ContractType, ContractMode)> {
         match ident {
@@ -112,11 +212,76 @@ impl ContractType {
             "test_requires" => Some((ContractType::Requires, ContractMode::Test)),
             "test_ensures" => Some((ContractType::Ensures, ContractMode::Test)),
             "test_invariant" => Some((ContractType::Invariant, ContractMode::Test)),
+            "requires_or_return" => Some((ContractType::Requires, ContractMode::ReturnError)),
+            "ensures_ok" => Some((ContractType::EnsuresOk, ContractMode::Always)),
+            "ensures_err" => Some((ContractType::EnsuresErr, ContractMode::Always)),
+            "ensures_some" => Some((ContractType::EnsuresSome, ContractMode::Always)),
+            "ensures_none" => Some((ContractType::EnsuresNone, ContractMode::Always)),
             _ => None,
         }
     }
 }
 
+/// Options of a contract given as `key = "value"` arguments or as flags.
+#[derive(Debug, Default)]
+pub(crate) struct ContractOptions {
+    /// Log level used when the contract is only logged.
+    pub(crate) log_level: Option<Ident>,
+    /// Log target used when the contract is only logged.
+    pub(crate) log_target: Option<syn::LitStr>,
+    /// Mode overriding the one of the attribute, along with the span of the
+    /// option.
+    pub(crate) mode: Option<(ContractMode, Span)>,
+    /// Cargo feature which has to be enabled for the contract to be checked.
+    pub(crate) cfg_feature: Option<syn::LitStr>,
+    /// Category which has to be enabled at runtime for the contract to be
+    /// checked.
+    pub(crate) category: Option<syn::LitStr>,
+    /// Name of a function to generate which evaluates the conditions.
+    pub(crate) checker: Option<Ident>,
+    /// Whether a function evaluating the conditions is generated with the
+    /// default name `__check_pre_<function>`.
+    pub(crate) default_checker: bool,
+    /// Name of a variant of the function to generate which doesn't check any
+    /// contracts.
+    pub(crate) unchecked: Option<Ident>,
+    /// Function formatting the messages of violations.
+    pub(crate) formatter: Option<syn::Path>,
+    /// Macro used instead of `assert!` to check the conditions.
+    pub(crate) via: Option<syn::Path>,
+    /// Function creating the payload violations panic with.
+    pub(crate) payload: Option<syn::Path>,
+    /// Function receiving the outcome of every evaluation of the conditions.
+    pub(crate) audit: Option<syn::Path>,
+    /// Function called with the violations before they are reported.
+    pub(crate) handler: Option<syn::Path>,
+    /// Whether the contract is left out of the generated documentation.
+    pub(crate) no_doc: bool,
+    /// Whether the contract keeps its mode, even if the features override the
+    /// modes of contracts.
+    pub(crate) force_always: bool,
+    /// Whether placeholders like `{x}` in the description are replaced by
+    /// the values of the variables.
+    pub(crate) interpolate: bool,
+    /// Whether the function isn't marked `#[track_caller]` for this
+    /// pre-condition.
+    pub(crate) no_track_caller: bool,
+    /// Points at which an invariant is checked, along with the span of the
+    /// option.
+    pub(crate) when: Option<(Checkpoints, Span)>,
+}
+
+/// Points of a function at which an invariant is checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Checkpoints {
+    /// Only before the function is run.
+    Entry,
+    /// Only after the function has run.
+    Exit,
+    /// Before and after the function is run.
+    Both,
+}
+
 /// Representation of a contract
 #[derive(Debug)]
 pub(crate) struct Contract {
@@ -126,11 +291,122 @@ pub(crate) struct Contract {
     pub(crate) assertions: Vec<Expr>,
     pub(crate) streams: Vec<TokenStream>,
     pub(crate) desc: Option<String>,
+    /// Error value returned on violation for `ReturnError` contracts.
+    pub(crate) fallback: Option<Expr>,
+    pub(crate) options: ContractOptions,
 }
 
 impl Contract {
+    /// Computes the mode of the contract based on feature flags. Contracts
+    /// with the `force_always` flag are only affected by `disable_contracts`
+    /// and the features disabling their type of contract.
+    pub(crate) fn final_mode(&self) -> ContractMode {
+        let mode = if self.options.force_always && !cfg!(feature = "disable_contracts") {
+            self.mode
+        } else {
+            self.mode.final_mode()
+        };
+
+        // like with `disable_contracts`, contracts returning an error or only
+        // checked in tests are kept
+        if self.ty.is_disabled() && mode != ContractMode::Test && mode != ContractMode::ReturnError
+        {
+            ContractMode::Disabled
+        } else {
+            mode
+        }
+    }
+
+    /// Whether the contract is checked before the function is run.
+    pub(crate) fn checked_on_entry(&self) -> bool {
+        match self.ty {
+            ContractType::Requires => true,
+            ContractType::Invariant => !matches!(self.options.when, Some((Checkpoints::Exit, _))),
+            _ => false,
+        }
+    }
+
+    /// Whether the contract is checked after the function has run.
+    pub(crate) fn checked_on_exit(&self) -> bool {
+        match self.ty {
+            ContractType::Requires => false,
+            ContractType::Invariant => !matches!(self.options.when, Some((Checkpoints::Entry, _))),
+            _ => true,
+        }
+    }
+
     pub(crate) fn from_toks(ty: ContractType, mode: ContractMode, toks: TokenStream) -> Self {
-        let (assertions, streams, desc) = parse::parse_attributes(toks);
+        let (binder, toks) = parse::parse_ret_binder(toks);
+
+        // post-conditions refer to the return value using `ret`, unless
+        // another name is given
+        let ret_binder = match (ty, binder) {
+            (ty, binder) if ty.is_post() => {
+                Some(binder.unwrap_or_else(|| Ident::new("ret", Span::call_site())))
+            }
+            (_, None) => None,
+            (_, Some(binder)) => {
+                let err = syn::Error::new(
+                    binder.span(),
+                    "naming the return value is only possible in post-conditions",
+                );
+
+                return Self {
+                    _span: Span::call_site(),
+                    ty,
+                    mode,
+                    assertions: vec![Expr::Verbatim(err.into_compile_error())],
+                    streams: vec![binder.into_token_stream()],
+                    desc: None,
+                    fallback: None,
+                    options: ContractOptions::default(),
+                };
+            }
+        };
+
+        // the error value is the last argument
+        let (toks, fallback) = if mode == ContractMode::ReturnError {
+            let (toks, fallback) = parse::split_last_argument(toks);
+            let fallback = match fallback.map(syn::parse2::<Expr>) {
+                Some(Ok(fallback)) => fallback,
+                Some(Err(err)) => Expr::Verbatim(err.into_compile_error()),
+                None => {
+                    let err = syn::Error::new(
+                        Span::call_site(),
+                        "expected an error value as the last argument",
+                    );
+                    Expr::Verbatim(err.into_compile_error())
+                }
+            };
+
+            (toks, Some(fallback))
+        } else {
+            (toks, None)
+        };
+
+        let (mut assertions, mut streams, desc, options) =
+            parse::parse_attributes(toks, ret_binder.as_ref());
+
+        // the error value can't be used when the mode is changed
+        let mode = match options.mode {
+            Some((_, span)) if mode == ContractMode::ReturnError => {
+                let err = syn::Error::new(
+                    span,
+                    "the mode of a contract returning an error can't be changed",
+                );
+                assertions.push(Expr::Verbatim(err.into_compile_error()));
+                streams.push(TokenStream::new());
+                mode
+            }
+            Some((option_mode, _)) => option_mode,
+            None => mode,
+        };
+
+        if let (Some((_, span)), false) = (options.when, ty == ContractType::Invariant) {
+            let err = syn::Error::new(span, "`when` is only available for invariants");
+            assertions.push(Expr::Verbatim(err.into_compile_error()));
+            streams.push(TokenStream::new());
+        }
 
         let span = Span::call_site();
 
@@ -141,15 +417,47 @@ impl Contract {
             assertions,
             streams,
             desc,
+            fallback,
+            options,
         }
     }
 }
 
+/// Name of a hidden attribute marking methods of traits and trait
+/// implementations, which are part of the public API of the trait even though
+/// they have no visibility.
+const TRAIT_METHOD_MARKER: &str = "__contracts_trait_method";
+
+/// Mark a method of a trait or a trait implementation, so `public_only` keeps
+/// its contracts. The marker is removed by the expansion of the contracts, so
+/// only methods with contracts are marked.
+pub(crate) fn mark_trait_method(attrs: &mut Vec<Attribute>) {
+    if !cfg!(feature = "public_only") {
+        return;
+    }
+
+    let has_contracts = attrs.iter().any(|attr| {
+        let name = attr.path().segments.last().unwrap().ident.to_string();
+        name == "contract" || ContractType::contract_type_and_mode(&name).is_some()
+    });
+    let marked = attrs
+        .iter()
+        .any(|attr| attr.path().is_ident(TRAIT_METHOD_MARKER));
+
+    if has_contracts && !marked {
+        let marker = Ident::new(TRAIT_METHOD_MARKER, Span::call_site());
+        attrs.push(syn::parse_quote!(#[#marker]));
+    }
+}
+
 /// A function that is annotated with contracts
 #[derive(Debug)]
 pub(crate) struct FuncWithContracts {
     pub(crate) contracts: Vec<Contract>,
     pub(crate) function: ItemFn,
+    /// Whether the function is a method of a trait or a trait
+    /// implementation.
+    pub(crate) trait_method: bool,
 }
 
 impl FuncWithContracts {
@@ -222,17 +530,79 @@ impl FuncWithContracts {
             func.attrs = other_attrs;
         }
 
+        let trait_method = func
+            .attrs
+            .iter()
+            .any(|attr| attr.path().is_ident(TRAIT_METHOD_MARKER));
+        func.attrs
+            .retain(|attr| !attr.path().is_ident(TRAIT_METHOD_MARKER));
+
         Self {
             function: func,
             contracts,
+            trait_method,
         }
     }
 
     /// Generates the resulting tokens including all contract-checks
     pub(crate) fn generate(mut self) -> TokenStream {
+        // `#[contract_test]` reads the contracts from the attributes of the
+        // function, which are gone once they are expanded
+        let test_attr = self.function.attrs.iter().position(|attr| {
+            attr.path()
+                .segments
+                .last()
+                .is_some_and(|seg| seg.ident == "contract_test")
+        });
+        if let Some(idx) = test_attr {
+            let attr = self.function.attrs.remove(idx);
+            let err = syn::Error::new_spanned(
+                attr,
+                "`#[contract_test]` has to be placed above the contracts of the function",
+            );
+            return emit_error(err, self.function);
+        }
+
+        // only public functions are checked with `public_only`. Methods of
+        // traits are as public as the trait, and `pub(crate)` functions
+        // aren't part of the public API. Like with `disable_contracts`,
+        // contracts returning an error or only checked in tests are kept.
+        let public = self.trait_method || matches!(self.function.vis, Visibility::Public(_));
+        if cfg!(feature = "public_only") && !public {
+            for contract in &mut self.contracts {
+                if contract.mode != ContractMode::Test && contract.mode != ContractMode::ReturnError
+                {
+                    contract.mode = ContractMode::Disabled;
+                }
+            }
+        }
+
+        // descriptions can refer to the name of the function as `{fn}`
+        let func_name = self.function.sig.ident.to_string();
+        for contract in &mut self.contracts {
+            if let Some(desc) = &mut contract.desc {
+                *desc = codegen::with_fn_name(desc, &func_name, contract.options.interpolate);
+            }
+        }
+
+        let mut checkers = self
+            .contracts
+            .iter()
+            .filter_map(|contract| codegen::condition_checker(&self.function, contract))
+            .collect::<TokenStream>();
+
+        checkers.extend(
+            self.contracts
+                .iter()
+                .filter_map(|contract| contract.options.unchecked.as_ref())
+                .map(|name| codegen::unchecked_variant(&self.function, name)),
+        );
+
         let doc_attrs = doc::generate_attributes(&self.contracts);
         let olds = codegen::extract_old_calls(&mut self.contracts);
 
-        codegen::generate(self, doc_attrs, olds)
+        let mut toks = codegen::generate(self, doc_attrs, olds);
+        toks.extend(checkers);
+        toks
     }
 }
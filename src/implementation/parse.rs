@@ -2,53 +2,296 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use proc_macro2::{Spacing, TokenStream, TokenTree};
-use syn::{Expr, ExprLit, Lit};
+use proc_macro2::{Delimiter, Group, Ident, Spacing, TokenStream, TokenTree};
+use syn::{
+    visit_mut::{self, VisitMut},
+    BinOp, Expr, ExprAssign, ExprLit, Lit,
+};
 
-/// Parse attributes into a list of expression and an optional description of
-/// the assert
+use crate::implementation::{ret_binding, Checkpoints, ContractMode, ContractOptions};
+
+/// Parse attributes into a list of expression, an optional description of
+/// the assert and the options given as `key = "value"` arguments or as the
+/// `no_doc`, `force_always`, `interpolate`, `checker` and `no_track_caller`
+/// flags.
+///
+/// If a `ret_binder` is given, all uses of that identifier are renamed to refer
+/// to the binding of the return value.
 pub(crate) fn parse_attributes(
     attrs: TokenStream,
-) -> (Vec<Expr>, Vec<TokenStream>, Option<String>) {
+    ret_binder: Option<&Ident>,
+) -> (Vec<Expr>, Vec<TokenStream>, Option<String>, ContractOptions) {
+    let (attrs, no_doc) = take_flag(attrs, "no_doc");
+    let (attrs, force_always) = take_flag(attrs, "force_always");
+    let (attrs, interpolate) = take_flag(attrs, "interpolate");
+    let (attrs, default_checker) = take_flag(attrs, "checker");
+    let (attrs, no_track_caller) = take_flag(attrs, "no_track_caller");
     let segments = segment_input(attrs);
-    let mut segments_stream: Vec<TokenStream> = segments
-        .iter()
-        .map(|x| x.iter().cloned().collect::<TokenStream>())
-        .collect();
-    let rewritten_segs: Vec<_> = segments.into_iter().map(rewrite).collect();
 
     let mut conds: Vec<Expr> = vec![];
+    let mut segments_stream: Vec<TokenStream> = vec![];
+    let mut options = ContractOptions {
+        no_doc,
+        force_always,
+        interpolate,
+        default_checker,
+        no_track_caller,
+        ..ContractOptions::default()
+    };
 
-    let last_segment_idx = rewritten_segs.len().saturating_sub(1);
+    for seg in segments {
+        let stream = seg.iter().cloned().collect::<TokenStream>();
 
-    for (idx, seg) in rewritten_segs.into_iter().enumerate() {
-        let expr = match syn::parse2::<Expr>(seg) {
+        let seg = match ret_binder {
+            Some(binder) => rename_ret(seg, binder),
+            None => seg,
+        };
+
+        let expr = match syn::parse2::<Expr>(rewrite(seg)) {
             Ok(val) => val,
-            Err(err) => Expr::Verbatim(err.to_compile_error()),
+            Err(err) => Expr::Verbatim(respan_end_of_input(err, &stream).to_compile_error()),
         };
 
-        if idx != last_segment_idx && string_lit_value(&expr).is_some() {
-            let err =
-                syn::Error::new_spanned(expr, "contract description must be the last argument");
-            conds.push(Expr::Verbatim(err.to_compile_error()));
+        // assignments can't be valid conditions, so they are used for options
+        if let Expr::Assign(assign) = &expr {
+            if let Err(err) = parse_option(assign, &mut options) {
+                conds.push(Expr::Verbatim(err.to_compile_error()));
+                segments_stream.push(stream);
+            }
             continue;
         }
 
         conds.push(expr);
+        segments_stream.push(stream);
     }
 
-    let desc = conds.last().and_then(string_lit_value);
+    // all trailing string literals make up the description, so a long one can
+    // be split up
+    let desc_len = conds
+        .iter()
+        .rev()
+        .take_while(|cond| string_lit_value(cond).is_some())
+        .count();
+    let desc_start = conds.len() - desc_len;
 
-    if desc.is_some() {
-        conds.pop();
-        segments_stream.pop();
+    for cond in &mut conds[..desc_start] {
+        if string_lit_value(cond).is_some() {
+            let err =
+                syn::Error::new_spanned(&*cond, "contract description must be the last argument");
+            *cond = Expr::Verbatim(err.to_compile_error());
+        }
     }
 
+    let desc = if desc_len > 0 {
+        let parts = conds[desc_start..]
+            .iter()
+            .filter_map(string_lit_value)
+            .collect::<Vec<_>>();
+        Some(parts.join(" "))
+    } else {
+        None
+    };
+
+    conds.truncate(desc_start);
+    segments_stream.truncate(desc_start);
+
     for cond in &mut conds {
         error_on_false_literal(cond);
+
+        if cfg!(feature = "strict_purity") {
+            reject_side_effects(cond);
+        }
+    }
+
+    (conds, segments_stream, desc, options)
+}
+
+/// Errors at the end of the input of a condition would point at the whole
+/// attribute, so they are moved to the incomplete condition instead.
+fn respan_end_of_input(err: syn::Error, condition: &TokenStream) -> syn::Error {
+    if condition.is_empty() || !err.to_string().starts_with("unexpected end of input") {
+        return err;
+    }
+
+    syn::Error::new_spanned(condition, err.to_string())
+}
+
+/// Parse a `key = "value"` option of a contract.
+fn parse_option(assign: &ExprAssign, options: &mut ContractOptions) -> syn::Result<()> {
+    let key = match &*assign.left {
+        Expr::Path(path) => path.path.get_ident(),
+        _ => None,
+    }
+    .ok_or_else(|| syn::Error::new_spanned(&assign.left, "expected a contract option name"))?;
+
+    let value = match &*assign.right {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(str), ..
+        }) => str,
+        value => {
+            return Err(syn::Error::new_spanned(
+                value,
+                format!("expected a string literal as value of `{}`", key),
+            ))
+        }
+    };
+
+    match key.to_string().as_str() {
+        "level" => {
+            const LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+            if !LEVELS.contains(&value.value().as_str()) {
+                return Err(syn::Error::new_spanned(
+                    value,
+                    format!("expected one of the log levels {}", LEVELS.join(", ")),
+                ));
+            }
+
+            options.log_level = Some(Ident::new(&value.value(), value.span()));
+        }
+        "target" => options.log_target = Some(value.clone()),
+        "cfg" => options.cfg_feature = Some(value.clone()),
+        "category" => options.category = Some(value.clone()),
+        "checker" => {
+            let name = value.parse::<Ident>().map_err(|_| {
+                syn::Error::new_spanned(value, "expected a function name as checker")
+            })?;
+            options.checker = Some(name);
+        }
+        "formatter" => {
+            let path = value.parse::<syn::Path>().map_err(|_| {
+                syn::Error::new_spanned(value, "expected the path of a function as formatter")
+            })?;
+            options.formatter = Some(path);
+        }
+        "via" => {
+            let path = value.parse::<syn::Path>().map_err(|_| {
+                syn::Error::new_spanned(value, "expected the path of an assertion macro")
+            })?;
+            options.via = Some(path);
+        }
+        "payload" => {
+            let path = value.parse::<syn::Path>().map_err(|_| {
+                syn::Error::new_spanned(value, "expected the path of a function as payload")
+            })?;
+            options.payload = Some(path);
+        }
+        "audit" => {
+            let path = value.parse::<syn::Path>().map_err(|_| {
+                syn::Error::new_spanned(value, "expected the path of a function as audit sink")
+            })?;
+            options.audit = Some(path);
+        }
+        "handler" => {
+            let path = value.parse::<syn::Path>().map_err(|_| {
+                syn::Error::new_spanned(value, "expected the path of a function as handler")
+            })?;
+            options.handler = Some(path);
+        }
+        "unchecked" => {
+            let name = value.parse::<Ident>().map_err(|_| {
+                syn::Error::new_spanned(value, "expected a function name as unchecked variant")
+            })?;
+            options.unchecked = Some(name);
+        }
+        "mode" => {
+            let mode = match value.value().as_str() {
+                "always" => ContractMode::Always,
+                "debug" => ContractMode::Debug,
+                "test" => ContractMode::Test,
+                "log" => ContractMode::LogOnly,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        "expected one of the modes always, debug, test, log",
+                    ))
+                }
+            };
+
+            options.mode = Some((mode, value.span()));
+        }
+        "when" => {
+            let when = match value.value().as_str() {
+                "entry" => Checkpoints::Entry,
+                "exit" => Checkpoints::Exit,
+                "both" => Checkpoints::Both,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        "expected one of entry, exit, both",
+                    ))
+                }
+            };
+
+            options.when = Some((when, value.span()));
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                key,
+                format!("unknown contract option `{}`", key),
+            ))
+        }
     }
 
-    (conds, segments_stream, desc)
+    Ok(())
+}
+
+/// Split off a leading `@name:` which names the binding of the return value.
+pub(crate) fn parse_ret_binder(attrs: TokenStream) -> (Option<Ident>, TokenStream) {
+    let tokens: Vec<TokenTree> = attrs.into_iter().collect();
+
+    match tokens.as_slice() {
+        [TokenTree::Punct(at), TokenTree::Ident(name), TokenTree::Punct(colon), rest @ ..]
+            if at.as_char() == '@'
+                && colon.as_char() == ':'
+                && colon.spacing() == Spacing::Alone =>
+        {
+            (Some(name.clone()), rest.iter().cloned().collect())
+        }
+        _ => (None, tokens.into_iter().collect()),
+    }
+}
+
+// Renames all uses of the identifier `from` to the return value binding,
+// keeping the original locations. Identifiers directly following a `.` or
+// `::` are field, method or path segment names and are left alone.
+fn rename_ret(tokens: Vec<TokenTree>, from: &Ident) -> Vec<TokenTree> {
+    let mut renamed = Vec::with_capacity(tokens.len());
+
+    for tt in tokens {
+        // a single `:`, like the one of a struct literal field, is followed
+        // by an expression
+        let is_member = match renamed.as_slice() {
+            [.., TokenTree::Punct(first), TokenTree::Punct(second)]
+                if first.as_char() == ':'
+                    && first.spacing() == Spacing::Joint
+                    && second.as_char() == ':' =>
+            {
+                true
+            }
+            [.., TokenTree::Punct(p)] => p.as_char() == '.',
+            _ => false,
+        };
+
+        let tt = match tt {
+            TokenTree::Ident(ident) if ident == *from && !is_member => {
+                TokenTree::Ident(ret_binding(ident.span()))
+            }
+            TokenTree::Group(group) => {
+                let stream = rename_ret(group.stream().into_iter().collect(), from);
+
+                let mut new_group = Group::new(group.delimiter(), stream.into_iter().collect());
+                new_group.set_span(group.span());
+
+                TokenTree::Group(new_group)
+            }
+            tt => tt,
+        };
+
+        renamed.push(tt);
+    }
+
+    renamed
 }
 
 fn string_lit_value(expr: &Expr) -> Option<String> {
@@ -77,21 +320,329 @@ fn error_on_false_literal(expr: &mut Expr) {
     *expr = Expr::Verbatim(err.into_compile_error());
 }
 
-// This function rewrites a list of TokenTrees so that the "pseudooperator" for
-// implication `==>` gets transformed into an `if` expression.
+/// Methods of the standard library that mutate their receiver.
+const MUTATING_METHODS: &[&str] = &[
+    "append",
+    "clear",
+    "dedup",
+    "drain",
+    "extend",
+    "insert",
+    "pop",
+    "pop_back",
+    "pop_front",
+    "push",
+    "push_back",
+    "push_front",
+    "push_str",
+    "remove",
+    "retain",
+    "reverse",
+    "sort",
+    "sort_unstable",
+    "swap",
+    "truncate",
+];
+
+/// Report obvious side effects in a contract, like assignments, `&mut`
+/// borrows and calls to mutating methods. Checks should behave the same
+/// whether contracts are enabled or not.
+///
+/// This is best-effort only, as calls to other functions can't be checked.
+fn reject_side_effects(expr: &mut Expr) {
+    struct SideEffectRejector;
+
+    impl VisitMut for SideEffectRejector {
+        fn visit_expr_mut(&mut self, expr: &mut Expr) {
+            let effect = match &*expr {
+                Expr::Assign(_) => Some("an assignment".to_string()),
+                Expr::Binary(binary) if is_compound_assignment(&binary.op) => {
+                    Some("an assignment".to_string())
+                }
+                Expr::Reference(reference) if reference.mutability.is_some() => {
+                    Some("a mutable borrow".to_string())
+                }
+                Expr::MethodCall(call)
+                    if MUTATING_METHODS.contains(&call.method.to_string().as_str()) =>
+                {
+                    Some(format!("a call to the mutating method `{}`", call.method))
+                }
+                _ => None,
+            };
+
+            if let Some(effect) = effect {
+                let msg = format!("contracts must be free of side effects, found {}", effect);
+                let err = syn::Error::new_spanned(&*expr, msg);
+                *expr = Expr::Verbatim(err.into_compile_error());
+                return;
+            }
+
+            visit_mut::visit_expr_mut(self, expr);
+        }
+    }
+
+    fn is_compound_assignment(op: &BinOp) -> bool {
+        matches!(
+            op,
+            BinOp::AddAssign(_)
+                | BinOp::SubAssign(_)
+                | BinOp::MulAssign(_)
+                | BinOp::DivAssign(_)
+                | BinOp::RemAssign(_)
+                | BinOp::BitXorAssign(_)
+                | BinOp::BitAndAssign(_)
+                | BinOp::BitOrAssign(_)
+                | BinOp::ShlAssign(_)
+                | BinOp::ShrAssign(_)
+        )
+    }
+
+    SideEffectRejector.visit_expr_mut(expr);
+}
+
+/// Custom logical operators that can be used in contract expressions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Operator {
+    /// Implication `->`, also accepted as `==>`
+    Implies,
+    /// Reverse implication `<==`
+    ImpliedBy,
+    /// Biconditional `<==>`
+    Iff,
+}
+
+impl Operator {
+    /// Detect an operator starting at `idx`, returning it along with the
+    /// number of tokens it consists of.
+    fn at(segments: &[TokenTree], idx: usize) -> Option<(Self, usize)> {
+        let punct = |idx: usize, c: char, s: Spacing| -> bool {
+            if let Some(TokenTree::Punct(p)) = segments.get(idx) {
+                p.as_char() == c && p.spacing() == s
+            } else {
+                false
+            }
+        };
+
+        if punct(idx, '<', Spacing::Joint)
+            && punct(idx + 1, '=', Spacing::Joint)
+            && punct(idx + 2, '=', Spacing::Joint)
+            && punct(idx + 3, '>', Spacing::Alone)
+        {
+            Some((Operator::Iff, 4))
+        } else if punct(idx, '<', Spacing::Joint)
+            && punct(idx + 1, '=', Spacing::Joint)
+            && punct(idx + 2, '=', Spacing::Alone)
+        {
+            // the trailing `=` has to be alone so this can't be confused with
+            // a `<=` comparison
+            Some((Operator::ImpliedBy, 3))
+        } else if punct(idx, '=', Spacing::Joint)
+            && punct(idx + 1, '=', Spacing::Joint)
+            && punct(idx + 2, '>', Spacing::Alone)
+        {
+            Some((Operator::Implies, 3))
+        } else if punct(idx, '-', Spacing::Joint)
+            && punct(idx + 1, '>', Spacing::Alone)
+            && !Self::is_return_arrow(segments, idx)
+        {
+            Some((Operator::Implies, 2))
+        } else {
+            None
+        }
+    }
+
+    /// Whether the `->` at `idx` denotes a return type instead of an
+    /// implication, as in `|x| -> bool { x }` or `fn(u8) -> u8`.
+    fn is_return_arrow(segments: &[TokenTree], idx: usize) -> bool {
+        if idx == 0 {
+            return false;
+        }
+
+        match &segments[idx - 1] {
+            // an expression can't end with a `|`, so this has to be the
+            // end of a closure parameter list
+            TokenTree::Punct(p) => p.as_char() == '|',
+            TokenTree::Group(g) if g.delimiter() == Delimiter::Parenthesis && idx >= 2 => {
+                match &segments[idx - 2] {
+                    TokenTree::Ident(ident) => {
+                        ident == "fn" || ident == "Fn" || ident == "FnMut" || ident == "FnOnce"
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Operator::Implies => "->",
+            Operator::ImpliedBy => "<==",
+            Operator::Iff => "<==>",
+        }
+    }
+}
+
+// This function rewrites a list of TokenTrees so that the "pseudooperators" for
+// implication `->` (or `==>`), reverse implication `<==` and biconditional `<==>` get
+// transformed into boolean expressions.
 //
 // This has to happen on a TokenStream/Tree because it's not possible to easily
 // add new syntax to the syn parsers without basically re-writing the whole
 // expression parsing functions from scratch.
 //
-// The input gets classified into "before `==>` op" and "after `==>` op". Those
-// two groups are then used to create an `if` that has implication semantics.
-// However, because the input is only split based on the operator, no precedence
-// is respected, including keywords such as `if`. This means the implication
-// operator should only be used in grouped expressions.
-// This also has the effect that implication is right-associative, which is the
-// expected behaviour.
+// The input gets classified into "before op" and "after op". Those two groups
+// are then used to create an expression that has the semantics of the
+// operator. However, because the input is only split based on the operator, no
+// precedence is respected, including keywords such as `if`. This means the
+// operators should only be used in grouped expressions.
+// This also has the effect that the operators are right-associative, which is
+// the expected behaviour for implication. Mixing different operators without
+// explicit grouping is rejected, as the result would likely be surprising.
+// A `->` following a closure parameter list or `fn(...)` denotes a return type
+// and is left untouched.
+// Operators in the condition of `if`, `while` and `match` expressions and in
+// the statements of blocks and arms of `match` expressions are rewritten on
+// their own.
 fn rewrite(segments: Vec<TokenTree>) -> proc_macro2::TokenStream {
+    rewrite_operators(segments).0
+}
+
+// Rewrites the contents of a block, where operators only apply within a
+// single statement. For `let` statements only the initializer is rewritten.
+fn rewrite_statements(segments: Vec<TokenTree>) -> TokenStream {
+    fn rewrite_statement(mut statement: Vec<TokenTree>) -> TokenStream {
+        let is_let = matches!(statement.first(), Some(TokenTree::Ident(i)) if i == "let");
+
+        // find the `=` of the statement, which is not part of `==`, `<=`, ...
+        let mut assign = None;
+        let mut joint = false;
+
+        for (idx, tt) in statement.iter().enumerate() {
+            if let TokenTree::Punct(p) = tt {
+                if p.as_char() == '=' && p.spacing() == Spacing::Alone && !joint {
+                    assign = Some(idx);
+                    break;
+                }
+
+                joint = p.spacing() == Spacing::Joint;
+            } else {
+                joint = false;
+            }
+        }
+
+        match assign {
+            Some(assign) if is_let => {
+                let init = statement.split_off(assign + 1);
+                let mut result: TokenStream = statement.into_iter().collect();
+                result.extend(rewrite(init));
+                result
+            }
+            _ => rewrite(statement),
+        }
+    }
+
+    let mut result = TokenStream::new();
+    let mut statement = vec![];
+
+    for tt in segments {
+        match &tt {
+            TokenTree::Punct(p) if p.as_char() == ';' => {
+                result.extend(rewrite_statement(std::mem::take(&mut statement)));
+                result.extend(Some(tt));
+            }
+            _ => statement.push(tt),
+        }
+    }
+
+    result.extend(rewrite_statement(statement));
+
+    result
+}
+
+// Rewrites the arms of a `match` expression, where operators only apply within
+// the guard or the body of a single arm. An arm ends at a comma or, if its body
+// is a block, right after the block.
+fn rewrite_match_arms(segments: Vec<TokenTree>) -> TokenStream {
+    fn rewrite_arm(mut arm: Vec<TokenTree>, arrow: usize) -> TokenStream {
+        let body = arm.split_off(arrow + 2);
+        let fat_arrow = arm.split_off(arrow);
+
+        let mut result = TokenStream::new();
+
+        match arm
+            .iter()
+            .position(|tt| matches!(tt, TokenTree::Ident(i) if i == "if"))
+        {
+            Some(guard) => {
+                let condition = arm.split_off(guard + 1);
+                result.extend(arm);
+                result.extend(rewrite(condition));
+            }
+            None => result.extend(arm),
+        }
+
+        result.extend(fat_arrow);
+        result.extend(rewrite(body));
+        result
+    }
+
+    let is_punct = |tt: &TokenTree, c, spacing| matches!(tt, TokenTree::Punct(p) if p.as_char() == c && p.spacing() == spacing);
+
+    let mut result = TokenStream::new();
+    let mut arm = vec![];
+    let mut arrow = None;
+
+    for (idx, tt) in segments.iter().enumerate() {
+        match arrow {
+            None => {
+                // a `=>` which is not the end of `==>` or `<==>`
+                let is_arrow = is_punct(tt, '=', Spacing::Joint)
+                    && segments
+                        .get(idx + 1)
+                        .is_some_and(|next| is_punct(next, '>', Spacing::Alone))
+                    && !arm
+                        .last()
+                        .is_some_and(|prev| is_punct(prev, '=', Spacing::Joint));
+
+                if is_arrow {
+                    arrow = Some(arm.len());
+                }
+
+                arm.push(tt.clone());
+            }
+            Some(start) => {
+                if is_punct(tt, ',', Spacing::Alone) {
+                    result.extend(rewrite_arm(std::mem::take(&mut arm), start));
+                    result.extend(Some(tt.clone()));
+                    arrow = None;
+                    continue;
+                }
+
+                arm.push(tt.clone());
+
+                let is_block_body = arm.len() == start + 3
+                    && matches!(tt, TokenTree::Group(g) if g.delimiter() == Delimiter::Brace);
+
+                if is_block_body {
+                    result.extend(rewrite_arm(std::mem::take(&mut arm), start));
+                    arrow = None;
+                }
+            }
+        }
+    }
+
+    match arrow {
+        Some(start) => result.extend(rewrite_arm(arm, start)),
+        None => result.extend(arm),
+    }
+
+    result
+}
+
+// Performs the rewrite of `rewrite`, additionally returning the top-level
+// operator found in `segments`.
+fn rewrite_operators(segments: Vec<TokenTree>) -> (TokenStream, Option<Operator>) {
     let mut lhs = vec![];
     let mut rhs: Option<_> = None;
     let mut span: Option<_> = None;
@@ -103,7 +654,11 @@ fn rewrite(segments: Vec<TokenTree>) -> proc_macro2::TokenStream {
             TokenTree::Group(group) => {
                 let stream: Vec<_> = group.stream().into_iter().collect();
 
-                let new_stream: TokenStream = rewrite(stream).into_iter().collect();
+                let new_stream: TokenStream = if group.delimiter() == Delimiter::Brace {
+                    rewrite_statements(stream)
+                } else {
+                    rewrite(stream)
+                };
 
                 let mut new_group = proc_macro2::Group::new(group.delimiter(), new_stream);
                 new_group.set_span(group.span());
@@ -111,7 +666,51 @@ fn rewrite(segments: Vec<TokenTree>) -> proc_macro2::TokenStream {
                 lhs.push(TokenTree::Group(new_group));
                 idx += 1;
             }
-            TokenTree::Ident(_) => {
+            TokenTree::Ident(ident) => {
+                // the condition of control flow expressions ends at the
+                // block, so operators in it are rewritten on their own
+                if ident == "if" || ident == "while" || ident == "match" {
+                    let block = segments[idx + 1..].iter().position(
+                        |tt| matches!(tt, TokenTree::Group(g) if g.delimiter() == Delimiter::Brace),
+                    );
+
+                    if let Some(block) = block {
+                        let end = idx + 1 + block;
+                        let condition = Vec::from(&segments[idx + 1..end]);
+
+                        lhs.push(tt.clone());
+                        lhs.extend(rewrite(condition));
+                        idx = end;
+
+                        // the arms of a `match` are separated by commas
+                        // instead of semicolons
+                        if let (TokenTree::Group(body), true) = (&segments[end], ident == "match") {
+                            let stream = body.stream().into_iter().collect();
+                            let mut new_group = proc_macro2::Group::new(
+                                body.delimiter(),
+                                rewrite_match_arms(stream),
+                            );
+                            new_group.set_span(body.span());
+
+                            lhs.push(TokenTree::Group(new_group));
+                            idx += 1;
+                        }
+
+                        continue 'segment;
+                    }
+                }
+
+                if let Some(TokenTree::Group(group)) = segments.get(idx + 1) {
+                    let rewritten = rewrite_quantifier(ident, group)
+                        .or_else(|| rewrite_same_alloc(ident, group));
+
+                    if let Some(rewritten) = rewritten {
+                        lhs.extend(rewritten);
+                        idx += 2;
+                        continue 'segment;
+                    }
+                }
+
                 lhs.push(tt.clone());
                 idx += 1;
             }
@@ -120,27 +719,26 @@ fn rewrite(segments: Vec<TokenTree>) -> proc_macro2::TokenStream {
                 idx += 1;
             }
             TokenTree::Punct(_) => {
-                let punct = |idx: usize, c: char, s: Spacing| -> bool {
-                    let tt = if let Some(val) = segments.get(idx) {
-                        val
-                    } else {
-                        return false;
-                    };
-
-                    if let TokenTree::Punct(p) = tt {
-                        p.as_char() == c && p.spacing() == s
-                    } else {
-                        false
+                if let Some((op, len)) = Operator::at(&segments, idx) {
+                    // found an operator
+                    let rest = Vec::from(&segments[idx + len..]);
+
+                    if lhs.is_empty() || rest.is_empty() {
+                        // report the operator as written, which may be an
+                        // alternative spelling
+                        let written = segments[idx..idx + len]
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<String>();
+                        let msg = format!("expected an expression on both sides of `{}`", written);
+                        let err = syn::Error::new(segments[idx].span(), msg);
+                        return (err.into_compile_error(), Some(op));
                     }
-                };
 
-                if punct(idx, '-', Spacing::Joint) && punct(idx + 1, '>', Spacing::Alone) {
-                    // found the implication
-                    let rest = Vec::from(&segments[idx + 2..]);
-                    let rhs_stream = rewrite(rest);
+                    let rhs_stream = rewrite_operators(rest);
 
-                    rhs = Some(rhs_stream);
-                    span = Some(segments[idx + 1].span());
+                    rhs = Some((op, rhs_stream));
+                    span = Some(segments[idx + len - 1].span());
                     break 'segment;
                 } else {
                     // consume all so that =========> would not match with
@@ -172,20 +770,230 @@ fn rewrite(segments: Vec<TokenTree>) -> proc_macro2::TokenStream {
     }
 
     match (rhs, span) {
-        (None, None) => lhs.into_iter().collect(),
+        (None, None) => (lhs.into_iter().collect(), None),
         (None, Some(_)) => {
-            unreachable!("If there's a span there should be an implication")
+            unreachable!("If there's a span there should be an operator")
         }
         (Some(_), None) => unreachable!("Invalid spans"),
-        (Some(rhs), Some(span)) => {
+        (Some((op, (rhs, rhs_op))), Some(span)) => {
+            if let Some(rhs_op) = rhs_op.filter(|rhs_op| *rhs_op != op) {
+                let msg = format!(
+                    "mixing `{}` and `{}` requires explicit grouping using parentheses",
+                    op.symbol(),
+                    rhs_op.symbol()
+                );
+                return (syn::Error::new(span, msg).into_compile_error(), Some(op));
+            }
+
             let lhs: TokenStream = lhs.into_iter().collect();
 
-            quote::quote_spanned! {
-                span =>
-                (!(#lhs) || #rhs)
+            let toks = match op {
+                Operator::Implies => quote::quote_spanned! {
+                    span =>
+                    (!(#lhs) || #rhs)
+                },
+                Operator::ImpliedBy => quote::quote_spanned! {
+                    span =>
+                    (!(#rhs) || (#lhs))
+                },
+                Operator::Iff => quote::quote_spanned! {
+                    span =>
+                    ((#lhs) == (#rhs))
+                },
+            };
+
+            (toks, Some(op))
+        }
+    }
+}
+
+// This function rewrites the quantifier pseudo-functions
+// `forall(pat in iter, predicate)` and `exists(pat in iter, predicate)` into
+// iterator method calls.
+//
+// Just like the implication operator, the `pat in iter` part is not valid
+// expression syntax, so this has to happen before the tokens are handed to
+// syn. The predicate is rewritten recursively so it can contain implications
+// or nested quantifiers itself. The predicate closure is not a `move` closure,
+// so anything it references besides the bound pattern is only borrowed.
+// Iterables naming a place, like `v` or `self.items`, are borrowed as well,
+// so they can still be used in the rest of the contract. Other iterables,
+// like `v.iter()` or `0..n`, are temporaries which are consumed.
+//
+// Calls without a top-level `in` are left alone, so functions named `forall`
+// or `exists` can still be called.
+fn rewrite_quantifier(ident: &Ident, group: &Group) -> Option<TokenStream> {
+    let method = match ident.to_string().as_str() {
+        "forall" => Ident::new("all", ident.span()),
+        "exists" => Ident::new("any", ident.span()),
+        _ => return None,
+    };
+
+    if group.delimiter() != Delimiter::Parenthesis {
+        return None;
+    }
+
+    let span = ident.span();
+    let tokens: Vec<TokenTree> = group.stream().into_iter().collect();
+
+    let in_idx = tokens
+        .iter()
+        .position(|tt| matches!(tt, TokenTree::Ident(i) if i == "in"))?;
+    let comma_idx = tokens[in_idx..]
+        .iter()
+        .position(|tt| matches!(tt, TokenTree::Punct(p) if p.as_char() == ','))
+        .map(|pos| in_idx + pos);
+
+    // the pattern, the iterable and the predicate must not be empty
+    let comma_idx = match comma_idx {
+        Some(comma_idx) if in_idx > 0 && comma_idx > in_idx + 1 && comma_idx + 1 < tokens.len() => {
+            comma_idx
+        }
+        _ => {
+            let msg = format!("expected `{}(<pattern> in <iterable>, <predicate>)`", ident);
+            return Some(syn::Error::new(group.span(), msg).into_compile_error());
+        }
+    };
+
+    let pat: TokenStream = tokens[..in_idx].iter().cloned().collect();
+    let iter = rewrite(Vec::from(&tokens[in_idx + 1..comma_idx]));
+    let pred = rewrite(Vec::from(&tokens[comma_idx + 1..]));
+
+    // `into_iter` is called as a method, so references to collections are
+    // dereferenced until a reference implementing `IntoIterator` is found
+    let iter = if is_place(&iter) {
+        quote::quote_spanned! { span=>
+            {
+                #[allow(clippy::into_iter_on_ref)]
+                let __contracts_iter = (&#iter).into_iter();
+                __contracts_iter
             }
         }
+    } else {
+        quote::quote_spanned! { span=> ::core::iter::IntoIterator::into_iter(#iter) }
+    };
+
+    Some(quote::quote_spanned! { span=>
+        (#iter.#method(|#pat| #pred))
+    })
+}
+
+/// Whether the tokens are an expression naming a place, like a variable or a
+/// field, which can be borrowed instead of being moved.
+fn is_place(tokens: &TokenStream) -> bool {
+    fn place(expr: &Expr) -> bool {
+        match expr {
+            Expr::Path(_) => true,
+            Expr::Field(field) => place(&field.base),
+            Expr::Index(index) => place(&index.expr),
+            Expr::Paren(paren) => place(&paren.expr),
+            _ => false,
+        }
     }
+
+    syn::parse2::<Expr>(tokens.clone()).is_ok_and(|expr| place(&expr))
+}
+
+// This function rewrites the pseudo-function `same_alloc(v, old(v))` into a
+// comparison of the pointers returned by `as_ptr()`. Only the pointer is
+// captured by `old()`, so the value itself isn't moved or borrowed.
+fn rewrite_same_alloc(ident: &Ident, group: &Group) -> Option<TokenStream> {
+    if ident != "same_alloc" || group.delimiter() != Delimiter::Parenthesis {
+        return None;
+    }
+
+    let span = ident.span();
+    let args = segment_input(group.stream());
+
+    let (value, old_value) = match args.as_slice() {
+        [value, old_call] if !value.is_empty() => match old_call.as_slice() {
+            [TokenTree::Ident(old), TokenTree::Group(old_value)]
+                if old == "old" && old_value.delimiter() == Delimiter::Parenthesis =>
+            {
+                (rewrite(value.clone()), old_value.stream())
+            }
+            _ => return Some(same_alloc_error(group)),
+        },
+        _ => return Some(same_alloc_error(group)),
+    };
+
+    Some(quote::quote_spanned! { span=>
+        ((#value).as_ptr() == old((#old_value).as_ptr()))
+    })
+}
+
+fn same_alloc_error(group: &Group) -> TokenStream {
+    let msg = "expected `same_alloc(<value>, old(<value>))`";
+    syn::Error::new(group.span(), msg).into_compile_error()
+}
+
+/// Split off the last argument of an attribute.
+pub(crate) fn split_last_argument(attrs: TokenStream) -> (TokenStream, Option<TokenStream>) {
+    let mut segments = segment_input(attrs);
+    let last = segments
+        .pop()
+        .map(|seg| seg.into_iter().collect::<TokenStream>());
+
+    let mut rest = TokenStream::new();
+    for (idx, seg) in segments.into_iter().enumerate() {
+        if idx > 0 {
+            rest.extend(quote::quote!(,));
+        }
+        rest.extend(seg);
+    }
+
+    (rest, last)
+}
+
+/// Remove an argument consisting of only the identifier `flag`, returning
+/// whether it was present.
+pub(crate) fn take_flag(attrs: TokenStream, flag: &str) -> (TokenStream, bool) {
+    let mut found = false;
+
+    let segments = segment_input(attrs)
+        .into_iter()
+        .filter(|seg| match seg.as_slice() {
+            [TokenTree::Ident(ident)] if ident == flag => {
+                found = true;
+                false
+            }
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+
+    (join_segments(segments), found)
+}
+
+/// Remove a `key = value` argument, returning the tokens of its value.
+pub(crate) fn take_option(attrs: TokenStream, key: &str) -> (TokenStream, Option<TokenStream>) {
+    let mut value = None;
+
+    let segments = segment_input(attrs)
+        .into_iter()
+        .filter(|seg| match seg.as_slice() {
+            [TokenTree::Ident(ident), TokenTree::Punct(eq), rest @ ..]
+                if ident == key && eq.as_char() == '=' && eq.spacing() == Spacing::Alone =>
+            {
+                value = Some(rest.iter().cloned().collect());
+                false
+            }
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+
+    (join_segments(segments), value)
+}
+
+fn join_segments(segments: Vec<Vec<TokenTree>>) -> TokenStream {
+    let mut joined = TokenStream::new();
+    for (idx, seg) in segments.into_iter().enumerate() {
+        if idx > 0 {
+            joined.extend(quote::quote!(,));
+        }
+        joined.extend(seg);
+    }
+
+    joined
 }
 
 // The tokenstream can contain multiple expressions to be checked, separated by
@@ -211,3 +1019,349 @@ fn segment_input(tts: TokenStream) -> Vec<Vec<TokenTree>> {
 
     groups
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_attributes, reject_side_effects, rewrite, take_option};
+    use crate::implementation::{Checkpoints, ContractMode};
+    use syn::Expr;
+
+    #[test]
+    fn return_arrows_are_kept() {
+        let tokens = quote::quote! {
+            a -> v.iter().all(|x| -> bool { *x > 0 })
+        };
+
+        let expected = quote::quote! {
+            (!(a) || v.iter().all(|x| -> bool { *x > 0 }))
+        };
+
+        let rewritten = rewrite(tokens.into_iter().collect());
+
+        assert_eq!(rewritten.to_string(), expected.to_string());
+
+        let tokens = quote::quote! {
+            f as fn(u8) -> u8 == g -> (|| -> bool { true })()
+        };
+
+        let expected = quote::quote! {
+            (!(f as fn(u8) -> u8 == g) || (|| -> bool { true })())
+        };
+
+        let rewritten = rewrite(tokens.into_iter().collect());
+
+        assert_eq!(rewritten.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn implication_spellings() {
+        let arrow = quote::quote! {
+            a -> b -> (c == d)
+        };
+
+        // `quote!` splits `==>` into `==` and `>`, unlike the compiler
+        let long_arrow: proc_macro2::TokenStream = "a ==> b ==> (c == d)".parse().unwrap();
+
+        let rewritten_arrow = rewrite(arrow.into_iter().collect());
+        let rewritten_long_arrow = rewrite(long_arrow.into_iter().collect());
+
+        assert_eq!(
+            rewritten_arrow.to_string(),
+            rewritten_long_arrow.to_string()
+        );
+    }
+
+    #[test]
+    fn blocks_and_control_flow() {
+        let tokens = quote::quote! {
+            (x > 0) -> { let y = a -> b; y > 0 }
+        };
+
+        let expected = quote::quote! {
+            (!((x > 0)) || { let y = (!(a) || b); y > 0 })
+        };
+
+        let rewritten = rewrite(tokens.into_iter().collect());
+
+        assert_eq!(rewritten.to_string(), expected.to_string());
+
+        let tokens = quote::quote! {
+            if a -> b { c } else { d }
+        };
+
+        let expected = quote::quote! {
+            if (!(a) || b) { c } else { d }
+        };
+
+        let rewritten = rewrite(tokens.into_iter().collect());
+
+        assert_eq!(rewritten.to_string(), expected.to_string());
+
+        let tokens = quote::quote! {
+            match x { Some(y) if a -> b => y -> c, None => { d -> e } _ => f }
+        };
+
+        let expected = quote::quote! {
+            match x {
+                Some(y) if (!(a) || b) => (!(y) || c),
+                None => { (!(d) || e) }
+                _ => f
+            }
+        };
+
+        let rewritten = rewrite(tokens.into_iter().collect());
+
+        assert_eq!(rewritten.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn log_options() {
+        let attrs = quote::quote! {
+            x > 0, "x is positive", level = "warn", target = "app::db"
+        };
+
+        let (conds, streams, desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        assert_eq!(streams.len(), 1);
+        assert_eq!(desc.as_deref(), Some("x is positive"));
+        assert_eq!(options.log_level.unwrap().to_string(), "warn");
+        assert_eq!(options.log_target.unwrap().value(), "app::db");
+        assert!(options.cfg_feature.is_none());
+
+        let attrs = quote::quote! {
+            x > 0, cfg = "expensive_checks"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        assert_eq!(options.cfg_feature.unwrap().value(), "expensive_checks");
+
+        let attrs = quote::quote! {
+            x > 0, category = "security"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        assert_eq!(options.category.unwrap().value(), "security");
+
+        let attrs = quote::quote! {
+            x > 0, formatter = "messages::house_style"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        let formatter = options.formatter.unwrap();
+        assert_eq!(
+            quote::quote!(#formatter).to_string(),
+            "messages :: house_style"
+        );
+
+        let attrs = quote::quote! {
+            x > 0, via = "checks::my_assert"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        let via = options.via.unwrap();
+        assert_eq!(quote::quote!(#via).to_string(), "checks :: my_assert");
+
+        let attrs = quote::quote! {
+            x > 0, payload = "errors::Violation::new"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        let payload = options.payload.unwrap();
+        assert_eq!(
+            quote::quote!(#payload).to_string(),
+            "errors :: Violation :: new"
+        );
+
+        let attrs = quote::quote! {
+            x > 0, audit = "log::record"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        let audit = options.audit.unwrap();
+        assert_eq!(quote::quote!(#audit).to_string(), "log :: record");
+
+        let attrs = quote::quote! {
+            x > 0, handler = "report"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        let handler = options.handler.unwrap();
+        assert_eq!(quote::quote!(#handler).to_string(), "report");
+
+        let attrs = quote::quote! {
+            x > 0, checker = "is_positive"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        assert_eq!(options.checker.unwrap(), "is_positive");
+
+        let attrs = quote::quote! {
+            x > 0, checker = "is positive"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 2);
+        assert!(options.checker.is_none());
+
+        let attrs = quote::quote! {
+            x > 0, no_doc, "x is positive"
+        };
+
+        let (conds, _streams, desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        assert_eq!(desc.as_deref(), Some("x is positive"));
+        assert!(options.no_doc);
+    }
+
+    #[test]
+    fn trailing_descriptions() {
+        let attrs = quote::quote! {
+            x > 0, y > 0
+        };
+
+        let (conds, streams, desc, _options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 2);
+        assert_eq!(streams.len(), 2);
+        assert!(desc.is_none());
+
+        let attrs = quote::quote! {
+            x > 0, "x is positive"
+        };
+
+        let (conds, _streams, desc, _options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        assert_eq!(desc.as_deref(), Some("x is positive"));
+
+        let attrs = quote::quote! {
+            x > 0, "x is positive,", "as it is used as a divisor", level = "warn"
+        };
+
+        let (conds, streams, desc, _options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        assert_eq!(streams.len(), 1);
+        assert_eq!(
+            desc.as_deref(),
+            Some("x is positive, as it is used as a divisor")
+        );
+    }
+
+    #[test]
+    fn side_effects() {
+        let effects = [
+            quote::quote!(x.push(1) == ()),
+            quote::quote!({
+                count += 1;
+                count > 0
+            }),
+            quote::quote!(check(&mut x)),
+            quote::quote!(v.iter_mut().all(|x| {
+                *x = 0;
+                true
+            })),
+        ];
+
+        for toks in effects {
+            let mut expr = syn::parse2::<Expr>(toks).unwrap();
+            reject_side_effects(&mut expr);
+
+            assert!(quote::quote!(#expr)
+                .to_string()
+                .contains("contracts must be free of side effects"));
+        }
+
+        let mut expr = syn::parse2::<Expr>(quote::quote! {
+            v.iter().take(2).all(|x| *x > 0) && v.len() == n
+        })
+        .unwrap();
+        let original = expr.clone();
+        reject_side_effects(&mut expr);
+
+        assert_eq!(expr, original);
+    }
+
+    #[test]
+    fn mode_option() {
+        let attrs = quote::quote! {
+            x > 0, mode = "log"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        assert_eq!(
+            options.mode.map(|(mode, _)| mode),
+            Some(ContractMode::LogOnly)
+        );
+
+        let attrs = quote::quote! {
+            x > 0, mode = "sometimes"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 2);
+        assert!(options.mode.is_none());
+        assert!(quote::quote!(#(#conds)*)
+            .to_string()
+            .contains("expected one of the modes"));
+    }
+
+    #[test]
+    fn when_option() {
+        let attrs = quote::quote! {
+            x > 0, when = "exit"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 1);
+        assert_eq!(options.when.map(|(when, _)| when), Some(Checkpoints::Exit));
+
+        let (rest, value) = take_option(quote::quote!(x > 0, when = "entry"), "when");
+
+        assert_eq!(rest.to_string(), quote::quote!(x > 0).to_string());
+        assert_eq!(value.unwrap().to_string(), "\"entry\"");
+    }
+
+    #[test]
+    fn invalid_options() {
+        let attrs = quote::quote! {
+            x > 0, level = "loud", colour = "red"
+        };
+
+        let (conds, _streams, _desc, options) = parse_attributes(attrs, None);
+
+        assert_eq!(conds.len(), 3);
+        assert!(options.log_level.is_none());
+
+        let errors = conds[1..]
+            .iter()
+            .map(|cond| quote::quote!(#cond).to_string())
+            .collect::<Vec<_>>();
+
+        assert!(errors[0].contains("expected one of the log levels"));
+        assert!(errors[1].contains("unknown contract option `colour`"));
+    }
+}
@@ -0,0 +1,96 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::ToTokens;
+use syn::{visit_mut::visit_block_mut, FnArg, ReceiverKind, Type};
+
+use crate::implementation::{
+    codegen::{binding_return_type, ReturnReplacer},
+    emit_error, parse_function, ret_binding,
+};
+
+/// Name of the variable that holds the `Debug` representation of `self`.
+const SNAPSHOT_BINDING: &str = "__contracts_pure_snapshot";
+
+pub(crate) fn pure(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let mut func = match parse_function(toks.clone()) {
+        Ok(func) => func,
+        Err(err) => return emit_error(err, toks),
+    };
+
+    if !attr.is_empty() {
+        let err = syn::Error::new_spanned(attr, "`#[pure]` does not take any arguments");
+        return emit_error(err, func);
+    }
+
+    let mut borrows_self = false;
+
+    for input in &func.sig.inputs {
+        match input {
+            FnArg::Receiver(receiver) => match &receiver.kind {
+                ReceiverKind::Reference(_, _, Some(_)) => {
+                    let err = syn::Error::new_spanned(
+                        receiver,
+                        "pure functions can't take `self` by mutable reference",
+                    );
+                    return emit_error(err, func);
+                }
+                ReceiverKind::Reference(_, _, None) => borrows_self = true,
+                _ => {}
+            },
+            FnArg::Typed(arg) => {
+                if let Type::Reference(ty) = &*arg.ty {
+                    if ty.mutability.is_some() {
+                        let err = syn::Error::new_spanned(
+                            arg,
+                            "pure functions can't take mutable references",
+                        );
+                        return emit_error(err, func);
+                    }
+                }
+            }
+        }
+    }
+
+    // only a shared `self` can still be mutated, using interior mutability
+    if !borrows_self || cfg!(feature = "disable_contracts") {
+        return func.into_token_stream();
+    }
+
+    let func_name = func.sig.ident.to_string();
+    let message = format!("Pure function {} mutated `self`", func_name);
+
+    let ret_ident = ret_binding(Span::call_site());
+    let snapshot_ident = Ident::new(SNAPSHOT_BINDING, Span::call_site());
+
+    let mut block = func.block.clone();
+    let mut replacer = ReturnReplacer::new(&func.sig.output);
+    visit_block_mut(&mut replacer, &mut block);
+    let try_trait = replacer.try_trait();
+
+    let ret_type = binding_return_type(&func.sig.output).map(|ty| quote::quote!(: #ty));
+
+    let new_block = quote::quote! {
+        {
+            #try_trait
+
+            #[cfg(debug_assertions)]
+            let #snapshot_ident = ::std::format!("{:?}", self);
+
+            let #ret_ident #ret_type = '__contracts_run: #block;
+
+            #[cfg(debug_assertions)]
+            {
+                assert!(#snapshot_ident == ::std::format!("{:?}", self), #message);
+            }
+
+            #ret_ident
+        }
+    };
+
+    *func.block = syn::parse_quote!(#new_block);
+
+    func.into_token_stream()
+}
@@ -3,19 +3,13 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use proc_macro2::TokenStream;
-use syn::ItemFn;
 
-use crate::implementation::{emit_error, ContractMode, ContractType, FuncWithContracts};
+use crate::implementation::{generate_function, ContractMode, ContractType, FuncWithContracts};
 
 pub(crate) fn requires(mode: ContractMode, attr: TokenStream, toks: TokenStream) -> TokenStream {
     let ty = ContractType::Requires;
 
-    let func: ItemFn = match syn::parse2(toks.clone()) {
-        Ok(func) => func,
-        Err(err) => return emit_error(err, toks),
-    };
-
-    let f = FuncWithContracts::new_with_initial_contract(func, ty, mode, attr);
-
-    f.generate()
+    generate_function(toks, |func| {
+        FuncWithContracts::new_with_initial_contract(func, ty, mode, attr).generate()
+    })
 }
@@ -2,17 +2,45 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use proc_macro2::TokenStream;
+use proc_macro2::{Span, TokenStream};
 use quote::ToTokens;
-use syn::{FnArg, ImplItem, ItemImpl, ItemTrait, Pat, TraitItem, TraitItemFn};
+use syn::{
+    FnArg, GenericArgument, GenericParam, ImplItem, ImplItemFn, Item, ItemImpl, ItemTrait, Meta,
+    Pat, PathArguments, Receiver, ReceiverKind, TraitItem, TraitItemFn, Type,
+};
 
-use crate::implementation::ContractType;
+use crate::implementation::{doc, emit_error, mark_trait_method, Contract, ContractType};
 
 /// Name used for the "re-routed" method.
 fn contract_method_impl_name(name: &str) -> String {
     format!("__contracts_impl_{}", name)
 }
 
+/// Name of a hidden method required by traits with `#[contract_trait]`, which
+/// implementations with the attribute provide. If only one of them has the
+/// attribute, the compiler reports this method as missing or unknown.
+const MARKER_METHOD: &str = "__contracts_missing_contract_trait_attribute";
+
+pub(crate) fn contract_trait(attrs: TokenStream, toks: TokenStream) -> TokenStream {
+    let msg = "#[contract_trait] can only be applied to `trait` and `impl ... for` items";
+
+    let item = match syn::parse2::<Item>(toks.clone()) {
+        Ok(item) => item,
+        Err(err) => return emit_error(err, toks),
+    };
+
+    match item {
+        Item::Trait(trait_) => contract_trait_item_trait(attrs, trait_),
+        Item::Impl(impl_) if impl_.trait_.is_some() => contract_trait_item_impl(attrs, impl_),
+        Item::Impl(impl_) => {
+            // point at the type, where the trait is missing
+            let err = syn::Error::new_spanned(&impl_.self_ty, msg);
+            emit_error(err, impl_)
+        }
+        item => emit_error(syn::Error::new_spanned(&item, msg), item),
+    }
+}
+
 /// Modifies a trait item in a way that it includes contracts.
 pub(crate) fn contract_trait_item_trait(_attrs: TokenStream, mut trait_: ItemTrait) -> TokenStream {
     /// Just rename the method to have an internal, generated name.
@@ -48,6 +76,22 @@ pub(crate) fn contract_trait_item_trait(_attrs: TokenStream, mut trait_: ItemTra
             m.sig.ident = syn::Ident::new(&new_name, m.sig.ident.span());
         }
 
+        // patterns are not allowed in functions without a body, only the
+        // wrapper function needs to destructure the arguments
+        if m.default.is_none() {
+            for arg in m.sig.inputs.iter_mut() {
+                if let FnArg::Typed(arg) = arg {
+                    match &mut *arg.pat {
+                        Pat::Ident(ident) if ident.subpat.is_none() => {
+                            ident.by_ref = None;
+                            ident.mutability = None;
+                        }
+                        pat => *pat = syn::parse_quote!(_),
+                    }
+                }
+            }
+        }
+
         m
     }
 
@@ -64,6 +108,8 @@ pub(crate) fn contract_trait_item_trait(_attrs: TokenStream, mut trait_: ItemTra
         fn arg_pat_info(pat: &Pat) -> ArgInfo {
             match pat {
                 Pat::Ident(ident) => {
+                    // only forward the name, not `mut` or `ref`
+                    let ident = &ident.ident;
                     let toks = quote::quote! {
                         #ident
                     };
@@ -87,24 +133,121 @@ pub(crate) fn contract_trait_item_trait(_attrs: TokenStream, mut trait_: ItemTra
                         call_toks: quote::quote!((#toks)),
                     }
                 }
-                Pat::TupleStruct(_tup) => unimplemented!(),
-                p => panic!("Unsupported pattern type: {:?}", p),
+                Pat::TupleStruct(tup) => {
+                    let path = &tup.path;
+                    let infos = tup.elems.iter().map(arg_pat_info);
+
+                    let toks = {
+                        let mut toks = proc_macro2::TokenStream::new();
+
+                        for info in infos {
+                            toks.extend(info.call_toks);
+                            toks.extend(quote::quote!(,));
+                        }
+
+                        toks
+                    };
+
+                    ArgInfo {
+                        call_toks: quote::quote!(#path(#toks)),
+                    }
+                }
+                Pat::Struct(strct) => {
+                    if strct.rest.is_some() {
+                        return unsupported(
+                            pat,
+                            "struct patterns with `..` can't be forwarded to the implementation",
+                        );
+                    }
+
+                    let path = &strct.path;
+
+                    let toks = {
+                        let mut toks = proc_macro2::TokenStream::new();
+
+                        for field in &strct.fields {
+                            let member = &field.member;
+                            let info = arg_pat_info(&field.pat);
+                            let call_toks = info.call_toks;
+
+                            toks.extend(quote::quote!(#member: #call_toks,));
+                        }
+
+                        toks
+                    };
+
+                    ArgInfo {
+                        call_toks: quote::quote!(#path { #toks }),
+                    }
+                }
+                Pat::Reference(reference) => {
+                    if reference.mutability.is_some() {
+                        return unsupported(
+                            pat,
+                            "`&mut` patterns can't be forwarded to the implementation",
+                        );
+                    }
+
+                    let info = arg_pat_info(&reference.pat);
+                    let call_toks = info.call_toks;
+
+                    ArgInfo {
+                        call_toks: quote::quote!(&#call_toks),
+                    }
+                }
+                pat => unsupported(
+                    pat,
+                    "this pattern can't be forwarded to the implementation, bind the argument \
+                     to a name instead",
+                ),
+            }
+        }
+
+        // the error replaces the argument of the forwarded call
+        fn unsupported(pat: &Pat, msg: &str) -> ArgInfo {
+            ArgInfo {
+                call_toks: syn::Error::new_spanned(pat, msg).into_compile_error(),
             }
         }
 
         let mut m = method.clone();
 
+        // ignored arguments still have to be forwarded, so they are given a
+        // name which contracts can't refer to
+        for (idx, input) in m.sig.inputs.iter_mut().enumerate() {
+            if let FnArg::Typed(arg) = input {
+                if let Pat::Wild(wild) = &*arg.pat {
+                    let name = syn::Ident::new(
+                        &format!("__contracts_arg_{}", idx),
+                        wild.underscore_token.span,
+                    );
+                    *arg.pat = syn::parse_quote!(#name);
+                }
+            }
+        }
+
+        // methods with a receiver are called on it, like they would be
+        // through a trait object
+        let receiver = m.sig.receiver().map(|receiver| {
+            // a pinned reference is reborrowed, so post-conditions can still
+            // use `self` after the call
+            if is_pinned_mut(receiver) {
+                quote::quote!(::core::pin::Pin::as_mut(&mut self))
+            } else {
+                quote::quote!(self)
+            }
+        });
+
         let argument_data = m
             .sig
             .inputs
-            .clone()
-            .into_iter()
-            .map(|t: FnArg| match &t {
-                FnArg::Receiver(_) => quote::quote!(self),
+            .iter()
+            .filter_map(|t: &FnArg| match t {
+                FnArg::Receiver(_) => None,
                 FnArg::Typed(p) => {
                     let info = arg_pat_info(&p.pat);
 
-                    info.call_toks
+                    Some(info.call_toks)
                 }
             })
             .collect::<Vec<_>>();
@@ -120,17 +263,55 @@ pub(crate) fn contract_trait_item_trait(_attrs: TokenStream, mut trait_: ItemTra
             toks
         };
 
+        // Forward the type and const parameters of the method explicitly, as
+        // they can't always be inferred from the arguments. Lifetimes are
+        // left out, as late-bound lifetimes can't be specified.
+        let generic_args = {
+            let params = m
+                .sig
+                .generics
+                .params
+                .iter()
+                .filter_map(|param| match param {
+                    GenericParam::Type(ty) => Some(ty.ident.clone()),
+                    GenericParam::Const(c) => Some(c.ident.clone()),
+                    GenericParam::Lifetime(_) => None,
+                })
+                .collect::<Vec<_>>();
+
+            if params.is_empty() {
+                None
+            } else {
+                Some(quote::quote!(::<#(#params),*>))
+            }
+        };
+
         let body: TokenStream = {
             let name = contract_method_impl_name(&m.sig.ident.to_string());
             let name = syn::Ident::new(&name, m.sig.ident.span());
 
-            quote::quote! {
-                {
-                    Self::#name(#arguments)
-                }
+            match receiver {
+                Some(receiver) => quote::quote! {
+                    {
+                        #receiver.#name #generic_args(#arguments)
+                    }
+                },
+                None => quote::quote! {
+                    {
+                        Self::#name #generic_args(#arguments)
+                    }
+                },
             }
         };
 
+        for input in m.sig.inputs.iter_mut() {
+            if let FnArg::Receiver(receiver) = input {
+                if is_pinned_mut(receiver) {
+                    receiver.mutability = Some(Default::default());
+                }
+            }
+        }
+
         let mut attrs = vec![];
 
         // keep the documentation and contracts of the original method
@@ -155,7 +336,7 @@ pub(crate) fn contract_trait_item_trait(_attrs: TokenStream, mut trait_: ItemTra
         m.attrs = attrs;
 
         {
-            let block: syn::Block = syn::parse2(body).unwrap();
+            let block: syn::Block = syn::parse_quote!(#body);
             m.default = Some(block);
             m.semi_token = None;
         }
@@ -163,47 +344,153 @@ pub(crate) fn contract_trait_item_trait(_attrs: TokenStream, mut trait_: ItemTra
         m
     }
 
-    // create method wrappers and renamed items
-    let funcs = trait_
+    // replace methods with their wrappers and renamed items, all other items
+    // like associated types and consts are kept as they are
+    trait_.items = trait_
         .items
-        .iter()
-        .filter_map(|item| {
-            if let TraitItem::Fn(m) = item {
+        .into_iter()
+        .flat_map(|item| {
+            if let TraitItem::Fn(m) = &item {
                 let rename = create_method_rename(m);
-                let wrapper = create_method_wrapper(m);
+                let mut wrapper = create_method_wrapper(m);
+                mark_trait_method(&mut wrapper.attrs);
 
-                Some(vec![TraitItem::Fn(rename), TraitItem::Fn(wrapper)])
+                vec![TraitItem::Fn(rename), TraitItem::Fn(wrapper)]
             } else {
-                None
+                vec![item]
             }
         })
-        .flatten()
-        .collect::<Vec<_>>();
-
-    // remove all previous methods
-    trait_
-        .items
-        .retain(|item| !matches!(item, TraitItem::Fn(_)));
+        .collect();
 
-    // add back new methods
-    trait_.items.extend(funcs);
+    let marker = syn::Ident::new(MARKER_METHOD, Span::call_site());
+    trait_.items.push(syn::parse_quote! {
+        #[doc(hidden)]
+        fn #marker()
+        where
+            Self: Sized;
+    });
 
     trait_.into_token_stream()
 }
 
+/// Whether the receiver is a pinned mutable reference, like
+/// `self: Pin<&mut Self>`.
+fn is_pinned_mut(receiver: &Receiver) -> bool {
+    let ty = match &receiver.kind {
+        ReceiverKind::Typed(_, ty) => ty,
+        _ => return false,
+    };
+
+    let segment = match &**ty {
+        Type::Path(path) => path.path.segments.last(),
+        _ => None,
+    };
+
+    match segment {
+        Some(segment) if segment.ident == "Pin" => match &segment.arguments {
+            PathArguments::AngleBracketed(args) => args.args.iter().any(|arg| match arg {
+                GenericArgument::Type(Type::Reference(reference)) => reference.mutability.is_some(),
+                _ => false,
+            }),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Contracts given on a method of a trait implementation.
+fn impl_method_contracts(method: &ImplItemFn) -> Vec<Contract> {
+    method
+        .attrs
+        .iter()
+        .filter_map(|attr| {
+            let name = attr.path().segments.last()?.ident.to_string();
+            let (ty, mode) = ContractType::contract_type_and_mode(&name)?;
+
+            let toks = match &attr.meta {
+                Meta::List(list) => list.tokens.clone(),
+                _ => return None,
+            };
+
+            Some(Contract::from_toks(ty, mode, toks))
+        })
+        .collect()
+}
+
 /// Rename all methods inside an `impl` to use the "internal implementation"
 /// name.
 pub(crate) fn contract_trait_item_impl(_attrs: TokenStream, impl_: ItemImpl) -> TokenStream {
     let new_impl = {
         let mut impl_: ItemImpl = impl_;
 
+        let trait_name = impl_
+            .trait_
+            .as_ref()
+            .and_then(|(path, _)| path.segments.last())
+            .map(|segment| segment.ident.to_string());
+
+        let mut impl_docs = vec![];
+
         impl_.items.iter_mut().for_each(|it| {
             if let ImplItem::Fn(method) = it {
-                let new_name = contract_method_impl_name(&method.sig.ident.to_string());
+                let name = method.sig.ident.to_string();
+
+                // contracts of the implementation are checked in addition to
+                // the ones of the trait. They end up on the hidden renamed
+                // method, so they are documented on the `impl` block instead.
+                let contract_docs = doc::contract_attributes(&impl_method_contracts(method));
+                if !contract_docs.is_empty() {
+                    if impl_docs.is_empty() {
+                        impl_docs.push(doc::make_attribute("# Additional contracts"));
+                        impl_docs.push(doc::make_attribute(""));
+                    }
+
+                    impl_docs.push(doc::make_attribute(&format!(
+                        "`{}` checks these contracts in addition to the ones of the trait:",
+                        name
+                    )));
+                    impl_docs.push(doc::make_attribute(""));
+                    impl_docs.extend(contract_docs);
+                }
+                let new_name = contract_method_impl_name(&name);
                 let new_ident = syn::Ident::new(&new_name, method.sig.ident.span());
 
                 method.sig.ident = new_ident;
+                mark_trait_method(&mut method.attrs);
+
+                // The renamed method is hidden from the documentation, point
+                // readers of the implementation to the method checking the
+                // contracts.
+                if let Some(trait_name) = &trait_name {
+                    let has_docs = method.attrs.iter().any(|attr| attr.path().is_ident("doc"));
+
+                    if has_docs {
+                        method.attrs.push(syn::parse_quote!(#[doc = ""]));
+                    }
+
+                    let note = format!(
+                        "Implementation of `{}::{}`, which checks the contracts of the trait.",
+                        trait_name, name
+                    );
+                    method.attrs.push(syn::parse_quote!(#[doc = #note]));
+                }
+            }
+        });
+
+        if !impl_docs.is_empty() {
+            // separate the generated section from existing documentation
+            if impl_.attrs.iter().any(|attr| attr.path().is_ident("doc")) {
+                impl_.attrs.push(doc::make_attribute(""));
             }
+
+            impl_.attrs.extend(impl_docs);
+        }
+
+        let marker = syn::Ident::new(MARKER_METHOD, Span::call_site());
+        impl_.items.push(syn::parse_quote! {
+            #[doc(hidden)]
+            #[inline(always)]
+            fn #marker() {}
         });
 
         impl_
@@ -244,6 +531,88 @@ mod tests {
                 fn random_number(min: u8, max: u8) -> u8 {
                     Self::__contracts_impl_random_number(min, max,)
                 }
+
+                #[doc(hidden)]
+                fn __contracts_missing_contract_trait_attribute()
+                where
+                    Self: Sized;
+            }
+        };
+
+        let generated = super::contract_trait_item_trait(Default::default(), code);
+
+        assert_eq!(generated.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn generic_method_parameters_are_forwarded() {
+        // type and const parameters are passed explicitly to the "internal"
+        // function, lifetimes are inferred.
+
+        let code = syn::parse_quote! {
+            trait Container<K> {
+                #[ensures(ret > 0)]
+                fn size_of<'a, T: Default, const N: usize>(&'a self) -> usize;
+            }
+        };
+
+        let expected = quote::quote! {
+            trait Container<K> {
+                #[doc(hidden)]
+                #[doc = "This is an internal function that is not meant to be used directly!"]
+                #[doc = "See the documentation of the `#[contract_trait]` attribute."]
+                fn __contracts_impl_size_of<'a, T: Default, const N: usize>(&'a self) -> usize;
+
+                #[ensures(ret > 0)]
+                #[inline(always)]
+                fn size_of<'a, T: Default, const N: usize>(&'a self) -> usize {
+                    self.__contracts_impl_size_of::<T, N>()
+                }
+
+                #[doc(hidden)]
+                fn __contracts_missing_contract_trait_attribute()
+                where
+                    Self: Sized;
+            }
+        };
+
+        let generated = super::contract_trait_item_trait(Default::default(), code);
+
+        assert_eq!(generated.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn associated_items_are_kept() {
+        let code = syn::parse_quote! {
+            trait Parser {
+                type Output;
+
+                fn parse(input: &str) -> Self::Output;
+
+                const LIMIT: usize;
+            }
+        };
+
+        let expected = quote::quote! {
+            trait Parser {
+                type Output;
+
+                #[doc(hidden)]
+                #[doc = "This is an internal function that is not meant to be used directly!"]
+                #[doc = "See the documentation of the `#[contract_trait]` attribute."]
+                fn __contracts_impl_parse(input: &str) -> Self::Output;
+
+                #[inline(always)]
+                fn parse(input: &str) -> Self::Output {
+                    Self::__contracts_impl_parse(input,)
+                }
+
+                const LIMIT: usize;
+
+                #[doc(hidden)]
+                fn __contracts_missing_contract_trait_attribute()
+                where
+                    Self: Sized;
             }
         };
 
@@ -271,9 +640,15 @@ mod tests {
             impl Random for AlwaysMin {
                 /// docs for this function!
                 #[no_panic]
+                #[doc = ""]
+                #[doc = "Implementation of `Random::random_number`, which checks the contracts of the trait."]
                 fn __contracts_impl_random_number(min: u8, max: u8) -> u8 {
                     min
                 }
+
+                #[doc(hidden)]
+                #[inline(always)]
+                fn __contracts_missing_contract_trait_attribute() {}
             }
         };
 
@@ -281,4 +656,114 @@ mod tests {
 
         assert_eq!(generated.to_string(), expected.to_string());
     }
+
+    #[cfg(not(feature = "doc_tables"))]
+    #[test]
+    fn impl_contracts_are_documented() {
+        let code = syn::parse_quote! {
+            /// A generator.
+            impl Random for Bounded {
+                #[requires(max < 100, "small ranges only")]
+                fn random_number(min: u8, max: u8) -> u8 {
+                    min
+                }
+            }
+        };
+
+        let expected = quote::quote! {
+            /// A generator.
+            #[doc = ""]
+            #[doc = "# Additional contracts"]
+            #[doc = ""]
+            #[doc = "`random_number` checks these contracts in addition to the ones of the trait:"]
+            #[doc = ""]
+            #[doc = "Pre-condition: small ranges only"]
+            #[doc = " - `max < 100`"]
+            #[doc = ""]
+            impl Random for Bounded {
+                #[requires(max < 100, "small ranges only")]
+                #[doc = "Implementation of `Random::random_number`, which checks the contracts of the trait."]
+                fn __contracts_impl_random_number(min: u8, max: u8) -> u8 {
+                    min
+                }
+
+                #[doc(hidden)]
+                #[inline(always)]
+                fn __contracts_missing_contract_trait_attribute() {}
+            }
+        };
+
+        let generated = super::contract_trait_item_impl(Default::default(), code);
+
+        assert_eq!(generated.to_string(), expected.to_string());
+    }
+
+    #[cfg(not(feature = "doc_tables"))]
+    #[test]
+    fn trait_method_contracts_are_documented() {
+        use quote::ToTokens;
+        use syn::TraitItem;
+
+        use crate::implementation::ContractMode;
+
+        // the wrapper keeps the contracts, which document themselves when
+        // they are expanded
+        let code = syn::parse_quote! {
+            trait Random {
+                /// Generates a number.
+                #[requires(min < max, "valid range")]
+                fn random_number(min: u8, max: u8) -> u8;
+            }
+        };
+
+        let generated = super::contract_trait_item_trait(Default::default(), code);
+        let trait_ = syn::parse2::<syn::ItemTrait>(generated).unwrap();
+
+        let wrapper = trait_
+            .items
+            .iter()
+            .find_map(|item| match item {
+                TraitItem::Fn(method) if method.sig.ident == "random_number" => Some(method),
+                _ => None,
+            })
+            .unwrap();
+
+        let mut attrs = wrapper.attrs.clone();
+        let idx = attrs
+            .iter()
+            .position(|attr| attr.path().is_ident("requires"))
+            .unwrap();
+        let contract = attrs.remove(idx);
+
+        let sig = &wrapper.sig;
+        let block = wrapper.default.as_ref().unwrap();
+        let func = quote::quote! {
+            #(#attrs)*
+            #sig #block
+        };
+
+        let expanded = crate::implementation::requires(
+            ContractMode::Always,
+            contract.meta.require_list().unwrap().tokens.clone(),
+            func,
+        );
+        let docs = syn::parse2::<syn::ItemFn>(expanded)
+            .unwrap()
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"))
+            .map(|attr| attr.to_token_stream().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            docs,
+            [
+                "# [doc = r\" Generates a number.\"]",
+                "# [doc = \"# Contracts\"]",
+                "# [doc = \"Pre-condition: valid range\"]",
+                "# [doc = \" - `min < max`\"]",
+                "# [doc = \"\"]",
+            ]
+        );
+    }
 }
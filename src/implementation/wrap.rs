@@ -0,0 +1,152 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use proc_macro2::TokenStream;
+use syn::{
+    parenthesized,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    ExprClosure, ExprPath, Ident, ReturnType, Token, Type,
+};
+
+use crate::implementation::{
+    closure::{into_closure, parse_closure},
+    contract::ContractClauses,
+    FuncWithContracts,
+};
+
+/// Wrap a function which can't be annotated, like one of another crate, in a
+/// closure checking the given contracts around the call.
+pub(crate) fn wrap(toks: TokenStream) -> TokenStream {
+    let wrapped = match syn::parse2::<Wrapped>(toks) {
+        Ok(wrapped) => wrapped,
+        Err(err) => return err.to_compile_error(),
+    };
+
+    let Wrapped { target, clauses } = wrapped;
+
+    let (name, local) = match target {
+        Target::Function { path, args, output } => {
+            // the last segment of the path names the function in violation
+            // messages
+            let name = path.path.segments.last().unwrap().ident.clone();
+
+            let inputs = args.iter().map(|(arg, ty)| match ty {
+                Some(ty) => quote::quote!(#arg: #ty),
+                None => quote::quote!(#arg),
+            });
+            let names = args.iter().map(|(arg, _)| arg);
+
+            let local = quote::quote! {
+                let #name = |#(#inputs),*| #output {
+                    #path(#(#names),*)
+                };
+            };
+
+            (name, local)
+        }
+        Target::Closure(closure) => {
+            let name = Ident::new("closure", proc_macro2::Span::call_site());
+            let local = quote::quote! {
+                let #name = #closure;
+            };
+
+            (name, local)
+        }
+    };
+
+    let (local, func) = parse_closure(&local).expect("the wrapper is a closure");
+
+    let generated = FuncWithContracts::new_with_contracts(func, clauses.contracts).generate();
+    let local = into_closure(local, generated);
+
+    quote::quote! {
+        {
+            #local
+            #name
+        }
+    }
+}
+
+/// The input of `wrap!`, like `std::cmp::max(a: u32, b: u32) -> u32,
+/// requires(a < 100)`.
+struct Wrapped {
+    target: Target,
+    clauses: ContractClauses,
+}
+
+/// What `wrap!` checks the contracts of.
+enum Target {
+    /// A function given by its path and the names of its arguments.
+    Function {
+        path: ExprPath,
+        args: Vec<(Ident, Option<Type>)>,
+        output: ReturnType,
+    },
+    /// A closure, which already names its arguments.
+    Closure(ExprClosure),
+}
+
+impl Parse for Wrapped {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let target = if input.peek(Token![|]) || input.peek(Token![||]) || input.peek(Token![move])
+        {
+            Target::Closure(input.parse::<ExprClosure>()?)
+        } else {
+            input.parse::<Target>()?
+        };
+
+        let clauses = if input.is_empty() {
+            ContractClauses {
+                contracts: Vec::new(),
+            }
+        } else {
+            input.parse::<Token![,]>()?;
+            input.parse::<ContractClauses>()?
+        };
+
+        Ok(Self { target, clauses })
+    }
+}
+
+impl Parse for Target {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let path = input.parse::<ExprPath>()?;
+
+        // the arguments are named, so the contracts can refer to them
+        let content;
+        parenthesized!(content in input);
+
+        let args = Punctuated::<(Ident, Option<Type>), Token![,]>::parse_terminated_with(
+            &content,
+            |input| {
+                let expected = "expected the name of an argument, like `x` or `x: u32`";
+
+                let arg = input
+                    .parse::<Ident>()
+                    .map_err(|err| syn::Error::new(err.span(), expected))?;
+                let ty = if input.peek(Token![:]) {
+                    input.parse::<Token![:]>()?;
+                    Some(input.parse::<Type>()?)
+                } else {
+                    None
+                };
+
+                if !input.is_empty() && !input.peek(Token![,]) {
+                    return Err(input.error(expected));
+                }
+
+                Ok((arg, ty))
+            },
+        )?;
+
+        let output = input.parse::<ReturnType>()?;
+
+        Ok(Target::Function {
+            path,
+            args: args.into_iter().collect(),
+            output,
+        })
+    }
+}
@@ -11,11 +11,45 @@
 //! [*post-conditions* (`ensures`)][postcond] and [*invariants*][invariant].
 //!
 //! Each "contract" annotation that is violated will cause an assertion failure.
+//! The message names the kind of contract, the function and the violated
+//! condition, followed by the file and line of the contract. Functions with
+//! checked pre-conditions are marked `#[track_caller]`, so the panic itself
+//! is reported at the call which violated the contract. This also applies to
+//! other panics inside of the function body, like failing `unwrap()` calls,
+//! unless every pre-condition has the `no_track_caller` flag, as in
+//! `#[requires(x > 0, no_track_caller)]`. It is skipped for `async`
+//! functions, `main` and functions with a foreign ABI, which don't support
+//! it.
 //!
 //! The attributes use "function call form" and can contain 1 or more conditions
-//! to check.
+//! to check. All of them have to hold, and each one is checked on its own, so
+//! the message of a violation names the condition which doesn't hold.
 //! If the last argument to an attribute is a string constant it will be
-//! inserted into the assertion message.
+//! inserted into the assertion message. Multiple trailing string constants
+//! are joined with spaces, so long descriptions can be split up.
+//! Contracts given the `interpolate` flag treat their description like a
+//! `format!` string, so placeholders such as `"x was {x}"` show the values
+//! of variables in scope, and `{ret}` shows the return value in
+//! post-conditions. Braces can then be escaped as `{{` and `}}`. Other
+//! descriptions are shown as they are. `{fn}` is always replaced by the name
+//! of the function, which is handy for macros generating contracts. As braces
+//! are only escaped in interpolated descriptions, `{{fn}}` shows the name of
+//! the function in braces otherwise.
+//! Additionally, options can be passed to the attributes as `key = "value"`
+//! arguments.
+//!
+//! The contracts are listed in a "Contracts" section of the documentation of
+//! the function. Contracts given the `no_doc` flag, as in
+//! `#[requires(x > 0, no_doc)]`, are left out of it, and the section is
+//! omitted if there are none left.
+//!
+//! Contracts of a `const fn` are checked in constant evaluation as well, so
+//! they can only use constant expressions. Their descriptions can't be
+//! interpolated, they can't be logged or given a `category`, a `handler`, a
+//! `payload` or an `audit` sink, and the features printing, counting or
+//! collecting violations leave them out. They are checked using `assert!`
+//! with the `mirai_assertions` feature, whose macros aren't `const`. The
+//! `override_log` feature disables them, as they can't be logged.
 //!
 //! ## Example
 //!
@@ -90,6 +124,17 @@
 //! [the Modes section](#modes) below.
 //!
 //! For `trait`s and trait `impl`s the `contract_trait` attribute can be used.
+//! The `contract_impl` attribute adds the same contracts to all methods of an
+//! `impl` block, like `#[contract_impl(requires(self.is_valid()))]`.
+//! Functions which can't be annotated, like the ones of other crates, can be
+//! wrapped in a closure checking contracts using the `wrap!` macro.
+//!
+//! The `requires`, `ensures` and `contract` attributes can also be applied to
+//! a `let` statement binding a closure, like
+//! `#[ensures(ret > 0)] let f = |x: i32| x + 1;`. Attributes on statements
+//! currently require the unstable `proc_macro_hygiene` and
+//! `stmt_expr_attributes` features, so this is limited to nightly compilers.
+//! On stable compilers, the closure can be passed to `wrap!` instead.
 //!
 //! ## Pseudo-functions and operators
 //!
@@ -107,6 +152,112 @@
 //! }
 //! ```
 //!
+//! Identical `old()` expressions of a function are only evaluated once. Any
+//! expression can be passed to `old()`, including a block of several
+//! statements like `old({ let mut v = self.items.clone(); v.sort(); v })`.
+//!
+//! Parameters are evaluated after the function body in post-conditions, so a
+//! `mut` parameter that is reassigned by the body refers to its final value.
+//! `old(n)` refers to the argument the function was called with, which is
+//! cheap for `Copy` types.
+//!
+//! The value of the expression passed to `old()` is stored as-is, so an
+//! expression that evaluates to a reference would still be borrowing the
+//! state that is modified by the function. The `old_clone()` variant calls
+//! `.clone()` on the expression instead. As method calls auto-dereference,
+//! this creates an owned snapshot of the value behind a reference, such as
+//! `self`.
+//!
+//! Taking a reference like `old(&self.data[0])` is rejected, as the borrow
+//! would prevent the function from modifying `self.data`. If keeping the
+//! reference is intended, for example for parameters which aren't modified,
+//! `old_ref()` can be used instead.
+//!
+//! The arguments of these functions are evaluated before the function body,
+//! so they must not use the `?` operator. A fallible value can be snapshotted
+//! as a `Result` or `Option` instead, such as `old(self.try_len())`, and
+//! handled in the condition.
+//!
+//! ```rust
+//! # use contracts::*;
+//! #[derive(Clone, PartialEq)]
+//! struct Stack(Vec<u32>);
+//!
+//! impl Stack {
+//!     #[ensures(self.0[..self.0.len() - 1] == old_clone(self.0)[..])]
+//!     #[ensures(self.0.len() == old_clone(self).0.len() + 1)]
+//!     fn push(&mut self, x: u32) {
+//!         self.0.push(x);
+//!     }
+//! }
+//! ```
+//!
+//! `old_self()` is a shorthand for a snapshot of `self`, which only compiles
+//! if `Self` implements `Clone`. Unlike `old_clone(self)`, it can't end up
+//! cloning the reference to `self` instead. Invariants which are only checked
+//! on exit, using `when = "exit"`, can use `old_self()` and the other `old()`
+//! variants as well.
+//!
+//! ```rust
+//! # use contracts::*;
+//! #[derive(Clone, Debug, PartialEq)]
+//! struct Counter(u32);
+//!
+//! #[invariant(self.0 >= old_self().0, when = "exit")]
+//! impl Counter {
+//!     #[ensures(*self != old_self())]
+//!     fn incr(&mut self) {
+//!         self.0 += 1;
+//!     }
+//! }
+//! ```
+//!
+//! ### `forall()` and `exists()` functions
+//!
+//! Quantifying over the elements of a collection can be expressed using the
+//! `forall(pattern in iterable, predicate)` and
+//! `exists(pattern in iterable, predicate)` pseudo-functions. They are
+//! rewritten into calls to [`Iterator::all`] and [`Iterator::any`]
+//! respectively, so for an empty iterable `forall` is vacuously true and
+//! `exists` is false.
+//!
+//! ```rust
+//! # use contracts::*;
+//! #[ensures(forall(x in ret.iter(), *x >= 0))]
+//! #[ensures(exists(x in input.iter(), *x < 0) -> ret != input)]
+//! fn absolutes(input: &[i32]) -> Vec<i32> {
+//!     input.iter().map(|x| x.abs()).collect()
+//! }
+//! ```
+//!
+//! The iterable can be anything implementing [`IntoIterator`]. Iterables
+//! naming a variable or a field, like `v` or `self.items`, are borrowed, so
+//! they can still be used in the rest of the contract. The predicate can make use of
+//! `old()`, `->` or other quantifiers. Like everywhere else, `old()`
+//! expressions inside a predicate are evaluated once, before the function is
+//! called, so they can't refer to the quantified variable.
+//!
+//! ### `same_alloc()` function
+//!
+//! Whether a mutable reference was modified in place, rather than reassigned
+//! to a different allocation, can be checked with the
+//! `same_alloc(value, old(value))` pseudo-function. It compares the pointers
+//! returned by `as_ptr()` before and after the call, so it works with
+//! anything providing that method, such as `Vec`, `String` or slices.
+//!
+//! ```rust
+//! # use contracts::*;
+//! #[ensures(same_alloc(v, old(v)))]
+//! fn clear_all(v: &mut Vec<u32>) {
+//!     v.iter_mut().for_each(|x| *x = 0);
+//! }
+//! ```
+//!
+//! Growing a value beyond its capacity reallocates it, which is reported like
+//! a reassignment. Empty values without capacity don't own an allocation and
+//! share the same dangling pointer, so replacing an empty `Vec` with another
+//! empty `Vec` isn't detected.
+//!
 //! ### `->` operator
 //!
 //! For more complex functions it can be useful to express behaviour using logical
@@ -127,14 +278,57 @@
 //! }
 //! ```
 //!
-//! This operator is right-associative.
+//! This operator is right-associative. It can also be spelled `==>`, which
+//! behaves exactly the same.
+//!
+//! A `->` directly following the parameters of a closure (`|x| -> bool { .. }`)
+//! or of a function pointer type (`fn(u8) -> u8`) is kept as a return type.
+//! Closures with an explicit return type are rare inside of conditions, but
+//! `==>` avoids any ambiguity.
 //!
 //! **Note**: Because of the design of `syn`, it is tricky to add custom operators
 //! to be parsed, so this crate performs a rewrite of the `TokenStream` instead.
 //! The rewrite works by separating the expression into a part that's left of the
-//! `->` operator and the rest on the right side. This means that
-//! `if a -> b { c } else { d }` will not generate the expected code.
-//! Explicit grouping using parenthesis or curly-brackets can be used to avoid this.
+//! `->` operator and the rest on the right side. Only the conditions of `if`,
+//! `while` and `match` expressions, the arms of `match` expressions and the
+//! statements inside of blocks are handled separately, so `if a -> b { c } else { d }` works as expected, but
+//! `a || b -> c` is read as `(a || b) -> c`.
+//! Explicit grouping using parenthesis or curly-brackets can be used to avoid
+//! surprises.
+//!
+//! ### `<==` operator
+//!
+//! Sometimes it reads more naturally to state the consequence first. The
+//! reverse implication operator `<==` allows to do so: `a <== b` is equivalent
+//! to `b -> a`.
+//!
+//! ```rust
+//! # use contracts::*;
+//! #[ensures(ret.is_none() <== key.is_empty())]
+//! fn first_char(key: &str) -> Option<char> {
+//!     key.chars().next()
+//! }
+//! ```
+//!
+//! Like the other operators, `<==` is right-associative and can't be mixed
+//! with other operators without explicit grouping.
+//!
+//! ### `<==>` operator
+//!
+//! Logical equivalence ("if and only if") can be expressed using the `<==>`
+//! operator, which is rewritten into an equality check of both sides.
+//!
+//! ```rust
+//! # use contracts::*;
+//! #[ensures(ret.is_empty() <==> input.is_empty())]
+//! fn double(input: &[u32]) -> Vec<u32> {
+//!     input.iter().map(|x| x * 2).collect()
+//! }
+//! ```
+//!
+//! Just like `->`, this operator is right-associative. Mixing `<==>` and `->`
+//! on the same level (such as `a <==> b -> c`) is rejected; use parenthesis to
+//! make the intended grouping explicit.
 //!
 //! ## Modes
 //!
@@ -157,17 +351,263 @@
 //!   }
 //!   ```
 //!
+//! The mode of a single contract can also be chosen using the `mode` option,
+//! which is one of `"always"`, `"debug"`, `"test"` or `"log"`. The latter only
+//! logs violations, like the `override_log` feature does for all contracts.
+//! Contracts given the `force_always` flag keep their mode, even if the
+//! `override_debug` or `override_log` features are enabled, which is useful
+//! for safety-critical conditions. A `#[requires(.., force_always)]` is
+//! always checked using `assert!`, while a `#[debug_requires(..,
+//! force_always)]` stays a `debug_assert!`. Only the `disable_contracts`
+//! feature, or the one disabling their kind of contract, still removes them.
+//!
+//! ```rust
+//! # use contracts::*;
+//! #[requires(x < 100, "x is small", mode = "debug")]
+//! fn incr(x: u32) -> u32 {
+//!     x + 1
+//! }
+//! ```
+//!
+//! Expensive checks can be limited to builds enabling a cargo feature of your
+//! crate using the `cfg` option, such as `cfg = "expensive_checks"`. Contracts
+//! with this option are only compiled if `feature = "expensive_checks"` is
+//! enabled.
+//!
+//! Contracts can also be grouped into categories using the `category` option,
+//! such as `category = "security"`, which can be turned on and off without
+//! recompiling. The `CONTRACTS_CATEGORIES` environment variable lists the
+//! enabled categories separated by commas. If it is set when building the
+//! crate, the categories are fixed at compile time. Otherwise it is read when
+//! the program runs, once per contract. If it is set neither time, all
+//! categories are enabled.
+//!
+//! Contracts can also be made conditional using `cfg_attr`, as in
+//! `#[cfg_attr(feature = "strict", ensures(ret > 0))]`. These are expanded
+//! separately from the contract attributes before them, so they are checked
+//! in an outer wrapper of the function and documented in their own
+//! "Contracts" section.
+//!
+//! The feature flags below still apply to these contracts, so
+//! `disable_contracts` disables them and `override_debug` turns them into
+//! `debug_*` versions, except for logged ones.
+//!
+//! ## Generated functions
+//!
+//! The conditions of a pre-condition can be reused using the `checker`
+//! option, which generates a function with the given name next to the
+//! annotated one. It returns whether the conditions hold for the given
+//! arguments, which is useful to check inputs before calling the function.
+//! The `checker` flag names the function `__check_pre_<function>` instead.
+//!
+//! ```rust
+//! # use contracts::*;
+//! #[requires(y != 0, checker = "can_divide")]
+//! fn divide(x: u32, y: u32) -> u32 {
+//!     x / y
+//! }
+//!
+//! #[requires(!name.is_empty(), checker)]
+//! fn greet(name: String) -> String {
+//!     format!("Hello {}", name)
+//! }
+//!
+//! assert!(!can_divide(&1, &0));
+//!
+//! let name = String::from("you");
+//! assert!(__check_pre_greet(&name));
+//! assert_eq!(greet(name), "Hello you");
+//! ```
+//!
+//! The checker borrows the arguments of the function, including `self`, so
+//! they can still be used afterwards. It is private, even if the function is
+//! public, so it doesn't become part of the API of the crate.
+//!
+//! Similarly, the `unchecked` option generates a variant of the function with
+//! the given name, which doesn't check any contracts. Calling it for recursion
+//! avoids checking the contracts again on every level, which can be costly
+//! for deep recursion or expensive conditions.
+//!
+//! ```rust
+//! # use contracts::*;
+//! #[requires(n < 20, unchecked = "factorial_unchecked")]
+//! fn factorial(n: u64) -> u64 {
+//!     if n == 0 {
+//!         1
+//!     } else {
+//!         n * factorial_unchecked(n - 1)
+//!     }
+//! }
+//! ```
+//!
+//! As these functions are defined next to the annotated one, both options
+//! can't be used on methods of trait implementations.
+//!
+//! ## Generated names
+//!
+//! The code generated for contracts uses identifiers and labels starting with
+//! `__contracts_`, so they don't clash with the names used in the function
+//! body. This prefix is reserved and should not be used in code annotated with
+//! contracts.
+//!
+//! ## Handling violations
+//!
+//! The `handler` option names a function which is called with the kind of a
+//! violated contract, the name of the function, the condition and the message
+//! before the violation is reported, for example to log or count it. The
+//! message includes the location of the contract.
+//!
+//! ```rust
+//! # use contracts::*;
+//! fn report(kind: &str, function: &str, condition: &str, message: &str) {
+//!     eprintln!("{} of {} broke `{}`: {}", kind, function, condition, message);
+//! }
+//!
+//! #[requires(x > 0, handler = "report")]
+//! fn incr(x: u32) -> u32 {
+//!     x + 1
+//! }
+//! ```
+//!
+//! The crate only provides procedural macros, so the handler is named by each
+//! contract instead of being set globally at runtime.
+//!
+//! The message of a violation can be changed using the `formatter` option,
+//! which names a function building it from the kind of the contract, the name
+//! of the function, the condition and the description, if there is one. The
+//! message is used as it is, without the location of the contract.
+//!
+//! ```rust
+//! # use contracts::*;
+//! fn house_style(kind: &str, function: &str, condition: &str, desc: Option<&str>) -> String {
+//!     format!("{}: {} broke `{}`", function, kind, desc.unwrap_or(condition))
+//! }
+//!
+//! #[requires(x > 0, formatter = "house_style")]
+//! fn incr(x: u32) -> u32 {
+//!     x + 1
+//! }
+//! ```
+//!
+//! Projects with their own assertion macro can check a contract using it
+//! with the `via` option, like `via = "my_crate::my_assert"`. The macro is
+//! called like `my_assert!(condition, message)` instead of `assert!`, where
+//! the message is a string. Contracts in debug or test mode only call it if
+//! `debug_assertions` are enabled.
+//!
+//! Violations can panic with a structured payload instead of a message, using
+//! the `payload` option. It names a function creating the payload from the
+//! kind of the contract, the name of the function, the condition and the
+//! message, which is passed to [`std::panic::panic_any`]. A handler using
+//! [`std::panic::catch_unwind`] can then downcast the payload to its type.
+//!
+//! ```rust
+//! # use contracts::*;
+//! pub struct Violation {
+//!     pub condition: &'static str,
+//!     pub message: String,
+//! }
+//!
+//! fn violation(_kind: &str, _function: &str, condition: &'static str, message: String) -> Violation {
+//!     Violation { condition, message }
+//! }
+//!
+//! #[requires(x > 0, payload = "violation")]
+//! fn incr(x: u32) -> u32 {
+//!     x + 1
+//! }
+//!
+//! let payload = std::panic::catch_unwind(|| incr(0)).unwrap_err();
+//! assert_eq!(payload.downcast_ref::<Violation>().unwrap().condition, "x > 0");
+//! ```
+//!
+//! Violations collected by the `collect_failures` feature are still reported
+//! with a message.
+//!
+//! The `audit` option names a function receiving the outcome of every
+//! evaluation of the conditions, passed as `record(kind, function, condition,
+//! passed)`. The checks themselves are unchanged. Keeping the last records,
+//! for example in a thread-local ring buffer, helps finding out which earlier
+//! call corrupted a value once a contract is violated.
+//!
+//! ```rust
+//! # use contracts::*;
+//! fn record(kind: &str, function: &str, condition: &str, passed: bool) {
+//!     println!("{} of {}: `{}` {}", kind, function, condition, passed);
+//! }
+//!
+//! #[requires(x > 0, audit = "record")]
+//! fn incr(x: u32) -> u32 {
+//!     x + 1
+//! }
+//! ```
+//!
+//! Contracts that shouldn't abort can be logged instead, using the `mode =
+//! "log"` option or the `override_log` feature.
+//!
 //! ## Feature flags
 //!
 //! Following feature flags are available:
 //!  - `disable_contracts` - disables all checks and assertions.
+//!  - `disable_pre`, `disable_post` and `disable_invariants` - disable only
+//!    the pre-conditions, post-conditions or invariants respectively, for
+//!    example to keep cheap checks of the inputs while leaving out expensive
+//!    post-conditions. `old()` expressions of disabled contracts aren't
+//!    evaluated. Assertions of `assert_contract!` count as pre-conditions.
 //!  - `override_debug` - changes all contracts (except `test_` ones) into
 //!    `debug_*` versions
 //!  - `override_log` - changes all contracts (except `test_` ones) into a
 //!    `log::error!()` call if the condition is violated.
-//!    No abortion happens.
+//!    No abortion happens. The log level and target of a contract can be
+//!    changed using the `level = "warn"` and `target = "my_target"` options.
 //! - `mirai_assertions` - instead of regular assert! style macros, emit macros
-//!   used by the [MIRAI] static analyzer.
+//!   used by the [MIRAI] static analyzer. Pre-conditions use `checked_assume!`,
+//!   while post-conditions and invariants use `checked_verify!`, or the
+//!   `debug_*` and unchecked variants depending on the mode.
+//! - `tracing` - emit logged contract violations (see `override_log`) using
+//!   the [`tracing`] crate instead of `log`. The events carry the kind of the
+//!   contract and the function name as `contract.kind` and `contract.fn` fields.
+//! - `strict_invariants` - also check invariants when a method panics, before
+//!   the panic continues to unwind. This does not apply to `async` functions
+//!   and `const fn`s.
+//! - `debug_values` - print the values of both operands when a comparison
+//!   like `ret > x` is violated. The operands need to implement `Debug`.
+//!   Violated equalities and inequalities are reported using `assert_eq!`
+//!   and `assert_ne!`, with the usual message of the contract.
+//! - `json_violations` - print a single line JSON object describing a violated
+//!   contract to stderr before it is reported as usual, such as
+//!   `{"contract":"Pre-condition","fn":"add","expr":"x > 0","desc":"..."}`.
+//!   The `desc` field contains the complete violation message.
+//! - `metrics` - count how often each contract is evaluated using the
+//!   [`metrics`] crate. Every check increments the `contracts.checks` counter
+//!   labeled with the kind of the contract as `kind` and the function name as
+//!   `fn`. The generated code calls `metrics::counter!`, so crates enabling
+//!   this feature need to depend on `metrics` 0.24 themselves. The counts are
+//!   kept by the installed metrics recorder and can be queried from it, as a
+//!   procedural macro crate like this one can't export a runtime API such as
+//!   a `snapshot()` function.
+//! - `strict_purity` - reject contracts with obvious side effects, like
+//!   assignments, `&mut` borrows or calls to mutating methods such as
+//!   `push()`. Contracts should behave the same whether they are checked or
+//!   not. This check is best-effort, as other function calls can't be checked.
+//! - `public_only` - only check the contracts of functions declared `pub`,
+//!   which are the entry points of the public API. Invariants of `impl`
+//!   blocks are then only checked by public methods, so private helpers may
+//!   temporarily break them. Functions declared `pub(crate)` or `pub(super)`
+//!   aren't part of the public API, so they aren't checked. Methods of traits
+//!   are as public as the trait, which is known for traits and
+//!   implementations using `#[contract_trait]`, and implementations using
+//!   `#[contract_impl]` or `#[invariant]`. The contracts of other trait
+//!   methods and of closures aren't checked. `test_` contracts and contracts
+//!   returning an error are always kept.
+//! - `doc_tables` - list the contracts in the generated documentation as a
+//!   table with the columns Kind, Mode, Condition and Description, instead of
+//!   one paragraph per contract.
+//! - `collect_failures` - check all pre-conditions of a function before
+//!   reporting the violated ones together in a single panic, one per line,
+//!   instead of panicking at the first one. The same applies to the
+//!   post-conditions. Contracts using a custom assertion macro (`via`) are
+//!   still reported by it.
 //!
 //! [dbc]: https://en.wikipedia.org/wiki/Design_by_contract
 //! [`libhoare`]: https://github.com/nrc/libhoare
@@ -175,6 +615,8 @@
 //! [postcond]: attr.ensures.html
 //! [invariant]: attr.invariant.html
 //! [MIRAI]: https://github.com/facebookexperimental/MIRAI
+//! [`tracing`]: https://docs.rs/tracing
+//! [`metrics`]: https://docs.rs/metrics
 
 extern crate proc_macro;
 
@@ -207,6 +649,46 @@ pub fn contract(attr: TokenStream, toks: TokenStream) -> TokenStream {
     implementation::contract(attr, toks).into()
 }
 
+/// Add the same contracts to all methods of an `impl` block.
+///
+/// The clauses are written like the ones of [`contract`], which is added to
+/// every method taking `self`. Methods without a receiver, like constructors,
+/// are left as they are.
+///
+/// The contracts of a method are combined with the ones of the `impl` block,
+/// so both are checked, pre-conditions of the `impl` block first. Like with
+/// [`invariant`] on `impl` blocks, the attributes are added by name, so
+/// `contract` has to be in scope.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// struct Range {
+///     start: u32,
+///     end: u32,
+/// }
+///
+/// #[contract_impl(invariant(self.start <= self.end, "ranges are ordered"))]
+/// impl Range {
+///     #[requires(by <= self.start)]
+///     fn shift_left(&mut self, by: u32) {
+///         self.start -= by;
+///         self.end -= by;
+///     }
+///
+///     fn len(&self) -> u32 {
+///         self.end - self.start
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn contract_impl(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let attr = attr.into();
+    let toks = toks.into();
+    implementation::contract_impl(attr, toks).into()
+}
+
 /// Pre-conditions are checked before the function body is run.
 ///
 /// ## Example
@@ -246,16 +728,64 @@ pub fn test_requires(attr: TokenStream, toks: TokenStream) -> TokenStream {
     implementation::requires(ContractMode::Test, attr, toks).into()
 }
 
+/// Same as [`requires`], but returns an error from the function instead of
+/// panicking when the pre-condition is violated.
+///
+/// The last argument of the attribute is the error value. It can either be an
+/// expression or a closure without arguments, which is only called on
+/// violation. The annotated function has to return a `Result`.
+///
+/// As returning an error is part of the behaviour of the function, these
+/// contracts are not affected by any of the feature flags.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// #[derive(Debug, PartialEq)]
+/// enum MathError {
+///     DivisionByZero,
+/// }
+///
+/// #[requires_or_return(divisor != 0, || MathError::DivisionByZero)]
+/// fn divide(dividend: i32, divisor: i32) -> Result<i32, MathError> {
+///     Ok(dividend / divisor)
+/// }
+///
+/// assert_eq!(divide(1, 0), Err(MathError::DivisionByZero));
+/// ```
+///
+/// [`requires`]: attr.requires.html
+#[proc_macro_attribute]
+pub fn requires_or_return(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let attr = attr.into();
+    let toks = toks.into();
+    implementation::requires(ContractMode::ReturnError, attr, toks).into()
+}
+
 /// Post-conditions are checked after the function body is run.
 ///
 /// The result of the function call is accessible in conditions using the `ret`
-/// identifier.
+/// identifier. A different name can be chosen by starting the attribute with
+/// `@name:`, which is useful if the function has a parameter called `ret`.
+/// As the value is returned afterwards, post-conditions only borrow it and
+/// can't move out of it. Values which need to be consumed, like a returned
+/// `impl Iterator`, can be inspected using a clone of `ret` instead.
+///
+/// Post-conditions are also checked when the function returns early, using
+/// either `return` or the `?` operator in functions returning a `Result`, an
+/// `Option`, a `Poll` or a `ControlFlow`, including type aliases like
+/// `io::Result<T>` or `type Res<T> = Result<T, Error>`. Functions returning
+/// `!` never finish, so their post-conditions are never checked.
 ///
 /// A "pseudo-function" named `old` can be used to evaluate expressions in a
 /// context *prior* to function execution.
 /// This function takes only a single argument and the result of it will be
 /// stored in a variable before the function is called. Because of this,
-/// handling references might require special care.
+/// handling references might require special care. The `old_clone` variant
+/// clones the result of its argument, which allows to snapshot values behind
+/// references. In `async` functions, `old()` expressions are evaluated when
+/// the returned future is first polled, before any `.await` in the body.
 ///
 /// ## Examples
 ///
@@ -274,6 +804,14 @@ pub fn test_requires(attr: TokenStream, toks: TokenStream) -> TokenStream {
 ///     *x += 1;
 /// }
 /// ```
+///
+/// ```rust
+/// # use contracts::*;
+/// #[ensures(@result: result > ret)]
+/// fn incr(ret: usize) -> usize {
+///     ret + 1
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn ensures(attr: TokenStream, toks: TokenStream) -> TokenStream {
     let attr = attr.into();
@@ -301,6 +839,104 @@ pub fn test_ensures(attr: TokenStream, toks: TokenStream) -> TokenStream {
     implementation::ensures(ContractMode::Test, attr, toks).into()
 }
 
+/// Post-conditions that are only checked if the function returns an `Ok`
+/// value.
+///
+/// Like in [`ensures`], the returned value is accessible using `ret`, but it
+/// refers to the value inside of `Ok` instead. This can only be used on
+/// functions returning a `Result`.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// #[ensures_ok(ret > 0, "parsed numbers are positive")]
+/// fn parse_positive(s: &str) -> Result<u32, String> {
+///     match s.parse::<u32>() {
+///         Ok(0) => Err("zero is not positive".to_string()),
+///         Ok(n) => Ok(n),
+///         Err(err) => Err(err.to_string()),
+///     }
+/// }
+/// ```
+///
+/// [`ensures`]: attr.ensures.html
+#[proc_macro_attribute]
+pub fn ensures_ok(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let attr = attr.into();
+    let toks = toks.into();
+    implementation::ensures_ok(ContractMode::Always, attr, toks).into()
+}
+
+/// Post-conditions that are only checked if the function returns an `Err`
+/// value.
+///
+/// The returned error is accessible using `ret`, which refers to the value
+/// inside of `Err`. This can only be used on functions returning a `Result`.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// #[ensures_err(!ret.is_empty(), "errors have a message")]
+/// fn parse(s: &str) -> Result<u32, String> {
+///     s.parse().map_err(|_| format!("not a number: {}", s))
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn ensures_err(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let attr = attr.into();
+    let toks = toks.into();
+    implementation::ensures_err(ContractMode::Always, attr, toks).into()
+}
+
+/// Post-conditions that are only checked if the function returns a `Some`
+/// value.
+///
+/// Like in [`ensures_ok`], the returned value is accessible using `ret`, which
+/// refers to the value inside of `Some`. This can only be used on functions
+/// returning an `Option`.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// #[ensures_some(ret < items.len(), "the index is in bounds")]
+/// fn position(items: &[u32], item: u32) -> Option<usize> {
+///     items.iter().position(|x| *x == item)
+/// }
+/// ```
+///
+/// [`ensures_ok`]: attr.ensures_ok.html
+#[proc_macro_attribute]
+pub fn ensures_some(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let attr = attr.into();
+    let toks = toks.into();
+    implementation::ensures_some(ContractMode::Always, attr, toks).into()
+}
+
+/// Post-conditions that are only checked if the function returns `None`.
+///
+/// There is no value to refer to, so the conditions are usually about the
+/// arguments or the state of `self`. This can only be used on functions
+/// returning an `Option`.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// #[ensures_none(!items.contains(&item), "only missing items aren't found")]
+/// fn position(items: &[u32], item: u32) -> Option<usize> {
+///     items.iter().position(|x| *x == item)
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn ensures_none(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let attr = attr.into();
+    let toks = toks.into();
+    implementation::ensures_none(ContractMode::Always, attr, toks).into()
+}
+
 /// Invariants are conditions that have to be maintained at the "interface
 /// boundaries".
 ///
@@ -308,7 +944,28 @@ pub fn test_ensures(attr: TokenStream, toks: TokenStream) -> TokenStream {
 /// `impl` blocks.
 ///
 /// When applied to an `impl`-block all methods taking `self` (either by value
-/// or reference) will be checked for the invariant.
+/// or reference) will be checked for the invariant. With the additional
+/// `check_constructors` argument, associated functions returning `Self` are
+/// checked to establish the invariant on the returned value, like in
+/// `#[invariant(self.len <= self.cap, check_constructors)]`.
+/// Methods marked with [`no_invariant`] are left unchecked.
+///
+/// By default invariants are checked both before and after the function is
+/// run. The `when` option restricts them to `"entry"` or `"exit"`, like in
+/// `#[invariant(self.is_open(), when = "exit")]` on a constructor.
+///
+/// When applied to a `struct` or `enum` definition, the attribute can't
+/// reach the methods of the type. Instead, a hidden
+/// `__check_invariant(&self)` method is generated, which can be called
+/// wherever the invariant should hold. A public `contracts_check(&self)`
+/// method is generated as well. It evaluates the conditions without
+/// panicking and returns the kind, the condition and the description of the
+/// violated ones, which is handy in tests and fuzzers. A procedural macro
+/// crate can't export a violation type, so they are returned as a
+/// `Vec<(&'static str, &'static str, Option<&'static str>)>`. Conditions
+/// which aren't checked, because of their mode, the feature flags or their
+/// `cfg` and `category` options, aren't evaluated either. Stacked invariants
+/// on the same type are checked by the same methods.
 ///
 /// ## Example
 ///
@@ -378,6 +1035,262 @@ pub fn test_invariant(attr: TokenStream, toks: TokenStream) -> TokenStream {
     implementation::invariant(mode, attr, toks).into()
 }
 
+/// Excludes a method from the invariants of its `impl` block.
+///
+/// Some methods leave the value in a state that doesn't satisfy the invariant
+/// on purpose, like helpers doing one half of an update which another method
+/// completes. The attribute has no effect on its own.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// struct Pair {
+///     left: u32,
+///     right: u32,
+/// }
+///
+/// #[invariant(self.left == self.right)]
+/// impl Pair {
+///     pub fn bump(&mut self) {
+///         self.bump_left();
+///         self.right += 1;
+///     }
+///
+///     #[no_invariant]
+///     fn bump_left(&mut self) {
+///         self.left += 1;
+///     }
+/// }
+///
+/// let mut pair = Pair { left: 0, right: 0 };
+/// pair.bump();
+/// ```
+///
+/// [`invariant`]: attr.invariant.html
+#[proc_macro_attribute]
+pub fn no_invariant(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let attr = attr.into();
+    let toks = toks.into();
+    implementation::no_invariant(attr, toks).into()
+}
+
+/// Checks a contract condition in the middle of a function body.
+///
+/// The arguments are the same as for [`requires`], including a description
+/// as the last argument. A violation is reported in the same style as the
+/// violation of an attribute contract, and the feature flags like
+/// `disable_contracts` or `override_log` apply as well.
+///
+/// As a macro can't see the function it is used in, the message doesn't name
+/// the function. Instead of `Pre-condition of average violated: ...`, the
+/// message of the example below reads
+/// `Contract assertion violated: there are values to average: ! values.is_empty()`,
+/// followed by the location of the assertion such as ` at src/lib.rs:4`.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// fn average(values: &[u32]) -> u32 {
+///     let sum: u32 = values.iter().sum();
+///     assert_contract!(!values.is_empty(), "there are values to average");
+///     sum / values.len() as u32
+/// }
+/// # average(&[1, 2, 3]);
+/// ```
+///
+/// [`requires`]: attr.requires.html
+#[proc_macro]
+pub fn assert_contract(toks: TokenStream) -> TokenStream {
+    let toks = toks.into();
+    implementation::assert_contract(toks).into()
+}
+
+/// Wraps a function which can't be annotated, like one of another crate, in a
+/// closure checking contracts around the call.
+///
+/// The function is given as a path followed by names for its arguments, which
+/// the contracts refer to, such as `std::cmp::max(a, b)`. The arguments can be
+/// given types and a return type can be added, like for a closure. These
+/// are needed if the compiler can't infer them from the call. The contracts
+/// follow as clauses like in [`contract`]. Violations are reported using the
+/// last segment of the path as the name of the function.
+///
+/// A closure can be given instead of a function, like
+/// `wrap!(|x: i32| x + 1, ensures(ret > 0))`. Its contracts are checked just
+/// like the ones of a closure bound by an annotated `let` statement, but this
+/// works on stable compilers. Violations are reported for a function named
+/// `closure`.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// let checked_max = wrap!(
+///     std::cmp::max(a: u32, b: u32) -> u32,
+///     requires(a < 100, "a is small"),
+///     ensures(ret >= a && ret >= b),
+/// );
+///
+/// assert_eq!(checked_max(5, 7), 7);
+/// ```
+///
+/// [`contract`]: attr.contract.html
+#[proc_macro]
+pub fn wrap(toks: TokenStream) -> TokenStream {
+    let toks = toks.into();
+    implementation::wrap(toks).into()
+}
+
+/// Changes the default mode of all contracts inside of an item, such as a
+/// module, an `impl` block or a function.
+///
+/// The mode can be `debug`, `test` or `always`. Contracts without an explicit
+/// mode, like `#[requires]` or `#[ensures]`, are turned into their `debug_` or
+/// `test_` versions. Contracts with an explicit mode are more specific and
+/// are kept as they are, so a `#[test_requires]` stays a test-only contract
+/// inside of a `#[default_mode(debug)]` module.
+///
+/// The attribute needs to be applied before the contained contracts are
+/// expanded, so it has to be placed above any contract attributes of the same
+/// item.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// #[default_mode(debug)]
+/// mod checked {
+///     use contracts::*;
+///
+///     // only checked in debug builds
+///     #[requires(x > 0)]
+///     pub fn checked_incr(x: u32) -> u32 {
+///         x + 1
+///     }
+/// }
+/// # checked::checked_incr(1);
+/// ```
+#[proc_macro_attribute]
+pub fn default_mode(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let attr = attr.into();
+    let toks = toks.into();
+    implementation::default_mode(attr, toks).into()
+}
+
+/// Checks that a recursive function terminates, using a measure which
+/// decreases with every recursive call.
+///
+/// The measure is an integer expression of the function's parameters. In
+/// debug builds, it is checked to be non-negative when the function is
+/// entered. Calls of the function by its name inside of its own body are
+/// checked to pass arguments with a smaller measure.
+///
+/// This only detects direct recursion of free functions whose parameters are
+/// simple identifiers. Method calls like `self.f(...)` or `Self::f(...)`,
+/// calls through other functions and calls of generic functions with
+/// different type arguments only get the check on entry. Like other
+/// contracts, the checks can be disabled using the `disable_contracts`
+/// feature.
+///
+/// In release builds this attribute has no effect.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// #[decreases(n)]
+/// fn factorial(n: u64) -> u64 {
+///     if n == 0 {
+///         1
+///     } else {
+///         n * factorial(n - 1)
+///     }
+/// }
+/// # assert_eq!(factorial(5), 120);
+/// ```
+#[proc_macro_attribute]
+pub fn decreases(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let attr = attr.into();
+    let toks = toks.into();
+    implementation::decreases(attr, toks).into()
+}
+
+/// Marks a function as pure, so it can be called inside of contracts without
+/// changing the behaviour of the program.
+///
+/// Pure functions can't take `&mut self` or other mutable references. Methods
+/// taking `&self` could still mutate their object using interior mutability,
+/// so in debug builds the `Debug` representation of `self` is compared before
+/// and after the call. This requires `Self: Debug`.
+///
+/// In release builds this attribute has no effect.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// #[derive(Debug)]
+/// struct Library {
+///     books: Vec<String>,
+/// }
+///
+/// impl Library {
+///     #[pure]
+///     fn book_exists(&self, book_id: &str) -> bool {
+///         self.books.iter().any(|b| b == book_id)
+///     }
+///
+///     #[requires(!self.book_exists(book_id))]
+///     #[ensures(self.book_exists(book_id))]
+///     fn add_book(&mut self, book_id: &str) {
+///         self.books.push(book_id.to_string());
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn pure(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let attr = attr.into();
+    let toks = toks.into();
+    implementation::pure(attr, toks).into()
+}
+
+/// Generate a property test checking a function with [`quickcheck`].
+///
+/// The generated test is named `contract_test_` followed by the name of the
+/// function. It calls the function with random arguments, discarding the ones
+/// which don't satisfy the pre-conditions, and fails if the function panics.
+/// As the post-conditions are checked by the function itself, this includes
+/// violated post-conditions.
+///
+/// The pre-conditions are taken from the `requires` attributes following this
+/// attribute, so it has to come first, which is checked by the contracts.
+/// Only free functions without generic parameters are supported, and all
+/// arguments must implement `quickcheck::Arbitrary` and `Debug`, which rules
+/// out references. The crate using the attribute needs a (dev-)dependency on
+/// `quickcheck`.
+///
+/// ## Example
+///
+/// ```rust
+/// # use contracts::*;
+/// #[contract_test]
+/// #[requires(x < u32::MAX)]
+/// #[ensures(ret > x)]
+/// fn incr(x: u32) -> u32 {
+///     x + 1
+/// }
+/// ```
+///
+/// [`quickcheck`]: https://docs.rs/quickcheck
+#[proc_macro_attribute]
+pub fn contract_test(attr: TokenStream, toks: TokenStream) -> TokenStream {
+    let attr = attr.into();
+    let toks = toks.into();
+    implementation::contract_test(attr, toks).into()
+}
+
 /// A "contract_trait" is a trait which ensures all implementors respect all
 /// provided contracts.
 ///
@@ -387,8 +1300,21 @@ pub fn test_invariant(attr: TokenStream, toks: TokenStream) -> TokenStream {
 /// When this attribute is applied to an `impl Trait for Type` item, the
 /// implementation gets modified so it matches the trait definition.
 ///
+/// The contracts of the trait methods are part of the generated
+/// documentation, in the same "Contracts" section as for free functions.
+///
+/// Methods of an implementation can have contracts of their own, which are
+/// checked in addition to the ones of the trait and listed in an "Additional
+/// contracts" section of the documentation of the `impl` block. Callers only
+/// know the contracts of the trait, so to keep implementations substitutable
+/// for each other, additional pre-conditions should follow from the ones of
+/// the trait, while additional post-conditions may promise more than the
+/// trait does. This is not checked by the attribute.
+///
 /// **When the `#[contract_trait]` is not applied to either the trait or an
-/// `impl` it will cause compile errors**.
+/// `impl` it will cause compile errors**. These errors mention a hidden
+/// `__contracts_missing_contract_trait_attribute` method, which is missing
+/// from or unknown to the trait in that case.
 ///
 /// ## Example
 ///
@@ -413,22 +1339,7 @@ pub fn test_invariant(attr: TokenStream, toks: TokenStream) -> TokenStream {
 /// ```
 #[proc_macro_attribute]
 pub fn contract_trait(attrs: TokenStream, toks: TokenStream) -> TokenStream {
-    let attrs: proc_macro2::TokenStream = attrs.into();
-    let toks: proc_macro2::TokenStream = toks.into();
-
-    let item: syn::Item = syn::parse_quote!(#toks);
-
-    let tts = match item {
-        syn::Item::Trait(trait_) => implementation::contract_trait_item_trait(attrs, trait_),
-        syn::Item::Impl(impl_) => {
-            assert!(
-                impl_.trait_.is_some(),
-                "#[contract_trait] can only be applied to `trait` and `impl ... for` items"
-            );
-            implementation::contract_trait_item_impl(attrs, impl_)
-        }
-        _ => panic!("#[contract_trait] can only be applied to `trait` and `impl ... for` items"),
-    };
-
-    tts.into()
+    let attrs = attrs.into();
+    let toks = toks.into();
+    implementation::contract_trait(attrs, toks).into()
 }
@@ -0,0 +1,133 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![cfg(not(any(
+    feature = "disable_contracts",
+    feature = "disable_pre",
+    feature = "disable_post",
+    feature = "disable_invariants",
+    feature = "override_log",
+    feature = "mirai_assertions",
+    feature = "public_only"
+)))]
+
+use contracts::*;
+
+/// Audit log keeping the last records of the current thread.
+mod audit_log {
+    use std::{cell::RefCell, collections::VecDeque, time::Instant};
+
+    const CAPACITY: usize = 4;
+
+    #[derive(Debug)]
+    pub struct Record {
+        pub kind: &'static str,
+        pub func: &'static str,
+        pub condition: &'static str,
+        pub passed: bool,
+        #[allow(dead_code)]
+        pub at: Instant,
+    }
+
+    thread_local! {
+        static RECORDS: RefCell<VecDeque<Record>> = const { RefCell::new(VecDeque::new()) };
+    }
+
+    pub fn record(kind: &'static str, func: &'static str, condition: &'static str, passed: bool) {
+        RECORDS.with(|records| {
+            let mut records = records.borrow_mut();
+
+            if records.len() == CAPACITY {
+                records.pop_front();
+            }
+
+            records.push_back(Record {
+                kind,
+                func,
+                condition,
+                passed,
+                at: Instant::now(),
+            });
+        });
+    }
+
+    pub fn drain() -> Vec<Record> {
+        RECORDS.with(|records| records.borrow_mut().drain(..).collect())
+    }
+}
+
+#[test]
+fn test_audit_records_evaluations() {
+    #[requires(x < 100, audit = "audit_log::record")]
+    #[ensures(ret > x, "result is bigger", audit = "audit_log::record")]
+    fn incr(x: usize) -> usize {
+        x + 1
+    }
+
+    incr(1);
+
+    let records = audit_log::drain();
+    let summary = records
+        .iter()
+        .map(|record| (record.kind, record.func, record.condition, record.passed))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        summary,
+        [
+            ("Pre-condition", "incr", "x < 100", true),
+            ("Post-condition", "incr", "ret > x", true),
+        ]
+    );
+    assert!(audit_log::drain().is_empty());
+}
+
+#[test]
+fn test_audit_keeps_last_records() {
+    struct Counter(usize);
+
+    #[invariant(self.0 < 3, audit = "audit_log::record")]
+    impl Counter {
+        fn incr(&mut self) {
+            self.0 += 1;
+        }
+    }
+
+    let result = std::panic::catch_unwind(|| {
+        let mut counter = Counter(0);
+
+        for _ in 0..3 {
+            counter.incr();
+        }
+    });
+    assert!(result.is_err());
+
+    let records = audit_log::drain();
+    let outcomes = records
+        .iter()
+        .map(|record| (record.kind, record.passed))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        outcomes,
+        [
+            ("Invariant (as pre-condition)", true),
+            ("Invariant (as post-condition)", true),
+            ("Invariant (as pre-condition)", true),
+            ("Invariant (as post-condition)", false),
+        ]
+    );
+}
+
+#[test]
+fn test_unaudited_contracts() {
+    #[requires(x < 100)]
+    fn decr(x: usize) -> usize {
+        x - 1
+    }
+
+    decr(1);
+
+    assert!(audit_log::drain().is_empty());
+}
@@ -0,0 +1,52 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![cfg(not(any(
+    feature = "disable_contracts",
+    feature = "disable_pre",
+    feature = "public_only"
+)))]
+
+use contracts::*;
+
+#[cfg(feature = "mirai_assertions")]
+mod mirai_assertion_mocks;
+
+#[requires(!token.is_empty(), category = "security")]
+fn authorize(token: &str) -> bool {
+    token.len() > 3
+}
+
+#[ensures(ret.windows(2).all(|pair| pair[0] <= pair[1]), category = "expensive")]
+fn sorted(mut items: Vec<u32>) -> Vec<u32> {
+    items.sort_unstable();
+    // break the post-condition, which is only noticed if it is checked
+    items.reverse();
+    items
+}
+
+// the enabled categories are read once, so this is the only test of this file
+#[test]
+fn enabled_categories() {
+    if option_env!("CONTRACTS_CATEGORIES").is_none() {
+        std::env::set_var("CONTRACTS_CATEGORIES", "security, logging");
+    }
+
+    let enabled = |category: &str| {
+        option_env!("CONTRACTS_CATEGORIES")
+            .unwrap_or("security, logging")
+            .split(',')
+            .any(|enabled| enabled.trim() == category)
+    };
+
+    assert!(authorize("secret"));
+    assert_eq!(
+        std::panic::catch_unwind(|| authorize("")).is_err(),
+        enabled("security")
+    );
+    assert_eq!(
+        std::panic::catch_unwind(|| sorted(vec![2, 1, 3])).is_err(),
+        enabled("expensive")
+    );
+}
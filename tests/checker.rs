@@ -0,0 +1,73 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use contracts::*;
+
+#[cfg(feature = "mirai_assertions")]
+mod mirai_assertion_mocks;
+
+#[requires(y != 0, x >= y, checker = "can_div")]
+#[ensures(ret >= 1)]
+fn div(x: u32, y: u32) -> u32 {
+    x / y
+}
+
+#[requires(!name.is_empty(), "names are not empty", checker = "is_name")]
+fn greet(mut name: String) -> String {
+    name.insert_str(0, "Hello ");
+    name
+}
+
+struct Stack(Vec<u32>);
+
+impl Stack {
+    #[requires(!self.0.is_empty(), checker = "can_pop")]
+    fn pop(&mut self) -> u32 {
+        self.0.pop().unwrap()
+    }
+
+    #[requires(self.0.len() < 2, checker = "can_take_first")]
+    fn into_first(self, fallback: u32) -> u32 {
+        self.0.first().copied().unwrap_or(fallback)
+    }
+}
+
+#[requires(values.len() > 1, values[0] <= values[1], checker)]
+fn spread(values: Vec<u32>) -> u32 {
+    values[1] - values[0]
+}
+
+#[test]
+fn test_function_checkers() {
+    assert!(can_div(&4, &2));
+    assert!(!can_div(&4, &0));
+    assert!(!can_div(&1, &2));
+    assert_eq!(div(4, 2), 2);
+
+    // the arguments are only borrowed by the checker
+    let name = "you".to_string();
+    assert!(is_name(&name));
+    assert!(!is_name(&String::new()));
+    assert_eq!(greet(name), "Hello you");
+}
+
+#[test]
+fn test_default_checker_name() {
+    let values = vec![1, 3];
+    assert!(__check_pre_spread(&values));
+    assert!(!__check_pre_spread(&vec![3, 1]));
+    assert_eq!(spread(values), 2);
+}
+
+#[test]
+fn test_method_checkers() {
+    let mut stack = Stack(vec![1]);
+
+    assert!(stack.can_pop());
+    assert_eq!(stack.pop(), 1);
+    assert!(!stack.can_pop());
+
+    assert!(stack.can_take_first(&3));
+    assert_eq!(stack.into_first(3), 3);
+}
@@ -0,0 +1,56 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+#![cfg(all(
+    feature = "collect_failures",
+    not(feature = "disable_contracts"),
+    not(feature = "override_log"),
+    not(feature = "mirai_assertions")
+))]
+
+use contracts::*;
+
+#[requires(start <= end, "range is ordered")]
+#[requires(end <= 100, "range is bounded")]
+#[requires(step > 0)]
+#[ensures(ret <= 100)]
+#[ensures(ret % step == 0)]
+fn last_step(start: u32, end: u32, step: u32) -> u32 {
+    (start..end)
+        .step_by(step as usize)
+        .next_back()
+        .unwrap_or(start)
+}
+
+fn panic_message(f: impl FnOnce() + std::panic::UnwindSafe) -> String {
+    let payload = std::panic::catch_unwind(f).unwrap_err();
+    payload
+        .downcast_ref::<String>()
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[test]
+fn all_failures_are_reported() {
+    let message = panic_message(|| {
+        last_step(200, 150, 1);
+    });
+    let lines = message.lines().collect::<Vec<_>>();
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("Pre-condition of last_step violated: range is ordered"));
+    assert!(lines[1].starts_with("Pre-condition of last_step violated: range is bounded"));
+}
+
+#[test]
+fn phases_are_reported_separately() {
+    let message = panic_message(|| {
+        last_step(3, 10, 4);
+    });
+
+    assert!(message.starts_with("Post-condition of last_step violated: ret % step == 0"));
+    assert_eq!(message.lines().count(), 1);
+    assert_eq!(last_step(0, 10, 5), 5);
+}
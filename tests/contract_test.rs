@@ -0,0 +1,54 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use contracts::*;
+
+#[cfg(feature = "mirai_assertions")]
+mod mirai_assertion_mocks;
+
+// the generated tests are run by the test harness like any other test
+
+#[contract_test]
+#[requires(x < u32::MAX)]
+#[ensures(ret > x)]
+fn incr(x: u32) -> u32 {
+    x + 1
+}
+
+#[contract_test]
+#[requires(y != 0, "no division by zero")]
+#[ensures(ret <= x)]
+#[must_use]
+fn checked_div(x: u32, y: u32) -> u32 {
+    x / y
+}
+
+// odd inputs violate the pre-condition, so the generated test only passes if
+// they are discarded
+#[contract_test]
+#[requires(x % 2 == 0, "only even numbers are halved")]
+#[ensures(ret * 2 == x)]
+fn halve(x: u32) -> u32 {
+    x / 2
+}
+
+#[test]
+fn test_generated_tests_can_be_called() {
+    contract_test_incr();
+    contract_test_halve();
+}
+
+#[cfg(not(any(feature = "disable_contracts", feature = "disable_post")))]
+#[test]
+#[should_panic]
+#[allow(unnameable_test_items)]
+fn test_fails_on_violated_post_conditions() {
+    #[contract_test]
+    #[ensures(ret > x)]
+    fn broken_incr(x: u32) -> u32 {
+        x
+    }
+
+    contract_test_broken_incr();
+}
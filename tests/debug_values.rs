@@ -0,0 +1,87 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+#![cfg(all(feature = "debug_values", not(feature = "mirai_assertions")))]
+
+use contracts::*;
+
+#[test]
+fn test_values_hold() {
+    #[requires(x != 0)]
+    #[ensures(ret >= x)]
+    fn double(x: usize) -> usize {
+        x * 2
+    }
+
+    assert_eq!(double(4), 8);
+}
+
+#[test]
+#[should_panic(expected = "Post-condition of three violated: ret > x (ret = 3, x = 5)")]
+fn test_post_values() {
+    #[ensures(ret > x)]
+    fn three(x: usize) -> usize {
+        let _ = x;
+        3
+    }
+
+    three(5);
+}
+
+#[test]
+#[should_panic(expected = "name is set: name.len() > limit (name.len() = 3, limit = 4)")]
+fn test_pre_values() {
+    #[requires(name.len() > limit, "name is set")]
+    fn greet(name: &str, limit: usize) {
+        let _ = (name, limit);
+    }
+
+    greet("Bob", 4);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "left: 1\n right: 2")]
+fn test_old_values() {
+    #[debug_ensures(*total == old(*total) + 1)]
+    fn incr(total: &mut u32) {
+        *total *= 1;
+    }
+
+    incr(&mut 1);
+}
+
+#[test]
+fn test_equality_operands() {
+    #[ensures(ret == x * 2, "doubles")]
+    fn double(x: u32) -> u32 {
+        x + 2
+    }
+
+    #[requires(a != b)]
+    fn distinct(a: &str, b: &str) {
+        let _ = (a, b);
+    }
+
+    let message = |result: std::thread::Result<()>| {
+        let payload = result.unwrap_err();
+        payload
+            .downcast_ref::<String>()
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    let eq = message(std::panic::catch_unwind(|| {
+        double(3);
+    }));
+    assert!(eq.starts_with(
+        "assertion `left == right` failed: Post-condition of double violated: doubles: ret == x * 2"
+    ));
+    assert!(eq.contains("left: 5\n right: 6"));
+
+    let ne = message(std::panic::catch_unwind(|| distinct("a", "a")));
+    assert!(ne.starts_with("assertion `left != right` failed: Pre-condition of distinct violated"));
+    assert!(ne.contains("left: \"a\"\n right: \"a\""));
+}
@@ -0,0 +1,70 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![cfg(not(feature = "disable_contracts"))]
+
+use contracts::*;
+
+#[cfg(feature = "mirai_assertions")]
+mod mirai_assertion_mocks;
+
+#[test]
+fn test_decreasing_measure() {
+    #[decreases(a + b)]
+    fn shrink(a: u64, b: u64) -> u64 {
+        if a == 0 {
+            b + 1
+        } else if b == 0 {
+            shrink(a - 1, 1)
+        } else {
+            a + b
+        }
+    }
+
+    #[decreases(values.len())]
+    #[ensures(ret >= values.iter().copied().max().unwrap_or(0))]
+    fn max(values: &[u32]) -> u32 {
+        match values {
+            [] => 0,
+            [first, rest @ ..] => (*first).max(max(rest)),
+        }
+    }
+
+    assert_eq!(shrink(0, 3), 4);
+    assert_eq!(shrink(2, 3), 5);
+    assert_eq!(max(&[3, 8, 1]), 8);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "Termination measure of count_up violated: measure does not decrease")]
+fn test_increasing_measure() {
+    #[decreases(limit - n)]
+    fn count_up(n: i32, limit: i32) -> i32 {
+        if n >= limit {
+            n
+        } else {
+            // oops, `n` moves away from the limit
+            count_up(n - 1, limit)
+        }
+    }
+
+    count_up(1, 10);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "Termination measure of sum violated: measure is negative")]
+fn test_negative_measure() {
+    #[decreases(n)]
+    fn sum(n: i32) -> i32 {
+        if n <= 0 {
+            0
+        } else {
+            n + sum(n - 1)
+        }
+    }
+
+    sum(-1);
+}
@@ -0,0 +1,63 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use contracts::*;
+
+#[cfg(feature = "mirai_assertions")]
+mod mirai_assertion_mocks;
+
+#[contracts::default_mode(debug)]
+mod library {
+    use contracts::*;
+
+    #[derive(Default)]
+    pub struct Library {
+        pub books: Vec<u32>,
+    }
+
+    #[invariant(self.books.len() < 10)]
+    impl Library {
+        #[requires(!self.books.contains(&id))]
+        #[ensures(self.books.contains(&id))]
+        #[test_ensures(!self.books.is_empty())]
+        pub fn add(&mut self, id: u32) {
+            self.books.push(id);
+        }
+    }
+
+    #[contract(requires(x > 0), ensures(ret > x))]
+    pub fn incr(x: u32) -> u32 {
+        x + 1
+    }
+}
+
+#[test]
+fn test_default_mode_module() {
+    let mut library = library::Library::default();
+    library.add(1);
+    library.add(2);
+
+    assert_eq!(library::incr(1), 2);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "Pre-condition of add violated")]
+fn test_default_mode_violation() {
+    let mut library = library::Library::default();
+    library.add(1);
+    library.add(1);
+}
+
+#[test]
+fn test_default_mode_function() {
+    #[default_mode(test)]
+    #[requires(x < 100)]
+    fn double(x: u32) -> u32 {
+        x * 2
+    }
+
+    assert_eq!(double(4), 8);
+}
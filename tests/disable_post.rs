@@ -0,0 +1,42 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+#![cfg(all(
+    feature = "disable_post",
+    not(feature = "disable_pre"),
+    not(feature = "disable_contracts")
+))]
+
+use std::cell::Cell;
+
+use contracts::*;
+
+thread_local! {
+    static SNAPSHOTS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn snapshot(x: u32) -> u32 {
+    SNAPSHOTS.with(|count| count.set(count.get() + 1));
+    x
+}
+
+#[requires(x > 0, "x must be positive")]
+#[ensures(ret == x, "never holds")]
+#[ensures(ret > old(snapshot(x)))]
+fn incr(x: u32) -> u32 {
+    x + 1
+}
+
+#[test]
+fn test_post_conditions_disabled() {
+    assert_eq!(incr(1), 2);
+    assert_eq!(SNAPSHOTS.with(Cell::get), 0);
+}
+
+#[test]
+#[should_panic(expected = "x must be positive")]
+fn test_pre_conditions_kept() {
+    incr(0);
+}
@@ -0,0 +1,59 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! Contracts with the `force_always` flag are checked in release builds, even
+//! with `override_debug`. Run with
+//! `cargo test --release --features override_debug --test force_always`.
+
+#![cfg(not(any(
+    feature = "disable_contracts",
+    feature = "mirai_assertions",
+    feature = "public_only",
+    feature = "disable_invariants"
+)))]
+
+use contracts::*;
+
+struct Valve {
+    pressure: u32,
+}
+
+#[invariant(self.pressure <= 100, "pressure is within bounds", force_always)]
+#[invariant(self.pressure % 2 == 0, "pressure is even")]
+impl Valve {
+    fn raise(&mut self, amount: u32) {
+        self.pressure += amount;
+    }
+}
+
+#[test]
+#[should_panic(expected = "pressure is within bounds")]
+fn forced_invariant_is_checked() {
+    let mut valve = Valve { pressure: 90 };
+    valve.raise(20);
+}
+
+#[test]
+#[cfg_attr(
+    debug_assertions,
+    should_panic(expected = "Pre-condition of open violated")
+)]
+fn forced_debug_contract_keeps_its_mode() {
+    #[debug_requires(valve.pressure > 0, force_always)]
+    fn open(valve: &mut Valve) {
+        valve.pressure = 0;
+    }
+
+    open(&mut Valve { pressure: 0 });
+}
+
+#[test]
+#[cfg(all(feature = "override_debug", not(debug_assertions)))]
+fn other_contracts_are_downgraded() {
+    let mut valve = Valve { pressure: 10 };
+    valve.raise(1);
+
+    assert_eq!(valve.pressure, 11);
+}
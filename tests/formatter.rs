@@ -0,0 +1,67 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![cfg(not(feature = "disable_contracts"))]
+
+use contracts::*;
+
+#[cfg(feature = "mirai_assertions")]
+mod mirai_assertion_mocks;
+
+fn house_style(kind: &str, function: &str, condition: &str, description: Option<&str>) -> String {
+    format!(
+        "[{}] {} broke `{}` ({})",
+        kind.to_uppercase(),
+        function,
+        condition,
+        description.unwrap_or("no description")
+    )
+}
+
+mod style {
+    pub fn short(kind: &str, function: &str, _: &str, _: Option<&str>) -> String {
+        format!("{} {}", kind, function)
+    }
+}
+
+#[requires(x > 0, "x was {x}", interpolate, formatter = "house_style")]
+#[ensures(ret < 100, formatter = "house_style")]
+fn scale(x: u32) -> u32 {
+    x * 10
+}
+
+#[requires(!name.is_empty(), formatter = "style::short")]
+fn greet(name: &str) -> String {
+    format!("Hello {}", name)
+}
+
+fn panic_message(f: impl FnOnce() + std::panic::UnwindSafe) -> String {
+    let payload = std::panic::catch_unwind(f).unwrap_err();
+    payload.downcast_ref::<String>().unwrap().clone()
+}
+
+#[test]
+fn custom_messages() {
+    assert_eq!(scale(5), 50);
+    assert_eq!(greet("you"), "Hello you");
+
+    assert_eq!(
+        panic_message(|| {
+            scale(0);
+        }),
+        "[PRE-CONDITION] scale broke `x > 0` (x was 0)"
+    );
+    assert_eq!(
+        panic_message(|| {
+            scale(20);
+        }),
+        "[POST-CONDITION] scale broke `ret < 100` (no description)"
+    );
+    assert_eq!(
+        panic_message(|| {
+            greet("");
+        }),
+        "Pre-condition greet"
+    );
+}
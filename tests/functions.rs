@@ -51,6 +51,19 @@ fn test_sort() {
     sort(&input);
 }
 
+#[test]
+#[should_panic(expected = "Pre-condition of halve violated")]
+fn test_test_requires() {
+    #[test_requires(x % 2 == 0)]
+    #[test_ensures(ret * 2 == x)]
+    fn halve(x: u32) -> u32 {
+        x / 2
+    }
+
+    assert_eq!(halve(4), 2);
+    halve(3);
+}
+
 #[test]
 fn test_invariant() {
     #[invariant(*val <= 10)]
@@ -169,3 +182,900 @@ fn test_braced_condition_expression_clippy() {
     })]
     fn param_test(_x: i32, __y: i32) {}
 }
+
+#[test]
+fn test_ret_binder() {
+    #[ensures(@result: result == ret + 1, "named result")]
+    #[ensures(ret < 10)]
+    fn incr(ret: u32) -> u32 {
+        ret + 1
+    }
+
+    #[ensures(@out: out.len() == input.len())]
+    #[ensures(!input.is_empty() -> matches!(ret.first(), Some(x) if *x == input[0]))]
+    fn copy(input: &[u32]) -> Vec<u32> {
+        input.to_vec()
+    }
+
+    assert_eq!(incr(3), 4);
+    assert_eq!(copy(&[1, 2]), [1, 2]);
+}
+
+#[test]
+#[should_panic(expected = "Post-condition of decr violated")]
+fn test_ret_binder_violation() {
+    #[ensures(@result: result < ret)]
+    fn decr(ret: u32) -> u32 {
+        ret // oops
+    }
+
+    decr(3);
+}
+
+#[test]
+fn test_ret_after_colon() {
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Point {
+        x: u32,
+        y: u32,
+    }
+
+    #[ensures(Point { x: ret.x, y: ret.y } == ret)]
+    #[ensures({ let shifted: Point = ret; shifted.x > 0 })]
+    fn shift(p: Point) -> Point {
+        Point { x: p.x + 1, ..p }
+    }
+
+    assert!(shift(Point { x: 0, y: 2 }) == Point { x: 1, y: 2 });
+}
+
+#[test]
+fn test_extern_c_symbol() {
+    #[no_mangle]
+    #[requires(x % 2 == 0, "x has to be even")]
+    #[ensures(ret * 2 == x)]
+    pub extern "C" fn __contracts_test_halve(x: u32) -> u32 {
+        x / 2
+    }
+
+    extern "C" {
+        #[link_name = "__contracts_test_halve"]
+        fn halve_by_symbol(x: u32) -> u32;
+    }
+
+    assert_eq!(__contracts_test_halve(8), 4);
+    assert_eq!(unsafe { halve_by_symbol(6) }, 3);
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of __contracts_test_unwinding_halve violated")]
+fn test_extern_c_unwind_violation() {
+    #[no_mangle]
+    #[requires(x % 2 == 0, "x has to be even")]
+    pub extern "C-unwind" fn __contracts_test_unwinding_halve(x: u32) -> u32 {
+        x / 2
+    }
+
+    __contracts_test_unwinding_halve(3);
+}
+
+#[test]
+#[should_panic(expected = "Post-condition of normalize violated: all must hold: ret.len() <= 8")]
+fn test_shared_description_names_condition() {
+    #[ensures(!ret.is_empty(), ret.len() <= 8, ret.is_ascii(), "all must hold")]
+    fn normalize(name: &str) -> String {
+        name.trim().to_lowercase()
+    }
+
+    normalize("Contracts");
+}
+
+#[test]
+fn test_requires_or_return() {
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        TooSmall,
+        TooLarge(usize),
+    }
+
+    #[requires_or_return(x > 0, || Error::TooSmall)]
+    #[requires_or_return(x < 10, "x must be small", Error::TooLarge(x))]
+    #[ensures(ret.is_ok())]
+    fn check(x: usize) -> Result<usize, Error> {
+        Ok(x * 2)
+    }
+
+    assert_eq!(check(3), Ok(6));
+    assert_eq!(check(0), Err(Error::TooSmall));
+    assert_eq!(check(12), Err(Error::TooLarge(12)));
+}
+
+#[test]
+fn test_log_options() {
+    #[requires(x > 0, level = "warn", target = "contracts::tests")]
+    #[ensures(ret > x, "result grows", level = "info")]
+    fn incr(x: usize) -> usize {
+        x + 1
+    }
+
+    incr(3);
+}
+
+#[test]
+fn test_ensures_ok_err() {
+    #[ensures_ok(ret >= min, "value is at least min")]
+    #[ensures_err(ret.contains("small"))]
+    #[ensures(ret.is_ok() == (x >= min))]
+    fn at_least(x: u32, min: u32) -> Result<u32, String> {
+        if x < min {
+            return Err(format!("{} is too small", x));
+        }
+
+        Ok(x)
+    }
+
+    #[ensures_ok(@value: value == old(*counter) + 1)]
+    fn incr(counter: &mut u32) -> Result<u32, ()> {
+        *counter += 1;
+        Ok(*counter)
+    }
+
+    assert_eq!(at_least(5, 3), Ok(5));
+    assert!(at_least(1, 3).is_err());
+    assert_eq!(incr(&mut 1), Ok(2));
+}
+
+#[test]
+#[should_panic(expected = "Post-condition (on `Ok`) of broken violated")]
+fn test_ensures_ok_violation() {
+    #[ensures_ok(ret > 0)]
+    fn broken(fail: bool) -> Result<i32, ()> {
+        if fail {
+            Err(())
+        } else {
+            Ok(0)
+        }
+    }
+
+    let _ = broken(true);
+    let _ = broken(false);
+}
+
+#[derive(Debug, PartialEq)]
+enum LookupError {
+    NotFound,
+    Empty,
+}
+
+#[test]
+fn test_ensures_err_binding() {
+    #[ensures_err(@err: matches!(err, LookupError::NotFound) -> !names.is_empty())]
+    #[ensures_err(@err: matches!(err, LookupError::Empty) -> names.is_empty())]
+    fn lookup(names: &[&str], name: &str) -> Result<usize, LookupError> {
+        if names.is_empty() {
+            return Err(LookupError::Empty);
+        }
+
+        names
+            .iter()
+            .position(|n| *n == name)
+            .ok_or(LookupError::NotFound)
+    }
+
+    assert_eq!(lookup(&["a", "b"], "b"), Ok(1));
+    assert_eq!(lookup(&["a", "b"], "c"), Err(LookupError::NotFound));
+    assert_eq!(lookup(&[], "c"), Err(LookupError::Empty));
+}
+
+#[test]
+fn test_ensures_some_none() {
+    #[ensures_some(names[ret] == name)]
+    #[ensures_none(!names.contains(&name))]
+    fn find(names: &[&str], name: &str) -> Option<usize> {
+        names.iter().position(|n| *n == name)
+    }
+
+    #[ensures_some(@word: !word.contains(' '))]
+    fn first_word(text: &str) -> Option<&str> {
+        text.split_whitespace().next()
+    }
+
+    assert_eq!(find(&["a", "b"], "b"), Some(1));
+    assert_eq!(find(&["a", "b"], "c"), None);
+    assert_eq!(first_word("hello world"), Some("hello"));
+    assert_eq!(first_word(" "), None);
+}
+
+#[test]
+#[should_panic(expected = "Post-condition (on `None`) of broken_find violated")]
+fn test_ensures_none_violation() {
+    #[ensures_none(!names.contains(&name))]
+    fn broken_find(names: &[&str], name: &str) -> Option<usize> {
+        if names.len() > 1 {
+            return None;
+        }
+
+        names.iter().position(|n| *n == name)
+    }
+
+    assert_eq!(broken_find(&["a"], "a"), Some(0));
+    broken_find(&["a", "b"], "b");
+}
+
+#[test]
+fn test_impl_iterator_ret() {
+    #[ensures(ret.clone().count() == n)]
+    #[ensures(ret.len() == n)]
+    fn numbers(n: usize) -> impl ExactSizeIterator<Item = usize> + Clone {
+        0..n
+    }
+
+    assert_eq!(numbers(3).sum::<usize>(), 3);
+}
+
+#[test]
+fn test_assert_contract() {
+    fn average(values: &[u32]) -> u32 {
+        let sum: u32 = values.iter().sum();
+        assert_contract!(!values.is_empty(), values.contains(&1) -> sum >= 1);
+        sum / values.len() as u32
+    }
+
+    assert_eq!(average(&[1, 2, 3]), 2);
+}
+
+#[test]
+fn test_assert_contract_violation() {
+    const ASSERTION_LINE: u32 = line!() + 2;
+    fn average(values: &[u32]) -> u32 {
+        assert_contract!(!values.is_empty(), "there are values");
+        values.iter().sum::<u32>() / values.len() as u32
+    }
+
+    let payload = std::panic::catch_unwind(|| average(&[])).unwrap_err();
+    let expected = format!(
+        "Contract assertion violated: there are values: ! values.is_empty() at {}:{}",
+        file!(),
+        ASSERTION_LINE
+    );
+
+    assert_eq!(payload.downcast_ref::<String>(), Some(&expected));
+}
+
+#[test]
+fn test_question_mark_operator() {
+    #[derive(Debug, PartialEq)]
+    struct ParseError;
+
+    impl From<std::num::ParseIntError> for ParseError {
+        fn from(_: std::num::ParseIntError) -> Self {
+            ParseError
+        }
+    }
+
+    // errors returned by `?` are converted and checked by the
+    // post-conditions, just like an early `return`
+    #[ensures(ret.is_ok() -> *ret.as_ref().unwrap() < 100)]
+    #[ensures_ok(ret < 100)]
+    #[ensures_err(ret == ParseError)]
+    fn parse_small(input: &str) -> Result<u32, ParseError> {
+        let value: u32 = input.trim().parse()?;
+
+        for candidate in 0.. {
+            if candidate == value {
+                return Ok(candidate % 100);
+            }
+        }
+
+        unreachable!()
+    }
+
+    #[ensures(ret.is_some() -> ret.unwrap() > 0)]
+    fn first_positive(values: &[i32]) -> Option<i32> {
+        let first = *values.first()?;
+
+        if first > 0 {
+            Some(first)
+        } else {
+            values.iter().copied().find(|x| *x > 0)
+        }
+    }
+
+    assert_eq!(parse_small("42"), Ok(42));
+    assert_eq!(parse_small("142"), Ok(42));
+    assert_eq!(parse_small("x"), Err(ParseError));
+    assert_eq!(first_positive(&[]), None);
+    assert_eq!(first_positive(&[-1, 3]), Some(3));
+}
+
+#[test]
+#[should_panic(expected = "Post-condition (on `Err`) of parse violated")]
+fn test_question_mark_checked() {
+    #[ensures_err(ret.to_string().is_empty())]
+    fn parse(input: &str) -> Result<u32, std::num::ParseIntError> {
+        let value = input.parse()?;
+        Ok(value)
+    }
+
+    let _ = parse("x");
+}
+
+#[test]
+fn test_question_mark_type_alias() {
+    type Res<T> = Result<T, std::num::ParseIntError>;
+    type Maybe<T> = Option<T>;
+
+    #[ensures(ret.is_err() -> input.is_empty(), "only empty input is invalid")]
+    fn parse(input: &str) -> Res<u32> {
+        let value = input.parse()?;
+        Ok(value)
+    }
+
+    #[ensures(ret.is_some())]
+    fn first(values: &[u32]) -> Maybe<u32> {
+        let first = values.first()?;
+        Some(*first)
+    }
+
+    assert_eq!(parse("42"), Ok(42));
+    assert!(parse("").is_err());
+    assert_eq!(first(&[1]), Some(1));
+
+    let result = std::panic::catch_unwind(|| parse("x"));
+    assert!(result.is_err());
+
+    let result = std::panic::catch_unwind(|| first(&[]));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_question_mark_poll_and_control_flow() {
+    use std::{num::ParseIntError, ops::ControlFlow, task::Poll};
+
+    #[ensures(ret.is_ready())]
+    fn parse(input: Poll<&str>) -> Poll<Result<u32, ParseIntError>> {
+        let input = match input {
+            Poll::Ready(input) => input,
+            Poll::Pending => return Poll::Ready(Ok(0)),
+        };
+        let value = input.parse::<u32>()?;
+        Poll::Ready(Ok(value))
+    }
+
+    #[ensures(ret.is_ready())]
+    fn forward(inner: Poll<Result<u32, ParseIntError>>) -> Poll<Result<u32, ParseIntError>> {
+        match inner? {
+            Poll::Ready(value) => Poll::Ready(Ok(value + 1)),
+            Poll::Pending => Poll::Ready(Ok(0)),
+        }
+    }
+
+    #[ensures(ret.is_ready())]
+    fn next(
+        inner: Poll<Option<Result<u32, ParseIntError>>>,
+    ) -> Poll<Option<Result<u32, ParseIntError>>> {
+        match inner? {
+            Poll::Ready(value) => Poll::Ready(value.map(|value| Ok(value * 2))),
+            Poll::Pending => Poll::Ready(None),
+        }
+    }
+
+    #[ensures(ret.is_continue())]
+    fn step(flow: ControlFlow<&'static str, u32>) -> ControlFlow<&'static str, u32> {
+        let value = flow?;
+        ControlFlow::Continue(value + 1)
+    }
+
+    assert_eq!(parse(Poll::Ready("42")), Poll::Ready(Ok(42)));
+    assert!(matches!(parse(Poll::Ready("x")), Poll::Ready(Err(_))));
+    assert_eq!(forward(Poll::Ready(Ok(1))), Poll::Ready(Ok(2)));
+    assert_eq!(forward(Poll::Pending), Poll::Ready(Ok(0)));
+    assert_eq!(next(Poll::Ready(Some(Ok(2)))), Poll::Ready(Some(Ok(4))));
+    assert_eq!(next(Poll::Ready(None)), Poll::Ready(None));
+    assert_eq!(step(ControlFlow::Continue(1)), ControlFlow::Continue(2));
+
+    assert!(matches!(
+        next(Poll::Ready(Some("x".parse()))),
+        Poll::Ready(Some(Err(_)))
+    ));
+
+    // the post-conditions are checked on the early returns of `?` as well
+    let result = std::panic::catch_unwind(|| step(ControlFlow::Break("stop")));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_nested_returns() {
+    #[ensures(ret > 0)]
+    fn outer(x: u32) -> u32 {
+        fn inner(x: u32) -> Option<u32> {
+            if x == 0 {
+                return None;
+            }
+
+            let doubled = x.checked_mul(2)?;
+            Some(doubled)
+        }
+
+        let closure = |x: u32| -> Result<u32, ()> {
+            let doubled = inner(x).ok_or(())?;
+            Ok(doubled)
+        };
+
+        closure(x).unwrap_or(1)
+    }
+
+    assert_eq!(outer(0), 1);
+    assert_eq!(outer(2), 4);
+}
+
+#[test]
+fn test_ret_borrowed() {
+    #[ensures(ret == "contracts")]
+    #[ensures(matches!(ret.as_str(), "contracts"))]
+    #[ensures(ret.chars().count() == ret.len())]
+    fn name() -> String {
+        String::from("contracts")
+    }
+
+    assert_eq!(name(), "contracts");
+}
+
+#[test]
+fn test_generated_names() {
+    #[ensures(ret == old(run) + 1)]
+    #[ensures(ret > 0)]
+    fn incr(run: u32) -> u32 {
+        let ret = run;
+
+        let mut result = 0;
+        'run: for i in 0..=ret + 1 {
+            if i > ret {
+                result = i;
+                break 'run;
+            }
+        }
+
+        result
+    }
+
+    assert_eq!(incr(2), 3);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "Pre-condition of halve violated")]
+fn test_mode_option() {
+    #[requires(x % 2 == 0, mode = "debug")]
+    #[ensures(ret * 2 == x, mode = "test")]
+    fn halve(x: u32) -> u32 {
+        x / 2
+    }
+
+    assert_eq!(halve(4), 2);
+    halve(3);
+}
+
+#[test]
+fn test_cfg_option() {
+    // any feature of the crate using the contracts can be used, this test
+    // relies on one of the features of `contracts` itself
+    #[ensures(ret < 5, cfg = "debug_values")]
+    fn ten() -> u32 {
+        10
+    }
+
+    let result = std::panic::catch_unwind(ten);
+
+    assert_eq!(
+        result.is_err(),
+        cfg!(all(
+            feature = "debug_values",
+            not(feature = "disable_contracts")
+        ))
+    );
+}
+
+#[test]
+fn test_cfg_attr() {
+    // contracts in `cfg_attr` are expanded before or after the others,
+    // depending on their position
+    #[cfg_attr(feature = "debug_values", requires(x > 0))]
+    #[ensures(ret < 100)]
+    fn double(x: i32) -> i32 {
+        x * 2
+    }
+
+    #[requires(x < 50)]
+    #[cfg_attr(feature = "debug_values", ensures(ret > 0))]
+    fn triple(x: i32) -> i32 {
+        x * 3
+    }
+
+    let checked = cfg!(all(
+        feature = "debug_values",
+        not(feature = "disable_contracts")
+    ));
+
+    assert_eq!(double(5), 10);
+    assert_eq!(triple(5), 15);
+
+    assert_eq!(std::panic::catch_unwind(|| double(-5)).is_err(), checked);
+    assert_eq!(std::panic::catch_unwind(|| triple(-5)).is_err(), checked);
+}
+
+#[test]
+fn test_violation_location() {
+    let line = line!() + 1;
+    #[requires(x > 0, "x is positive")]
+    fn checked(x: i32) -> i32 {
+        x
+    }
+
+    let payload = std::panic::catch_unwind(|| checked(0)).unwrap_err();
+    let message = payload.downcast_ref::<String>().unwrap();
+
+    assert!(message.starts_with("Pre-condition of checked violated: x is positive: x > 0"));
+    assert!(message.ends_with(&format!(" at {}:{}", file!(), line)));
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of halve violated: x must be even: x % 2 == 0")]
+fn test_plain_description() {
+    #[requires(x % 2 == 0, "x must be even")]
+    fn halve(x: u32) -> u32 {
+        x / 2
+    }
+
+    halve(3);
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of halve violated: x was 3, not {x}: x % 2 == 0")]
+fn test_interpolated_description() {
+    #[requires(x % 2 == 0, "x was {x}, not {{x}}", interpolate)]
+    fn halve(x: u32) -> u32 {
+        x / 2
+    }
+
+    halve(3);
+}
+
+#[test]
+#[should_panic(expected = "Post-condition of decr violated: returned Some(4) for 5: ret.is_none()")]
+fn test_interpolated_description_return_value() {
+    #[ensures(ret.is_none(), "returned {ret:?} for {x}", interpolate)]
+    fn decr(x: u32) -> Option<u32> {
+        x.checked_sub(1)
+    }
+
+    decr(5);
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of lookup violated: key is in {a, b}: key < 2")]
+fn test_description_braces_without_interpolation() {
+    #[requires(key < 2, "key is in {a, b}")]
+    fn lookup(key: u32) -> u32 {
+        key
+    }
+
+    lookup(2);
+}
+
+#[test]
+fn test_const_generics() {
+    #[requires(N > 0, "the array is not empty")]
+    #[ensures(ret <= N)]
+    #[ensures(a.iter().all(|x| *x != 0) -> ret == N)]
+    fn count_nonzero<const N: usize>(a: [u8; N]) -> usize {
+        a.iter().filter(|x| **x != 0).count()
+    }
+
+    #[ensures(ret.len() == N)]
+    #[ensures(ret.iter().zip(old(a)).all(|(x, y)| *x == y * 2))]
+    fn doubled<const N: usize>(a: [u8; N]) -> [u8; N] {
+        a.map(|x| x * 2)
+    }
+
+    assert_eq!(count_nonzero([1, 0, 3]), 2);
+    assert_eq!(doubled([1, 2]), [2, 4]);
+}
+
+#[test]
+#[should_panic(expected = "exiting with 1")]
+fn test_never_returning() {
+    #[requires(code > 0)]
+    #[ensures(code < 0, "never reached")]
+    fn exit(code: i32) -> ! {
+        panic!("exiting with {}", code)
+    }
+
+    exit(1);
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of exit violated")]
+fn test_never_returning_violation() {
+    #[requires(code > 0)]
+    fn exit(code: i32) -> ! {
+        panic!("exiting with {}", code)
+    }
+
+    exit(0);
+}
+
+#[test]
+fn test_stacked_mode_aliases() {
+    #[requires(x > 0)]
+    #[debug_ensures(ret > x)]
+    #[test_ensures(ret == x + 1)]
+    #[debug_requires(x < 100)]
+    #[test_requires(x != 50)]
+    #[contracts::debug_invariant(x > 0)]
+    #[test_invariant(x < 100)]
+    fn incr(x: u32) -> u32 {
+        x + 1
+    }
+
+    assert_eq!(incr(1), 2);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "Post-condition of incr violated")]
+fn test_stacked_debug_ensures() {
+    #[requires(x > 0)]
+    #[debug_ensures(ret > x)]
+    fn incr(x: u32) -> u32 {
+        x.saturating_add(1)
+    }
+
+    incr(u32::MAX);
+}
+
+#[test]
+#[should_panic(expected = "Post-condition of halve violated")]
+fn test_stacked_ensures_after_requires() {
+    #[requires(x % 2 == 0)]
+    #[ensures(ret * 2 == x)]
+    fn halve(x: u32) -> u32 {
+        x / 2 + 1
+    }
+
+    halve(4);
+}
+
+#[test]
+#[deny(unused_must_use)]
+fn test_must_use() {
+    #[must_use]
+    struct Token(u32);
+
+    #[requires(x > 0)]
+    #[ensures(ret.0 == x)]
+    fn token(x: u32) -> Token {
+        Token(x)
+    }
+
+    #[ensures(ret == x * 2)]
+    #[must_use]
+    fn double(x: u32) -> u32 {
+        x * 2
+    }
+
+    #[ensures(ret.is_ok())]
+    fn parse(input: &str) -> Result<u32, std::num::ParseIntError> {
+        input.parse()
+    }
+
+    assert_eq!(token(1).0, 1);
+    assert_eq!(double(2), 4);
+    assert_eq!(parse("3"), Ok(3));
+}
+
+#[test]
+fn test_lifetime_parameters() {
+    #[requires(!x.is_empty())]
+    #[ensures(ret.len() <= x.len())]
+    #[ensures(x.starts_with(ret))]
+    fn first_word<'a>(x: &'a str) -> &'a str {
+        x.split(' ').next().unwrap_or(x)
+    }
+
+    #[ensures(ret.len() == a.len().max(b.len()))]
+    fn longest<'a, 'b: 'a>(a: &'a str, b: &'b str) -> &'a str {
+        if a.len() >= b.len() {
+            a
+        } else {
+            b
+        }
+    }
+
+    struct Parser<'s> {
+        input: &'s str,
+    }
+
+    impl<'s> Parser<'s> {
+        #[ensures(self.input.len() + ret.len() == old(self.input.len()))]
+        fn take(&mut self, n: usize) -> &'s str {
+            let (head, tail) = self.input.split_at(n.min(self.input.len()));
+            self.input = tail;
+            head
+        }
+    }
+
+    assert_eq!(first_word("hello world"), "hello");
+    assert_eq!(longest("ab", "abc"), "abc");
+
+    let mut parser = Parser { input: "abcdef" };
+    assert_eq!(parser.take(2), "ab");
+    assert_eq!(parser.input, "cdef");
+}
+
+#[cfg(not(feature = "disable_contracts"))]
+#[test]
+fn test_unchecked_variant() {
+    use std::cell::Cell;
+
+    thread_local! {
+        static CHECKS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn counted(holds: bool) -> bool {
+        CHECKS.with(|checks| checks.set(checks.get() + 1));
+        holds
+    }
+
+    // recursive calls use the unchecked variant, so the contracts are only
+    // checked once
+    #[requires(counted(n < 20), unchecked = "sum_unchecked")]
+    #[ensures(ret == n * (n + 1) / 2)]
+    fn sum(n: u64) -> u64 {
+        if n == 0 {
+            0
+        } else {
+            n + sum_unchecked(n - 1)
+        }
+    }
+
+    assert_eq!(sum(10), 55);
+    assert_eq!(CHECKS.with(Cell::get), 1);
+
+    assert_eq!(sum_unchecked(30), 465);
+    assert_eq!(CHECKS.with(Cell::get), 1);
+}
+
+#[test]
+fn test_macro_generated_functions() {
+    macro_rules! adder {
+        ($name:ident, $amount:expr) => {
+            #[requires(x < u32::MAX - $amount)]
+            #[ensures(ret == x + $amount)]
+            #[ensures(*total == old(*total) + ret)]
+            fn $name(x: u32, total: &mut u32) -> u32 {
+                let ret = x + $amount;
+                *total += ret;
+                ret
+            }
+        };
+    }
+
+    macro_rules! with_contracts {
+        ($(#[$attr:meta])* fn $name:ident($arg:ident: $ty:ty) -> $ret:ty $body:block) => {
+            $(#[$attr])*
+            fn $name($arg: $ty) -> $ret $body
+        };
+    }
+
+    adder!(add_two, 2);
+
+    with_contracts! {
+        #[requires(x > 0)]
+        #[ensures(ret * 2 == x, "halves {x} into {ret}", interpolate)]
+        #[ensures(old(x) == x)]
+        fn halve(x: u32) -> u32 {
+            let mut ret = x / 2;
+            ret += x % 2;
+            ret
+        }
+    }
+
+    let mut total = 0;
+    assert_eq!(add_two(1, &mut total), 3);
+    assert_eq!(halve(8), 4);
+}
+
+#[test]
+fn test_const_fn() {
+    #[requires(x > 0, "x must be positive")]
+    #[ensures(ret >= x)]
+    #[ensures(ret == old(x) * 2)]
+    const fn double(x: u32) -> u32 {
+        x * 2
+    }
+
+    const TWO: u32 = double(1);
+
+    assert_eq!(TWO, 2);
+    assert_eq!(double(4), 8);
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of halve_even violated: x must be even")]
+fn test_const_fn_violation() {
+    #[requires(x % 2 == 0, "x must be even")]
+    const fn halve_even(x: u32) -> u32 {
+        x / 2
+    }
+
+    halve_even(std::hint::black_box(3));
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of scale violated: scale needs positive input")]
+fn test_fn_name_in_description() {
+    macro_rules! positive_input {
+        ($(#[$attr:meta])* fn $name:ident($arg:ident: $ty:ty) -> $ret:ty $body:block) => {
+            #[requires($arg > 0, "{fn} needs positive input")]
+            $(#[$attr])*
+            fn $name($arg: $ty) -> $ret $body
+        };
+    }
+
+    positive_input! {
+        #[ensures(ret > x, "{fn} grows {x}", interpolate)]
+        fn scale(x: i32) -> i32 {
+            x * 2
+        }
+    }
+
+    assert_eq!(scale(2), 4);
+    scale(0);
+}
+
+#[test]
+fn test_braces_in_descriptions() {
+    fn message(f: fn()) -> String {
+        let payload = std::panic::catch_unwind(f).unwrap_err();
+        payload.downcast_ref::<String>().unwrap().clone()
+    }
+
+    #[requires(x > 0, "{{fn}} needs {x}")]
+    fn literal(x: i32) {}
+
+    #[requires(x > 0, "{{fn}} needs {x}, not {{x}}", interpolate)]
+    fn interpolated(x: i32) {}
+
+    assert!(message(|| literal(0))
+        .starts_with("Pre-condition of literal violated: {literal} needs {x}: x > 0"));
+    assert!(message(|| interpolated(0))
+        .starts_with("Pre-condition of interpolated violated: {fn} needs 0, not {x}: x > 0"));
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of scale violated")]
+fn test_documented_trait_impl_method() {
+    #[contract_trait]
+    trait Scale {
+        /// Scales a positive value.
+        #[requires(x > 0)]
+        fn scale(&self, x: i32) -> i32;
+    }
+
+    struct Double;
+
+    #[contract_trait]
+    impl Scale for Double {
+        /// Doubles the value.
+        #[inline]
+        fn scale(&self, x: i32) -> i32 {
+            x * 2
+        }
+    }
+
+    // the documented implementation is still called through the method
+    // checking the contracts
+    assert_eq!(Double.scale(2), 4);
+    let _ = Double.scale(0);
+}
@@ -0,0 +1,71 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![cfg(not(any(
+    feature = "disable_contracts",
+    feature = "disable_pre",
+    feature = "override_log",
+    feature = "mirai_assertions",
+    feature = "public_only"
+)))]
+
+use std::cell::RefCell;
+
+use contracts::*;
+
+thread_local! {
+    static VIOLATIONS: RefCell<Vec<(String, String, String, String)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+fn record(kind: &str, function: &str, condition: &str, message: &str) {
+    VIOLATIONS.with(|violations| {
+        violations.borrow_mut().push((
+            kind.to_string(),
+            function.to_string(),
+            condition.to_string(),
+            message.to_string(),
+        ))
+    });
+}
+
+fn take_violations() -> Vec<(String, String, String, String)> {
+    VIOLATIONS.with(|violations| violations.borrow_mut().drain(..).collect())
+}
+
+#[requires(x > 0, "x must be positive", handler = "record")]
+fn double(x: i32) -> i32 {
+    x * 2
+}
+
+#[test]
+fn test_handler_sees_violations() {
+    assert_eq!(double(2), 4);
+    assert!(take_violations().is_empty());
+
+    let result = std::panic::catch_unwind(|| double(0));
+    assert!(result.is_err());
+
+    let violations = take_violations();
+    assert_eq!(violations.len(), 1);
+
+    let (kind, function, condition, message) = &violations[0];
+    assert_eq!(kind, "Pre-condition");
+    assert_eq!(function, "double");
+    assert_eq!(condition, "x > 0");
+    assert!(message.starts_with("Pre-condition of double violated: x must be positive: x > 0"));
+}
+
+#[test]
+fn test_handler_of_assertion() {
+    let result = std::panic::catch_unwind(|| {
+        let x = 1;
+        assert_contract!(x > 1, handler = "record");
+    });
+    assert!(result.is_err());
+
+    let violations = take_violations();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].2, "x > 1");
+}
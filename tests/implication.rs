@@ -80,3 +80,167 @@ fn test_nested_implication() {
     test(false, true);
     test(true, true);
 }
+
+#[test]
+fn test_ret_biconditional() {
+    #[ensures(do_thing <==> ret.is_some(), "do_thing iff Some(_)")]
+    fn perform_thing(do_thing: bool) -> Option<usize> {
+        if do_thing {
+            Some(12)
+        } else {
+            None
+        }
+    }
+
+    perform_thing(true);
+    perform_thing(false);
+}
+
+#[test]
+fn test_biconditional_old() {
+    #[ensures(old(*x) % 2 == 0 <==> *x % 2 == 0)]
+    fn incr(x: &mut usize) {
+        *x += 2;
+    }
+
+    let mut x = 0;
+    incr(&mut x);
+
+    let mut x = 1;
+    incr(&mut x);
+}
+
+#[test]
+#[should_panic(expected = "Post")]
+fn test_failing_biconditional() {
+    #[ensures(t <==> ret)]
+    #[allow(unused_variables)]
+    fn only_true(t: bool) -> bool {
+        true // oops
+    }
+
+    only_true(false);
+}
+
+#[test]
+fn test_grouped_biconditional_implication() {
+    #[requires((a -> b) <==> (!a || b))]
+    #[requires((a && b) <==> !(a -> !b))]
+    fn test(a: bool, b: bool) {}
+
+    test(true, true);
+    test(false, false);
+}
+
+#[test]
+fn test_reverse_implication() {
+    #[ensures(ret.is_some() <== do_thing, "do_thing should cause a Some(_)")]
+    #[ensures(ret.is_none() <== !do_thing)]
+    fn perform_thing(do_thing: bool) -> Option<usize> {
+        if do_thing {
+            Some(12)
+        } else {
+            None
+        }
+    }
+
+    perform_thing(true);
+    perform_thing(false);
+}
+
+#[test]
+fn test_reverse_implication_comparison() {
+    // `<=` must still be parsed as a comparison
+    #[requires(a <= b, (a <= b) <== (a == b))]
+    #[ensures(ret <= b <== a <= b)]
+    fn clamp(a: u32, b: u32) -> u32 {
+        a.min(b)
+    }
+
+    clamp(1, 2);
+    clamp(2, 2);
+}
+
+#[test]
+#[should_panic(expected = "Post")]
+fn test_failing_reverse_implication() {
+    #[ensures(ret <== t)]
+    #[allow(unused_variables)]
+    fn only_true(t: bool) -> bool {
+        false // oops
+    }
+
+    only_true(true);
+}
+
+#[test]
+fn test_implication_method_chains() {
+    #[requires(check -> values.iter().all(|x| *x > 0))]
+    #[requires(check -> values.iter().copied().filter(|x| *x > 10).count() < 2)]
+    #[ensures(check -> ret.iter().map(|x| x * 2).sum::<i32>() > 0)]
+    fn positive(values: Vec<i32>, check: bool) -> Vec<i32> {
+        values
+    }
+
+    positive(vec![1, 2, 3], true);
+    positive(vec![-1, 20, 30], false);
+}
+
+#[test]
+fn test_implication_closure_return_types() {
+    #[requires(check -> values.iter().all(|x| -> bool { *x > 0 }))]
+    #[ensures(ret -> values.iter().any(|x| -> bool { *x > 0 }))]
+    fn positive(values: &[i32], check: bool) -> bool {
+        check && !values.is_empty()
+    }
+
+    assert!(positive(&[1, 2, 3], true));
+    assert!(!positive(&[-1], false));
+}
+
+#[test]
+fn test_grouped_implication() {
+    #[requires(a -> b -> c)]
+    #[requires(a -> (b -> c))]
+    #[requires((a -> b) -> (a -> c) || !b)]
+    fn chain(a: bool, b: bool, c: bool) {}
+
+    chain(true, true, true);
+    chain(true, false, false);
+    chain(false, true, false);
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of positive violated")]
+fn test_failing_implication_method_chain() {
+    #[requires(check -> values.iter().all(|x| *x > 0))]
+    fn positive(values: Vec<i32>, check: bool) {}
+
+    positive(vec![1, -2], true);
+}
+
+#[test]
+fn test_implication_blocks() {
+    #[ensures((x > 0) -> { let y = x; y > 0 })]
+    #[ensures(if x > 10 -> ret > 10 { ret >= x } else { true })]
+    #[ensures(match x { 0 => ret == 0, _ if x > 0 => x > 10 ==> ret > 10, _ => ret < 0 })]
+    fn identity(x: i32) -> i32 {
+        x
+    }
+
+    identity(5);
+    identity(-5);
+    identity(20);
+}
+
+#[test]
+#[should_panic(expected = "Post-condition of only_true violated")]
+fn test_long_arrow_implication() {
+    #[ensures(t ==> ret)]
+    #[ensures(!t ==> !ret)]
+    fn only_true(t: bool) -> bool {
+        !t // oops
+    }
+
+    only_true(false);
+}
@@ -99,6 +99,76 @@ fn gl_issue_18() {
     assert_eq!(1, euclidean(3, 4));
 }
 
+// `debug_values` needs the operands of comparisons to implement `Debug`
+#[cfg(not(feature = "debug_values"))]
+#[test]
+fn gl_issue_18_bounds_in_conditions() {
+    use std::collections::HashMap;
+    use std::hash::Hash;
+    use std::ops::{Add, Rem};
+
+    use contracts::{ensures, invariant, requires};
+
+    // the conditions use operations which are only available because of the
+    // bounds of the `where` clause. Arguments moved by the body are cloned.
+    #[requires(divisor != T::default())]
+    #[ensures(ret.clone() + T::default() == ret)]
+    #[ensures(ret < old_clone(divisor), "remainders are smaller")]
+    fn remainder<T>(value: T, divisor: T) -> T
+    where
+        T: Rem<Output = T> + Add<Output = T> + PartialOrd + Default + Clone,
+    {
+        value % divisor
+    }
+
+    #[ensures(ret.len() <= items.len())]
+    #[ensures(items.iter().all(|item| ret.contains_key(item)))]
+    #[ensures(ret.values().sum::<usize>() == items.len())]
+    fn count<'a, K>(items: &'a [K]) -> HashMap<&'a K, usize>
+    where
+        K: Eq + Hash,
+    {
+        let mut counts = HashMap::new();
+        for item in items {
+            *counts.entry(item).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    struct Sorted<T> {
+        items: Vec<T>,
+    }
+
+    #[invariant(self.items.windows(2).all(|pair| pair[0] <= pair[1]))]
+    impl<T> Sorted<T>
+    where
+        T: Ord + Clone,
+    {
+        #[ensures(self.items.contains(&old_clone(item)))]
+        #[ensures(self.items.len() == old(self.items.len()) + 1)]
+        fn insert(&mut self, item: T) {
+            let idx = self.items.binary_search(&item).unwrap_or_else(|idx| idx);
+            self.items.insert(idx, item);
+        }
+
+        #[ensures(ret.map_or(true, |max| self.items.iter().all(|item| item <= max)))]
+        fn max(&self) -> Option<&T> {
+            self.items.last()
+        }
+    }
+
+    assert_eq!(remainder(7, 3), 1);
+    assert_eq!(remainder(7.5, 2.0), 1.5);
+    assert_eq!(count(&["a", "b", "a"])[&"a"], 2);
+
+    let mut sorted = Sorted { items: vec![] };
+    sorted.insert(3);
+    sorted.insert(1);
+    sorted.insert(2);
+    assert_eq!(sorted.max(), Some(&3));
+    assert_eq!(sorted.items, [1, 2, 3]);
+}
+
 #[allow(clippy::assertions_on_constants)] // TODO(MSRV 1.81): replace with expect
 #[test]
 fn gl_issue_41() {
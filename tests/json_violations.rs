@@ -0,0 +1,51 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![cfg(all(feature = "json_violations", not(feature = "mirai_assertions")))]
+
+use std::{env, process::Command};
+
+use contracts::*;
+
+/// Set for the child process which triggers the violation.
+const CHILD_ENV: &str = "CONTRACTS_JSON_VIOLATIONS_CHILD";
+
+#[requires(x > 0, "x is \"positive\"")]
+fn add(x: i32, y: i32) -> i32 {
+    x + y
+}
+
+#[test]
+fn test_json_output() {
+    if env::var_os(CHILD_ENV).is_some() {
+        add(0, 1);
+        return;
+    }
+
+    // the output of the test itself is captured, so the violation happens in
+    // a child process running only this test
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "test_json_output", "--nocapture"])
+        .env(CHILD_ENV, "1")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let json = stderr
+        .lines()
+        .find(|line| line.starts_with('{'))
+        .expect("no JSON object printed");
+
+    assert_eq!(
+        json,
+        r#"{"contract":"Pre-condition","fn":"add","expr":"x > 0","desc":"Pre-condition of add violated: x is \"positive\""}"#
+    );
+}
+
+#[test]
+fn test_no_output_when_holding() {
+    assert_eq!(add(1, 2), 3);
+}
@@ -103,6 +103,185 @@ fn impl_invariant() {
     adder.prev_even();
 }
 
+#[test]
+#[should_panic(expected = "Count has to always be even")]
+fn impl_invariant_constructors() {
+    struct EvenAdder {
+        count: usize,
+    }
+
+    #[invariant(self.count % 2 == 0, "Count has to always be even", check_constructors)]
+    impl EvenAdder {
+        fn new() -> Self {
+            EvenAdder { count: 0 }
+        }
+
+        fn starting_at(count: usize) -> EvenAdder {
+            EvenAdder { count }
+        }
+
+        fn next_even(&mut self) {
+            self.count += 2;
+        }
+    }
+
+    let mut adder = EvenAdder::new();
+    adder.next_even();
+
+    EvenAdder::starting_at(3);
+}
+
+#[test]
+fn generic_impl_invariant() {
+    struct Bounds<T> {
+        low: T,
+        high: T,
+    }
+
+    #[invariant(self.low <= self.high, "bounds are ordered")]
+    impl<T: PartialOrd + Copy> Bounds<T>
+    where
+        T: std::fmt::Debug,
+    {
+        #[requires(value >= self.low)]
+        fn raise_high(&mut self, value: T) {
+            if value > self.high {
+                self.high = value;
+            }
+        }
+
+        fn swap<U: Into<T>>(&mut self, low: U, high: U) {
+            self.low = low.into();
+            self.high = high.into();
+        }
+    }
+
+    let mut bounds = Bounds {
+        low: 1.0,
+        high: 2.0,
+    };
+    bounds.raise_high(3.0);
+    bounds.swap(0.0f32, 5.0f32);
+    assert_eq!(bounds.high, 5.0);
+
+    let result = std::panic::catch_unwind(move || bounds.swap(2.0f32, 1.0f32));
+    assert!(result.is_err());
+}
+
+#[test]
+fn invariant_checkpoints() {
+    struct Connection {
+        open: bool,
+    }
+
+    #[invariant(self.open, when = "entry")]
+    impl Connection {
+        fn close(&mut self) {
+            self.open = false;
+        }
+    }
+
+    #[invariant(self.open, when = "exit", check_constructors)]
+    impl Connection {
+        fn new() -> Self {
+            Connection { open: true }
+        }
+
+        fn reopen(&mut self) {
+            self.open = true;
+        }
+    }
+
+    #[invariant(self.open, when = "both")]
+    impl Connection {
+        fn is_open(&self) -> bool {
+            self.open
+        }
+    }
+
+    let mut conn = Connection::new();
+    assert!(conn.is_open());
+
+    conn.close();
+    conn.reopen();
+    assert!(conn.is_open());
+}
+
+#[test]
+#[should_panic(expected = "Invariant (as pre-condition) of close violated")]
+fn invariant_checked_on_entry() {
+    struct Connection {
+        open: bool,
+    }
+
+    #[invariant(self.open, when = "entry")]
+    impl Connection {
+        fn close(&mut self) {
+            self.open = false;
+        }
+    }
+
+    let mut conn = Connection { open: true };
+    conn.close();
+    conn.close();
+}
+
+#[test]
+#[should_panic(expected = "Invariant (as post-condition) of reset violated")]
+fn invariant_checked_on_exit() {
+    #[invariant(*count > 0, when = "exit")]
+    fn reset(count: &mut usize) {
+        *count = 0;
+    }
+
+    let mut count = 0;
+    reset(&mut count);
+}
+
+#[test]
+#[should_panic(expected = "Invariant (as pre-condition) of reset violated")]
+fn invariant_checked_on_both() {
+    #[invariant(*count > 0, when = "both")]
+    fn reset(count: &mut usize) {
+        *count = 1;
+    }
+
+    let mut count = 0;
+    reset(&mut count);
+}
+
+#[test]
+fn impl_invariant_opt_out() {
+    struct EvenAdder {
+        count: usize,
+    }
+
+    #[invariant(self.count % 2 == 0, "Count has to always be even")]
+    impl EvenAdder {
+        fn next_even(&mut self) {
+            self.step();
+            self.step();
+        }
+
+        #[no_invariant]
+        fn step(&mut self) {
+            self.count += 1;
+        }
+
+        #[no_invariant]
+        #[requires(self.count % 2 == 1)]
+        fn finish_step(&mut self) {
+            self.count += 1;
+        }
+    }
+
+    let mut adder = EvenAdder { count: 0 };
+    adder.next_even();
+    adder.step();
+    adder.finish_step();
+    assert_eq!(adder.count, 4);
+}
+
 #[test]
 fn test_self_macro_hygiene() {
     struct S {
@@ -136,3 +315,275 @@ fn test_self_macro_hygiene() {
     s.set_value(42);
     assert_eq!(s.value, 42);
 }
+
+#[test]
+fn type_invariant() {
+    #[invariant(self.len <= self.cap, "length is within capacity")]
+    struct Buf<T> {
+        items: Vec<T>,
+        len: usize,
+        cap: usize,
+    }
+
+    impl<T> Buf<T> {
+        fn push(&mut self, item: T) {
+            if self.len < self.cap {
+                self.items.push(item);
+                self.len += 1;
+            }
+
+            self.__check_invariant();
+        }
+    }
+
+    #[invariant(match self { Shape::Circle(r) => *r > 0.0, Shape::Point => true })]
+    enum Shape {
+        Circle(f64),
+        Point,
+    }
+
+    let mut buf = Buf {
+        items: vec![],
+        len: 0,
+        cap: 1,
+    };
+    buf.push(1);
+    buf.push(2);
+    buf.__check_invariant();
+
+    Shape::Circle(1.0).__check_invariant();
+    Shape::Point.__check_invariant();
+}
+
+#[test]
+#[should_panic(expected = "Invariant of Buf violated: length is within capacity")]
+fn type_invariant_violation() {
+    #[invariant(self.len <= self.cap, "length is within capacity")]
+    struct Buf {
+        len: usize,
+        cap: usize,
+    }
+
+    Buf { len: 2, cap: 1 }.__check_invariant();
+}
+
+#[test]
+// `contracts_check` only evaluates the invariants which are checked
+#[cfg(not(any(feature = "disable_contracts", feature = "disable_invariants")))]
+fn type_invariant_check_without_panic() {
+    #[invariant(self.len <= self.cap, "length is within capacity")]
+    struct Buf {
+        len: usize,
+        cap: usize,
+    }
+
+    #[invariant(self.0 > 0)]
+    #[allow(dead_code)]
+    struct Positive(i32);
+
+    assert_eq!(Buf { len: 1, cap: 2 }.contracts_check(), Ok(()));
+
+    let failed = Buf { len: 2, cap: 1 }.contracts_check().unwrap_err();
+    assert_eq!(
+        failed,
+        [(
+            "Invariant",
+            "self.len <= self.cap",
+            Some("length is within capacity")
+        )]
+    );
+
+    assert!(Positive(1).contracts_check().is_ok());
+    assert!(Positive(-1).contracts_check().is_err());
+}
+
+#[test]
+// `contracts_check` only evaluates the invariants which are checked
+#[cfg(not(any(feature = "disable_contracts", feature = "disable_invariants")))]
+fn stacked_type_invariants() {
+    #[invariant(self.len <= self.cap, "length is within capacity")]
+    #[debug_invariant(self.cap > 0, "capacity is not zero")]
+    struct Buf {
+        len: usize,
+        cap: usize,
+    }
+
+    Buf { len: 1, cap: 2 }.__check_invariant();
+    assert_eq!(Buf { len: 1, cap: 2 }.contracts_check(), Ok(()));
+
+    // the debug invariant is only checked with debug assertions, by both
+    // methods
+    let result = Buf { len: 0, cap: 0 }.contracts_check();
+    if cfg!(debug_assertions) {
+        assert_eq!(
+            result,
+            Err(vec![(
+                "Invariant",
+                "self.cap > 0",
+                Some("capacity is not zero")
+            )])
+        );
+    } else {
+        assert_eq!(result, Ok(()));
+    }
+}
+
+#[test]
+#[should_panic(expected = "Invariant of Buf violated: capacity is not zero")]
+fn stacked_type_invariant_violation() {
+    #[invariant(self.len <= self.cap, "length is within capacity")]
+    #[invariant(self.cap > 0, "capacity is not zero")]
+    struct Buf {
+        len: usize,
+        cap: usize,
+    }
+
+    Buf { len: 0, cap: 0 }.__check_invariant();
+}
+
+#[test]
+fn const_generic_invariant() {
+    struct Ring<const N: usize> {
+        items: [u32; N],
+        head: usize,
+    }
+
+    #[invariant(self.head < N)]
+    impl<const N: usize> Ring<N> {
+        #[ensures(ret < N)]
+        fn advance(&mut self) -> usize {
+            self.head = (self.head + 1) % N;
+            self.head
+        }
+
+        #[requires(index < N)]
+        fn get(&self, index: usize) -> u32 {
+            self.items[(self.head + index) % N]
+        }
+    }
+
+    let mut ring = Ring {
+        items: [1, 2, 3],
+        head: 0,
+    };
+    assert_eq!(ring.advance(), 1);
+    assert_eq!(ring.get(2), 1);
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of fmt violated: percentages are at most 100")]
+fn trait_impl_method() {
+    use std::fmt;
+
+    struct Percent(u32);
+
+    // contracts on the methods of trait implementations without
+    // `contract_trait` only apply to this implementation
+    impl fmt::Display for Percent {
+        #[requires(self.0 <= 100, "percentages are at most 100")]
+        #[ensures(ret.is_ok())]
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}%", self.0)
+        }
+    }
+
+    assert_eq!(Percent(42).to_string(), "42%");
+
+    let _ = Percent(120).to_string();
+}
+
+#[cfg(not(feature = "disable_contracts"))]
+#[test]
+fn contract_impl() {
+    struct Account {
+        balance: i64,
+        limit: i64,
+    }
+
+    impl Account {
+        fn is_valid(&self) -> bool {
+            self.balance >= -self.limit
+        }
+    }
+
+    #[contract_impl(invariant(self.is_valid(), "the limit is kept"))]
+    impl Account {
+        fn new(limit: i64) -> Self {
+            Account { balance: 0, limit }
+        }
+
+        #[requires(amount > 0)]
+        fn deposit(&mut self, amount: i64) {
+            self.balance += amount;
+        }
+
+        #[ensures(ret -> self.balance == old(self.balance) - amount)]
+        fn withdraw(&mut self, amount: i64) -> bool {
+            self.balance -= amount;
+            true
+        }
+
+        fn balance(&self) -> i64 {
+            self.balance
+        }
+    }
+
+    let mut account = Account::new(10);
+    account.deposit(5);
+    assert!(account.withdraw(12));
+    assert_eq!(account.balance(), -7);
+
+    // the contracts of the method are still checked
+    let result = std::panic::catch_unwind(move || account.deposit(0));
+    let payload = result.unwrap_err();
+    let message = payload.downcast_ref::<String>().unwrap();
+    assert!(message.starts_with("Pre-condition of deposit violated"));
+
+    let mut account = Account::new(10);
+    let result = std::panic::catch_unwind(move || account.withdraw(20));
+    let payload = result.unwrap_err();
+    let message = payload.downcast_ref::<String>().unwrap();
+    assert!(message.starts_with("Invariant (as post-condition) of withdraw violated: the limit"));
+}
+
+#[cfg(feature = "mirai_assertions")]
+#[test]
+fn mirai_macro_per_kind() {
+    struct Counter {
+        count: u32,
+    }
+
+    #[invariant(self.count < 10)]
+    impl Counter {
+        #[requires(by > 0)]
+        #[ensures(self.count > old(self.count))]
+        fn add(&mut self, by: u32) {
+            self.count += by;
+        }
+
+        #[debug_requires(by > 0)]
+        fn add_debug(&mut self, by: u32) {
+            self.count += by;
+        }
+    }
+
+    let mut counter = Counter { count: 0 };
+
+    mirai_assertion_mocks::take_invoked();
+    counter.add(2);
+    assert_eq!(
+        mirai_assertion_mocks::take_invoked(),
+        [
+            "checked_verify",
+            "checked_assume",
+            "checked_verify",
+            "checked_verify"
+        ]
+    );
+
+    counter.add_debug(2);
+    assert_eq!(
+        mirai_assertion_mocks::take_invoked(),
+        ["checked_verify", "debug_checked_assume", "checked_verify"]
+    );
+}
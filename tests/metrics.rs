@@ -0,0 +1,97 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![cfg(all(
+    feature = "metrics",
+    not(feature = "disable_contracts"),
+    not(feature = "mirai_assertions")
+))]
+
+use contracts::*;
+
+use std::collections::HashMap;
+
+use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+
+/// Returns the `contracts.checks` counters recorded since the last snapshot,
+/// keyed by their `kind` and `fn` labels.
+fn checks(snapshotter: &Snapshotter) -> HashMap<(String, String), u64> {
+    let mut checks = HashMap::new();
+
+    for (key, _, _, value) in snapshotter.snapshot().into_vec() {
+        let key = key.key();
+        if key.name() != "contracts.checks" {
+            continue;
+        }
+
+        let label = |name| {
+            key.labels()
+                .find(|label| label.key() == name)
+                .map(|label| label.value().to_owned())
+                .unwrap_or_default()
+        };
+
+        if let DebugValue::Counter(count) = value {
+            checks.insert((label("kind"), label("fn")), count);
+        }
+    }
+
+    checks
+}
+
+#[test]
+fn test_metrics_counts_checks() {
+    #[requires(x < 100)]
+    #[ensures(ret > x)]
+    fn incr(x: usize) -> usize {
+        x + 1
+    }
+
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+
+    metrics::with_local_recorder(&recorder, || {
+        for i in 0..5 {
+            incr(i);
+        }
+    });
+
+    let checks = checks(&snapshotter);
+    let pre = ("Pre-condition".to_owned(), "incr".to_owned());
+    let post = ("Post-condition".to_owned(), "incr".to_owned());
+
+    assert_eq!(checks[&pre], 5);
+    assert_eq!(checks[&post], 5);
+}
+
+#[test]
+fn test_metrics_counts_invariants() {
+    struct Counter(usize);
+
+    #[invariant(self.0 < 10)]
+    impl Counter {
+        fn incr(&mut self) {
+            self.0 += 1;
+        }
+    }
+
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+
+    metrics::with_local_recorder(&recorder, || {
+        let mut counter = Counter(0);
+        counter.incr();
+        counter.incr();
+    });
+
+    let checks = checks(&snapshotter);
+    let pre = ("Invariant (as pre-condition)".to_owned(), "incr".to_owned());
+    let post = (
+        "Invariant (as post-condition)".to_owned(),
+        "incr".to_owned(),
+    );
+
+    assert_eq!(checks[&pre], 2);
+    assert_eq!(checks[&post], 2);
+}
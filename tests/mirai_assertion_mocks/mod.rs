@@ -3,22 +3,51 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/.
  */
 
+use std::cell::RefCell;
+
+thread_local! {
+    static INVOKED: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Remember the name of an invoked MIRAI macro.
+pub fn record(name: &'static str) {
+    INVOKED.with(|invoked| invoked.borrow_mut().push(name));
+}
+
+/// The names of the MIRAI macros invoked since the last call.
+#[allow(dead_code)]
+pub fn take_invoked() -> Vec<&'static str> {
+    INVOKED.with(|invoked| invoked.take())
+}
+
 #[macro_export]
-macro_rules! debug_checked_precondition {
-    ($condition:expr, $($arg:tt)*) => ( debug_assert!($condition, $($arg)*); );
+macro_rules! debug_checked_assume {
+    ($condition:expr, $($arg:tt)*) => (
+        $crate::mirai_assertion_mocks::record("debug_checked_assume");
+        debug_assert!($condition, $($arg)*);
+    );
 }
 
 #[macro_export]
-macro_rules! debug_checked_postcondition {
-    ($condition:expr, $($arg:tt)*) => ( debug_assert!($condition, $($arg)*); );
+macro_rules! debug_checked_verify {
+    ($condition:expr, $($arg:tt)*) => (
+        $crate::mirai_assertion_mocks::record("debug_checked_verify");
+        debug_assert!($condition, $($arg)*);
+    );
 }
 
 #[macro_export]
-macro_rules! checked_precondition {
-    ($condition:expr, $($arg:tt)*) => ( assert!($condition, $($arg)*); );
+macro_rules! checked_assume {
+    ($condition:expr, $($arg:tt)*) => (
+        $crate::mirai_assertion_mocks::record("checked_assume");
+        assert!($condition, $($arg)*);
+    );
 }
 
 #[macro_export]
-macro_rules! checked_postcondition {
-    ($condition:expr, $($arg:tt)*) => ( assert!($condition, $($arg)*); );
+macro_rules! checked_verify {
+    ($condition:expr, $($arg:tt)*) => (
+        $crate::mirai_assertion_mocks::record("checked_verify");
+        assert!($condition, $($arg)*);
+    );
 }
@@ -40,3 +40,245 @@ fn test_violation() {
     let mut val = 0;
     incr(&mut val);
 }
+
+#[test]
+fn test_old_mut_argument() {
+    #[ensures(n == 0)]
+    #[ensures(ret == old(n) * (old(n) + 1) / 2)]
+    fn triangle(mut n: u32) -> u32 {
+        let mut sum = 0;
+        while n > 0 {
+            sum += n;
+            n -= 1;
+        }
+        sum
+    }
+
+    assert_eq!(triangle(4), 10);
+}
+
+#[test]
+fn test_old_clone_reference() {
+    #[ensures(v.len() == old_clone(*v).len() + 1)]
+    #[ensures(v[..v.len() - 1] == old_clone(*v)[..], "prefix is unchanged")]
+    fn push(v: &mut Vec<u32>, x: u32) {
+        v.push(x);
+    }
+
+    let mut v = vec![1, 2];
+    push(&mut v, 3);
+}
+
+#[test]
+fn test_old_clone_reallocated() {
+    #[ensures(v[0] == old_clone(v[0]))]
+    #[ensures(v.capacity() > old(v.capacity()))]
+    fn grow(v: &mut Vec<String>) {
+        let additional = v.capacity() + 1;
+        v.reserve(additional);
+    }
+
+    let mut v = vec![String::from("first")];
+    grow(&mut v);
+}
+
+#[test]
+fn test_old_ref() {
+    #[ensures(ret == old_ref(&values[1..]).len())]
+    fn count_tail(values: &[u32]) -> usize {
+        values.len() - 1
+    }
+
+    assert_eq!(count_tail(&[1, 2, 3]), 2);
+}
+
+#[test]
+fn test_old_clone_self() {
+    #[derive(Clone, PartialEq)]
+    struct Counter {
+        count: usize,
+        name: String,
+    }
+
+    impl Counter {
+        #[ensures(self.count == old_clone(self).count + 1)]
+        #[ensures(self.name == old_clone(self).name)]
+        fn incr(&mut self) {
+            self.count += 1;
+        }
+    }
+
+    let mut counter = Counter {
+        count: 0,
+        name: String::from("counter"),
+    };
+    counter.incr();
+}
+
+#[test]
+fn test_old_async() {
+    use std::{
+        cell::Cell,
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    };
+
+    // yields to the executor once before completing
+    struct YieldNow(bool);
+
+    impl Future for YieldNow {
+        type Output = ();
+
+        fn poll(mut self: std::pin::Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                Poll::Pending
+            }
+        }
+    }
+
+    #[ensures(ret == old(counter.get()))]
+    #[ensures(counter.get() == old(counter.get()) + 10)]
+    async fn snapshot(counter: &Cell<u32>) -> u32 {
+        let val = counter.get();
+        YieldNow(false).await;
+        val
+    }
+
+    fn noop_raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            noop_raw_waker()
+        }
+        fn noop(_: *const ()) {}
+
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut cx = Context::from_waker(&waker);
+
+    let counter = Cell::new(1);
+    let mut fut = pin!(snapshot(&counter));
+
+    // `old()` is evaluated when the future is first polled, so changes while
+    // the function is suspended are visible to the post-conditions
+    assert_eq!(fut.as_mut().poll(&mut cx), Poll::Pending);
+    counter.set(11);
+    assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(1));
+}
+
+#[test]
+fn test_old_block() {
+    struct Stack {
+        items: Vec<u32>,
+    }
+
+    impl Stack {
+        #[ensures(self.items == old({
+            let mut items: Vec<_> = self.items.iter().rev().cloned().collect();
+            items.sort();
+            items
+        }))]
+        #[ensures(old({ let total: u32 = self.items.iter().sum(); total }) == self.items.iter().sum())]
+        fn sort(&mut self) {
+            self.items.sort();
+        }
+
+        #[ensures(self.items.len() == old(self.items.iter().filter(|i| **i != item).count()) + 1)]
+        fn push(&mut self, item: u32) {
+            self.items.push(item);
+        }
+    }
+
+    let mut stack = Stack {
+        items: vec![3, 1, 2],
+    };
+    stack.sort();
+    stack.push(4);
+
+    assert_eq!(stack.items, [1, 2, 3, 4]);
+}
+
+#[test]
+fn test_same_alloc() {
+    #[ensures(same_alloc(v, old(v)))]
+    fn push_in_place(v: &mut Vec<u32>, x: u32) {
+        v.push(x);
+    }
+
+    let mut v = Vec::with_capacity(4);
+    v.push(1);
+    push_in_place(&mut v, 2);
+
+    assert_eq!(v, [1, 2]);
+}
+
+#[test]
+#[should_panic(expected = "same_alloc(v, old(v))")]
+fn test_same_alloc_reassigned() {
+    #[ensures(same_alloc(v, old(v)))]
+    fn replace(v: &mut Vec<u32>) {
+        *v = vec![1, 2, 3];
+    }
+
+    let mut v = vec![1];
+    replace(&mut v);
+}
+
+#[test]
+fn test_old_self() {
+    #[derive(Clone, PartialEq, Debug)]
+    struct Counter {
+        count: u32,
+        max: u32,
+    }
+
+    #[invariant(self.max == old_self().max, "the maximum never changes", when = "exit")]
+    impl Counter {
+        #[ensures(self.count == old_self().count + 1)]
+        #[ensures(*self != old_self())]
+        fn incr(&mut self) {
+            self.count += 1;
+        }
+
+        #[ensures(ret == old_self(), "resetting returns the previous state")]
+        fn reset(&mut self) -> Counter {
+            std::mem::replace(
+                self,
+                Counter {
+                    count: 0,
+                    max: self.max,
+                },
+            )
+        }
+    }
+
+    let mut counter = Counter { count: 0, max: 3 };
+    counter.incr();
+    counter.incr();
+
+    assert_eq!(counter.reset().count, 2);
+    assert_eq!(counter.count, 0);
+}
+
+#[test]
+#[should_panic(expected = "the maximum never changes")]
+fn test_old_self_invariant_violation() {
+    #[derive(Clone)]
+    struct Counter {
+        max: u32,
+    }
+
+    #[invariant(self.max == old_self().max, "the maximum never changes", when = "exit")]
+    impl Counter {
+        fn grow(&mut self) {
+            self.max += 1;
+        }
+    }
+
+    Counter { max: 3 }.grow();
+}
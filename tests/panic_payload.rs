@@ -0,0 +1,89 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![cfg(not(any(
+    feature = "disable_contracts",
+    feature = "disable_pre",
+    feature = "override_log",
+    feature = "mirai_assertions",
+    feature = "collect_failures"
+)))]
+
+use std::panic;
+
+use contracts::*;
+
+mod violations {
+    #[derive(Debug)]
+    pub struct ContractViolation {
+        pub kind: &'static str,
+        pub func: &'static str,
+        pub condition: &'static str,
+        pub message: String,
+    }
+
+    impl ContractViolation {
+        pub fn new(
+            kind: &'static str,
+            func: &'static str,
+            condition: &'static str,
+            message: String,
+        ) -> Self {
+            ContractViolation {
+                kind,
+                func,
+                condition,
+                message,
+            }
+        }
+    }
+}
+
+use violations::ContractViolation;
+
+#[requires(x > 0, "x must be positive", payload = "violations::ContractViolation::new")]
+#[ensures(ret > x)]
+fn double(x: i32) -> i32 {
+    x * 2
+}
+
+#[test]
+fn test_payload() {
+    assert_eq!(double(2), 4);
+
+    let payload = panic::catch_unwind(|| double(0)).unwrap_err();
+    let violation = payload
+        .downcast_ref::<ContractViolation>()
+        .expect("a contract violation");
+
+    assert_eq!(violation.kind, "Pre-condition");
+    assert_eq!(violation.func, "double");
+    assert_eq!(violation.condition, "x > 0");
+    assert!(violation.message.contains("x must be positive"));
+}
+
+#[test]
+fn test_payload_of_assertion() {
+    let payload = panic::catch_unwind(|| {
+        let x = 1;
+        assert_contract!(x > 1, payload = "ContractViolation::new");
+    })
+    .unwrap_err();
+    let violation = payload
+        .downcast_ref::<ContractViolation>()
+        .expect("a contract violation");
+
+    assert_eq!(violation.condition, "x > 1");
+}
+
+#[test]
+fn test_message_without_payload() {
+    let payload = panic::catch_unwind(|| {
+        let x = 1;
+        assert_contract!(x > 1);
+    })
+    .unwrap_err();
+
+    assert!(payload.downcast_ref::<ContractViolation>().is_none());
+}
@@ -0,0 +1,110 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![cfg(all(
+    feature = "public_only",
+    not(feature = "disable_contracts"),
+    not(feature = "override_log"),
+    not(feature = "mirai_assertions")
+))]
+
+use contracts::*;
+
+#[requires(x > 0)]
+pub fn public_halve(x: u32) -> u32 {
+    x / 2
+}
+
+#[requires(x > 0)]
+fn private_halve(x: u32) -> u32 {
+    x / 2
+}
+
+#[requires(x > 0)]
+pub(crate) fn crate_halve(x: u32) -> u32 {
+    x / 2
+}
+
+pub struct Counter(u32);
+
+#[invariant(self.0 < 3)]
+impl Counter {
+    pub fn incr(&mut self) {
+        self.incr_unchecked();
+    }
+
+    fn incr_unchecked(&mut self) {
+        self.0 += 1;
+    }
+}
+
+#[contract_trait]
+pub trait Shrink {
+    #[requires(by > 0)]
+    fn shrink(&mut self, by: u32);
+}
+
+#[contract_trait]
+impl Shrink for Counter {
+    #[requires(by <= self.0)]
+    fn shrink(&mut self, by: u32) {
+        self.0 = self.0.wrapping_sub(by);
+    }
+}
+
+pub struct Gauge(u32);
+
+#[invariant(self.0 < 3)]
+impl Iterator for Gauge {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        self.0 += 1;
+        Some(self.0)
+    }
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of public_halve violated")]
+fn test_public_function_checked() {
+    public_halve(0);
+}
+
+#[test]
+fn test_private_functions_unchecked() {
+    assert_eq!(private_halve(0), 0);
+    assert_eq!(crate_halve(0), 0);
+}
+
+#[test]
+#[should_panic(expected = "Invariant (as pre-condition) of incr violated")]
+fn test_invariant_on_public_methods() {
+    let mut counter = Counter(2);
+
+    // private methods don't check the invariant
+    counter.incr_unchecked();
+    assert_eq!(counter.0, 3);
+
+    counter.incr();
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of shrink violated")]
+fn test_trait_contracts_checked() {
+    Counter(1).shrink(0);
+}
+
+#[test]
+#[should_panic(expected = "violated: by <= self.0")]
+fn test_trait_impl_contracts_checked() {
+    Counter(1).shrink(2);
+}
+
+#[test]
+#[should_panic(expected = "Invariant (as post-condition) of next violated")]
+fn test_invariant_on_trait_impl_methods() {
+    let mut gauge = Gauge(1);
+    assert_eq!(gauge.next(), Some(2));
+    gauge.next();
+}
@@ -0,0 +1,75 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+use std::cell::Cell;
+
+use contracts::*;
+
+#[cfg(feature = "mirai_assertions")]
+mod mirai_assertion_mocks;
+
+#[derive(Debug)]
+struct Counter {
+    count: usize,
+    reads: Cell<usize>,
+}
+
+impl Counter {
+    #[pure]
+    fn count(&self) -> usize {
+        self.count
+    }
+
+    #[pure]
+    fn is_empty(&self) -> bool {
+        if self.count == 0 {
+            return true;
+        }
+
+        false
+    }
+
+    #[pure]
+    fn tracked_count(&self) -> usize {
+        self.reads.set(self.reads.get() + 1);
+        self.count
+    }
+
+    #[requires(!self.is_empty())]
+    #[ensures(self.count() == old(self.count()) - 1)]
+    fn decrement(&mut self) {
+        self.count -= 1;
+    }
+}
+
+#[pure]
+fn add(a: &usize, b: usize) -> usize {
+    a + b
+}
+
+#[test]
+fn test_pure() {
+    let mut counter = Counter {
+        count: 2,
+        reads: Cell::new(0),
+    };
+
+    counter.decrement();
+
+    assert_eq!(counter.count(), 1);
+    assert_eq!(add(&counter.count(), 1), 2);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "Pure function tracked_count mutated `self`")]
+fn test_pure_violation() {
+    let counter = Counter {
+        count: 2,
+        reads: Cell::new(0),
+    };
+
+    counter.tracked_count();
+}
@@ -0,0 +1,160 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Testing of the quantifier pseudo-functions.
+
+use contracts::*;
+
+#[cfg(feature = "mirai_assertions")]
+mod mirai_assertion_mocks;
+
+#[test]
+fn test_forall() {
+    #[ensures(forall(x in ret.iter(), *x >= 0))]
+    #[ensures(forall(x in &ret, *x >= 0), "references can be iterated")]
+    fn absolutes(input: &[i32]) -> Vec<i32> {
+        input.iter().map(|x| x.abs()).collect()
+    }
+
+    absolutes(&[-3, 0, 7]);
+    absolutes(&[]);
+}
+
+#[test]
+fn test_forall_patterns() {
+    #[requires(forall((k, v) in pairs.iter(), k < v))]
+    #[requires(forall(&(k, _) in pairs.iter(), k >= 0))]
+    fn sum_spans(pairs: &[(i32, i32)]) -> i32 {
+        pairs.iter().map(|(k, v)| v - k).sum()
+    }
+
+    assert_eq!(sum_spans(&[(0, 2), (5, 6)]), 3);
+}
+
+#[test]
+fn test_forall_old() {
+    #[ensures(forall(x in v.iter(), *x > old(v.len())))]
+    #[ensures(forall(i in 0..v.len(), v[i] >= 1 -> v[i] > 1))]
+    fn increment_all(v: &mut [usize]) {
+        let len = v.len();
+        for x in v.iter_mut() {
+            *x += len + 1;
+        }
+    }
+
+    let mut v = [0, 1, 2];
+    increment_all(&mut v);
+}
+
+#[test]
+fn test_forall_old_before_call() {
+    struct Bounded {
+        min: u32,
+    }
+
+    impl Bounded {
+        // the snapshot of `self.min` is taken before the body raises it
+        #[ensures(forall(x in ret.iter(), *x > old(self.min)))]
+        #[ensures(exists(x in ret.iter(), *x < self.min))]
+        fn shift(&mut self, values: &[u32]) -> Vec<u32> {
+            let shifted = values.iter().map(|x| x + self.min + 1).collect();
+            self.min += 100;
+            shifted
+        }
+    }
+
+    let mut bounded = Bounded { min: 10 };
+    assert_eq!(bounded.shift(&[0, 5]), vec![11, 16]);
+}
+
+#[test]
+fn test_exists() {
+    #[requires(exists(x in haystack.iter(), *x == needle), "needle must be present")]
+    #[ensures(exists(x in &haystack, *x == needle) -> haystack[ret] == needle)]
+    fn position(haystack: Vec<u8>, needle: u8) -> usize {
+        haystack.iter().position(|x| *x == needle).unwrap()
+    }
+
+    assert_eq!(position(vec![3, 1, 4], 4), 2);
+}
+
+#[test]
+fn test_nested_quantifiers() {
+    #[requires(forall(row in rows.iter(), exists(x in row.iter(), *x == 0)))]
+    #[ensures(exists(row in rows.iter(), forall(x in row.iter(), *x <= ret)))]
+    fn max_all(rows: &[Vec<u32>]) -> u32 {
+        rows.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    max_all(&[vec![0, 3], vec![7, 0]]);
+}
+
+#[test]
+#[should_panic(expected = "Pre-condition of first_even violated")]
+fn test_exists_empty() {
+    #[requires(exists(x in v.iter(), x % 2 == 0))]
+    fn first_even(v: &[u32]) -> u32 {
+        v.iter().copied().find(|x| x % 2 == 0).unwrap()
+    }
+
+    first_even(&[]);
+}
+
+#[test]
+#[should_panic(expected = "Post-condition of evens violated")]
+fn test_forall_violation() {
+    #[ensures(forall(x in ret.iter(), x % 2 == 0))]
+    fn evens(n: usize) -> Vec<usize> {
+        (0..n).collect()
+    }
+
+    evens(3);
+}
+
+#[test]
+fn test_functions_named_like_quantifiers() {
+    fn forall(values: &[u32], pred: impl Fn(u32) -> bool) -> bool {
+        values.iter().all(|x| pred(*x))
+    }
+
+    fn exists(values: &[u32], needle: u32) -> bool {
+        values.contains(&needle)
+    }
+
+    #[requires(forall(values, |x| x > 0))]
+    #[ensures(exists(values, ret))]
+    fn smallest(values: &[u32]) -> u32 {
+        *values.iter().min().unwrap()
+    }
+
+    assert_eq!(smallest(&[3, 1, 2]), 1);
+}
+
+#[test]
+fn test_quantifier_iterables() {
+    struct Bag {
+        items: Vec<u32>,
+    }
+
+    // collections are borrowed, so they can still be used in the rest of
+    // the condition
+    #[requires(forall(x in values, *x > 0) && values.len() > 1)]
+    #[requires(forall(x in &values, *x > 0) && exists(x in values, *x > 2))]
+    #[requires(forall(x in values.clone(), x > 0), "temporaries are consumed")]
+    #[requires(exists(x in [1, 2], x == values[0]), "arrays are iterated by value")]
+    #[requires(forall(x in bag.items, *x < 10) && !bag.items.is_empty())]
+    #[ensures(forall(x in ret, *x > 1) && ret.len() == values.len())]
+    fn incr_all(values: Vec<u32>, bag: &Bag) -> Vec<u32> {
+        values.iter().map(|x| x + bag.items[0]).collect()
+    }
+
+    #[requires(forall(x in values, *x > 0) && !values.is_empty())]
+    fn total(values: &[u32]) -> u32 {
+        values.iter().sum()
+    }
+
+    let bag = Bag { items: vec![1] };
+    assert_eq!(incr_all(vec![2, 3], &bag), [3, 4]);
+    assert_eq!(total(&[2, 3]), 5);
+}
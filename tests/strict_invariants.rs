@@ -0,0 +1,78 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+#![cfg(feature = "strict_invariants")]
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use contracts::*;
+
+#[cfg(feature = "mirai_assertions")]
+mod mirai_assertion_mocks;
+
+struct Stack {
+    items: Vec<usize>,
+    len: usize,
+}
+
+#[invariant(self.items.len() == self.len, "len is in sync")]
+impl Stack {
+    fn push(&mut self, item: usize) {
+        self.items.push(item);
+
+        if item == 0 {
+            panic!("zero is not allowed");
+        }
+
+        self.len += 1;
+    }
+
+    fn checked_push(&mut self, item: usize) -> Result<usize, String> {
+        if item == 0 {
+            panic!("zero is not allowed");
+        }
+
+        self.items.push(item);
+        self.len += 1;
+
+        Ok(self.len)
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else {
+        String::new()
+    }
+}
+
+#[test]
+fn test_invariant_checked_on_panic() {
+    let mut stack = Stack {
+        items: vec![],
+        len: 0,
+    };
+
+    let payload = catch_unwind(AssertUnwindSafe(|| stack.push(0))).unwrap_err();
+
+    assert!(panic_message(payload).contains("Invariant (as post-condition) of push violated"));
+}
+
+#[test]
+fn test_original_panic_resumes() {
+    let mut stack = Stack {
+        items: vec![],
+        len: 0,
+    };
+
+    assert_eq!(stack.checked_push(3), Ok(1));
+
+    let payload = catch_unwind(AssertUnwindSafe(|| stack.checked_push(0))).unwrap_err();
+
+    assert_eq!(panic_message(payload), "zero is not allowed");
+}
@@ -0,0 +1,154 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+#![cfg(all(feature = "tracing", not(feature = "mirai_assertions")))]
+
+use contracts::*;
+
+/// Minimal stand-in for the `tracing` macros which records the emitted
+/// events instead of dispatching them to a subscriber.
+mod tracing {
+    use std::cell::RefCell;
+
+    #[derive(Debug, PartialEq)]
+    pub struct Event {
+        pub level: &'static str,
+        pub target: Option<&'static str>,
+        pub kind: String,
+        pub func: String,
+        pub message: String,
+    }
+
+    thread_local! {
+        pub static EVENTS: RefCell<Vec<Event>> = const { RefCell::new(Vec::new()) };
+    }
+
+    pub fn take_events() -> Vec<Event> {
+        EVENTS.with(|events| events.borrow_mut().drain(..).collect())
+    }
+
+    macro_rules! record {
+        ($level:expr, $target:expr, $kind:expr, $func:expr, $($arg:tt)*) => {
+            $crate::tracing::EVENTS.with(|events| {
+                events.borrow_mut().push($crate::tracing::Event {
+                    level: $level,
+                    target: $target,
+                    kind: $kind.to_string(),
+                    func: $func.to_string(),
+                    message: format!($($arg)*),
+                })
+            })
+        };
+    }
+
+    // contracts only log at the error level with `override_log`
+    #[cfg(feature = "override_log")]
+    macro_rules! error_event {
+        (target: $target:expr, contract.kind = $kind:expr, contract.fn = $func:expr, $($arg:tt)*) => {
+            $crate::tracing::record!("error", Some($target), $kind, $func, $($arg)*)
+        };
+        (contract.kind = $kind:expr, contract.fn = $func:expr, $($arg:tt)*) => {
+            $crate::tracing::record!("error", None, $kind, $func, $($arg)*)
+        };
+    }
+
+    macro_rules! warn_event {
+        (target: $target:expr, contract.kind = $kind:expr, contract.fn = $func:expr, $($arg:tt)*) => {
+            $crate::tracing::record!("warn", Some($target), $kind, $func, $($arg)*)
+        };
+        (contract.kind = $kind:expr, contract.fn = $func:expr, $($arg:tt)*) => {
+            $crate::tracing::record!("warn", None, $kind, $func, $($arg)*)
+        };
+    }
+
+    #[cfg(feature = "override_log")]
+    pub(crate) use error_event as error;
+    pub(crate) use record;
+    pub(crate) use warn_event as warn;
+}
+
+#[cfg(feature = "override_log")]
+#[test]
+fn test_tracing_events() {
+    let line = line!() + 1;
+    #[requires(x > 10, "x must be large")]
+    #[ensures(ret < 5, level = "warn", target = "contracts::tests")]
+    fn double(x: usize) -> usize {
+        x * 2
+    }
+
+    double(3);
+
+    let events = tracing::take_events();
+
+    assert_eq!(events.len(), 2);
+
+    assert_eq!(events[0].level, "error");
+    assert_eq!(events[0].target, None);
+    assert_eq!(events[0].kind, "Pre-condition");
+    assert_eq!(events[0].func, "double");
+    assert_eq!(
+        events[0].message,
+        format!(
+            "Pre-condition of double violated: x must be large: x > 10 at {}:{}",
+            file!(),
+            line
+        )
+    );
+
+    assert_eq!(events[1].level, "warn");
+    assert_eq!(events[1].target, Some("contracts::tests"));
+    assert_eq!(events[1].kind, "Post-condition");
+    assert_eq!(events[1].func, "double");
+}
+
+#[cfg(feature = "override_log")]
+#[test]
+fn test_tracing_invariant_kind() {
+    struct Counter(usize);
+
+    #[invariant(self.0 < 2)]
+    impl Counter {
+        fn incr(&mut self) {
+            self.0 += 1;
+        }
+    }
+
+    let mut counter = Counter(1);
+    counter.incr();
+
+    let events = tracing::take_events();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, "Invariant (as post-condition)");
+    assert_eq!(events[0].func, "incr");
+}
+
+#[cfg(not(feature = "disable_contracts"))]
+#[test]
+fn test_log_mode_option() {
+    let line = line!() + 1;
+    #[requires(x > 10, "x must be large", mode = "log", level = "warn")]
+    #[ensures(ret == x * 2)]
+    fn double(x: usize) -> usize {
+        x * 2
+    }
+
+    assert_eq!(double(3), 6);
+
+    let events = tracing::take_events();
+
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].level, "warn");
+    assert_eq!(events[0].kind, "Pre-condition");
+    assert_eq!(
+        events[0].message,
+        format!(
+            "Pre-condition of double violated: x must be large: x > 10 at {}:{}",
+            file!(),
+            line
+        )
+    );
+}
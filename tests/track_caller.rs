@@ -0,0 +1,72 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![cfg(not(any(
+    feature = "disable_contracts",
+    feature = "disable_pre",
+    feature = "override_log"
+)))]
+
+use std::{
+    panic,
+    sync::{Arc, Mutex},
+};
+
+use contracts::*;
+
+#[cfg(feature = "mirai_assertions")]
+mod mirai_assertion_mocks;
+
+#[requires(divisor != 0)]
+fn divide(dividend: u32, divisor: u32) -> u32 {
+    dividend / divisor
+}
+
+const REMAINDER_LINE: u32 = line!() + 1;
+#[requires(divisor != 0, no_track_caller)]
+fn remainder(dividend: u32, divisor: u32) -> u32 {
+    dividend % divisor
+}
+
+const FIRST_LINE: u32 = line!() + 3;
+#[ensures(ret > 0)]
+fn first(values: &[u32]) -> u32 {
+    *values.first().unwrap()
+}
+
+// this is the only test in this file, as the panic hook is global
+#[test]
+fn test_violation_reported_at_caller() {
+    let location = Arc::new(Mutex::new(None));
+
+    let hook_location = Arc::clone(&location);
+    panic::set_hook(Box::new(move |info| {
+        let loc = info.location().unwrap();
+        *hook_location.lock().unwrap() = Some((loc.file().to_string(), loc.line()));
+    }));
+
+    let line = line!() + 1;
+    let result = panic::catch_unwind(|| divide(1, 0));
+    assert!(result.is_err());
+    let divide_location = location.lock().unwrap().take();
+
+    // without a pre-condition tracking the caller, panics are reported in
+    // the function
+    let result = panic::catch_unwind(|| remainder(1, 0));
+    assert!(result.is_err());
+    let remainder_location = location.lock().unwrap().take();
+
+    let result = panic::catch_unwind(|| first(&[]));
+    assert!(result.is_err());
+    let first_location = location.lock().unwrap().take();
+
+    let _ = panic::take_hook();
+
+    assert_eq!(divide_location, Some((file!().to_string(), line)));
+    assert_eq!(
+        remainder_location,
+        Some((file!().to_string(), REMAINDER_LINE))
+    );
+    assert_eq!(first_location, Some((file!().to_string(), FIRST_LINE)));
+}
@@ -77,3 +77,339 @@ fn interpolate_example() {
     Linear::interpolate(min, max, val);
     Quadratic::interpolate(min, max, val);
 }
+
+#[test]
+fn generic_trait_example() {
+    #[contract_trait]
+    trait Cache<K, V>
+    where
+        K: PartialEq,
+    {
+        fn len(&self) -> usize;
+
+        fn get(&self, key: &K) -> Option<&V>;
+
+        #[requires(self.get(&key).is_none(), "keys are unique")]
+        #[ensures(self.len() == old(self.len()) + 1)]
+        fn insert(&mut self, key: K, value: V);
+
+        #[ensures(ret <= self.len())]
+        fn count_matching<'a, F>(&'a self, pred: F) -> usize
+        where
+            F: Fn(&'a V) -> bool,
+            V: 'a;
+
+        #[ensures(ret >= std::mem::size_of::<K>())]
+        fn entry_size<T: Sized>() -> usize;
+    }
+
+    struct VecCache<K, V>(Vec<(K, V)>);
+
+    #[contract_trait]
+    impl<K: PartialEq, V> Cache<K, V> for VecCache<K, V> {
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        fn get(&self, key: &K) -> Option<&V> {
+            self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+        }
+
+        fn insert(&mut self, key: K, value: V) {
+            self.0.push((key, value));
+        }
+
+        fn count_matching<'a, F>(&'a self, pred: F) -> usize
+        where
+            F: Fn(&'a V) -> bool,
+            V: 'a,
+        {
+            self.0.iter().filter(|(_, v)| pred(v)).count()
+        }
+
+        fn entry_size<T: Sized>() -> usize {
+            std::mem::size_of::<(K, V, T)>()
+        }
+    }
+
+    let mut cache = VecCache(vec![]);
+
+    cache.insert("a", 1);
+    cache.insert("b", 2);
+
+    assert_eq!(cache.get(&"b"), Some(&2));
+    assert_eq!(cache.count_matching(|v| *v > 1), 1);
+    assert!(<VecCache<&str, u8> as Cache<&str, u8>>::entry_size::<u64>() >= 8);
+}
+
+#[test]
+fn generic_method_example() {
+    #[contract_trait]
+    trait Bag<T> {
+        fn len(&self) -> usize;
+
+        #[ensures(self.len() >= old(self.len()))]
+        fn insert<I: IntoIterator<Item = T>>(&mut self, items: I);
+
+        #[ensures(self.len() == old(self.len()) + N)]
+        fn insert_array<const N: usize>(&mut self, items: [T; N]);
+
+        #[ensures(ret <= self.len())]
+        fn count(&self, pred: impl Fn(&T) -> bool) -> usize;
+    }
+
+    struct VecBag<T>(Vec<T>);
+
+    #[contract_trait]
+    impl<T> Bag<T> for VecBag<T> {
+        fn len(&self) -> usize {
+            self.0.len()
+        }
+
+        fn insert<I: IntoIterator<Item = T>>(&mut self, items: I) {
+            self.0.extend(items);
+        }
+
+        fn insert_array<const N: usize>(&mut self, items: [T; N]) {
+            self.0.extend(items);
+        }
+
+        fn count(&self, pred: impl Fn(&T) -> bool) -> usize {
+            self.0.iter().filter(|x| pred(x)).count()
+        }
+    }
+
+    let mut bag = VecBag(vec![]);
+
+    bag.insert(vec![1, 2]);
+    bag.insert(3..5);
+    bag.insert_array([5, 6]);
+
+    assert_eq!(bag.len(), 6);
+    assert_eq!(bag.count(|x| x % 2 == 0), 3);
+}
+
+#[test]
+fn associated_items_example() {
+    #[contract_trait]
+    trait Parser {
+        type Output;
+
+        const LIMIT: usize;
+
+        #[requires(input.len() <= Self::LIMIT, "input is too long")]
+        fn parse(input: &str) -> Option<Self::Output>;
+    }
+
+    struct Digits;
+
+    #[contract_trait]
+    impl Parser for Digits {
+        type Output = u32;
+
+        const LIMIT: usize = 9;
+
+        fn parse(input: &str) -> Option<u32> {
+            input.parse().ok()
+        }
+    }
+
+    assert_eq!(Digits::parse("1234"), Some(1234));
+    assert_eq!(Digits::parse("abc"), None);
+}
+
+#[test]
+fn destructured_arguments_example() {
+    #[derive(Clone, Copy)]
+    struct Point(i32, i32);
+
+    #[derive(Clone, Copy)]
+    struct Size {
+        width: u32,
+        height: u32,
+    }
+
+    #[contract_trait]
+    trait Canvas {
+        #[requires(x >= 0 && y >= 0)]
+        fn plot(&mut self, Point(x, y): Point);
+
+        #[requires(width > 0, height > 0)]
+        #[ensures(ret == width * height)]
+        fn area(&self, Size { width, height }: Size) -> u32;
+
+        #[requires(scale > 0)]
+        fn scale(&mut self, &scale: &u32, offset: u32);
+    }
+
+    struct Plotter(Vec<(i32, i32)>);
+
+    #[contract_trait]
+    impl Canvas for Plotter {
+        fn plot(&mut self, Point(x, y): Point) {
+            self.0.push((x, y));
+        }
+
+        fn area(&self, Size { width, height }: Size) -> u32 {
+            width * height
+        }
+
+        fn scale(&mut self, &scale: &u32, mut offset: u32) {
+            offset += 1;
+
+            for p in &mut self.0 {
+                p.0 = p.0 * scale as i32 + offset as i32;
+            }
+        }
+    }
+
+    let mut plotter = Plotter(vec![]);
+
+    plotter.plot(Point(1, 2));
+    plotter.scale(&2, 0);
+
+    assert_eq!(plotter.0, vec![(3, 2)]);
+    assert_eq!(
+        plotter.area(Size {
+            width: 3,
+            height: 4
+        }),
+        12
+    );
+}
+
+#[test]
+fn provided_method_example() {
+    #[contract_trait]
+    trait Stack {
+        #[ensures(ret <= 10)]
+        fn len(&self) -> usize;
+
+        #[requires(self.len() > 0, "the stack is not empty")]
+        #[ensures(ret == self.len() - 1)]
+        fn last_index(&self) -> usize {
+            self.len() - 1
+        }
+    }
+
+    struct Fixed(usize);
+
+    #[contract_trait]
+    impl Stack for Fixed {
+        fn len(&self) -> usize {
+            self.0
+        }
+    }
+
+    assert_eq!(Fixed(3).last_index(), 2);
+
+    let result = std::panic::catch_unwind(|| Fixed(0).last_index());
+    assert!(result.is_err());
+}
+
+#[test]
+fn arbitrary_self_types_example() {
+    use std::{pin::Pin, rc::Rc};
+
+    #[contract_trait]
+    trait Node {
+        fn weight(&self) -> u32;
+
+        #[requires(self.weight() > 0)]
+        #[ensures(ret > 0)]
+        fn into_weight(self: Box<Self>) -> u32;
+
+        #[requires(self.weight() < 10)]
+        fn shared_weight(self: Rc<Self>) -> u32;
+
+        #[requires(delta < 10)]
+        #[ensures(self.weight() == old(self.weight()) + delta)]
+        fn grow(self: Pin<&mut Self>, delta: u32);
+    }
+
+    struct Leaf(u32);
+
+    #[contract_trait]
+    impl Node for Leaf {
+        fn weight(&self) -> u32 {
+            self.0
+        }
+
+        fn into_weight(self: Box<Self>) -> u32 {
+            self.0
+        }
+
+        fn shared_weight(self: Rc<Self>) -> u32 {
+            self.0
+        }
+
+        fn grow(mut self: Pin<&mut Self>, delta: u32) {
+            self.0 += delta;
+        }
+    }
+
+    assert_eq!(Box::new(Leaf(3)).into_weight(), 3);
+    assert_eq!(Rc::new(Leaf(4)).shared_weight(), 4);
+
+    let mut leaf = Leaf(1);
+    Pin::new(&mut leaf).grow(2);
+    assert_eq!(leaf.0, 3);
+}
+
+#[test]
+fn ignored_arguments() {
+    #[contract_trait]
+    trait Greeter {
+        #[ensures(!ret.is_empty())]
+        fn greet(&self, _: u32, name: &str) -> String;
+    }
+
+    struct English;
+
+    #[contract_trait]
+    impl Greeter for English {
+        fn greet(&self, _: u32, name: &str) -> String {
+            format!("Hello {}", name)
+        }
+    }
+
+    assert_eq!(English.greet(3, "you"), "Hello you");
+}
+
+#[test]
+fn trait_objects() {
+    #[contract_trait]
+    trait Shape {
+        #[ensures(ret >= 0.0)]
+        fn area(&self) -> f64;
+
+        #[requires(factor > 0.0)]
+        fn scale(&mut self, factor: f64);
+
+        #[ensures(ret.area() >= 0.0)]
+        fn boxed(self: Box<Self>) -> Box<dyn Shape>;
+    }
+
+    struct Square(f64);
+
+    #[contract_trait]
+    impl Shape for Square {
+        fn area(&self) -> f64 {
+            self.0 * self.0
+        }
+
+        fn scale(&mut self, factor: f64) {
+            self.0 *= factor;
+        }
+
+        fn boxed(self: Box<Self>) -> Box<dyn Shape> {
+            self
+        }
+    }
+
+    let mut shape: Box<dyn Shape> = Box::new(Square(2.0));
+    shape.scale(1.5);
+    let shape = shape.boxed();
+
+    assert_eq!(shape.area(), 9.0);
+}
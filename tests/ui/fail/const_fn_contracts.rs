@@ -0,0 +1,37 @@
+use contracts::*;
+
+#[requires(x > 0, "x was {x}", interpolate)]
+const fn double(x: u32) -> u32 {
+    x * 2
+}
+
+#[requires(x > 0, mode = "log")]
+const fn triple(x: u32) -> u32 {
+    x * 3
+}
+
+#[ensures(ret > 0, category = "arith")]
+const fn incr(x: u32) -> u32 {
+    x + 1
+}
+
+fn violation(_: &str, _: &str, _: &str, message: String) -> String {
+    message
+}
+
+#[requires(x > 0, payload = "violation")]
+const fn halve(x: u32) -> u32 {
+    x / 2
+}
+
+#[requires(x > 0, audit = "violation")]
+const fn quarter(x: u32) -> u32 {
+    x / 4
+}
+
+#[requires(x > 0, handler = "violation")]
+const fn eighth(x: u32) -> u32 {
+    x / 8
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use contracts::ensures;
+
+#[ensures(ret.count() == n)]
+fn numbers(n: usize) -> impl Iterator<Item = usize> {
+    0..n
+}
+
+fn main() {
+    numbers(3);
+}
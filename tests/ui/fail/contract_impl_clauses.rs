@@ -0,0 +1,17 @@
+use contracts::*;
+
+struct Counter(u32);
+
+#[contract_impl(invariant(self.0 < 10), checks(self.0 > 0))]
+impl Counter {
+    fn incr(&mut self) {
+        self.0 += 1;
+    }
+}
+
+#[contract_impl(requires(x > 0))]
+fn double(x: u32) -> u32 {
+    x * 2
+}
+
+fn main() {}
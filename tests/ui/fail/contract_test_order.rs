@@ -0,0 +1,11 @@
+use contracts::*;
+
+#[requires(x < 100)]
+#[contract_test]
+fn incr(x: u32) -> u32 {
+    x + 1
+}
+
+fn main() {
+    incr(1);
+}
@@ -0,0 +1,31 @@
+use contracts::*;
+
+#[contract_trait]
+trait Counter {
+    #[ensures(ret > 0)]
+    fn next(&mut self) -> u32;
+}
+
+struct Forgotten(u32);
+
+impl Counter for Forgotten {
+    fn next(&mut self) -> u32 {
+        self.0 += 1;
+        self.0
+    }
+}
+
+trait Plain {
+    fn value(&self) -> u32;
+}
+
+struct Extra;
+
+#[contract_trait]
+impl Plain for Extra {
+    fn value(&self) -> u32 {
+        1
+    }
+}
+
+fn main() {}
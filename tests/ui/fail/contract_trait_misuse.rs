@@ -0,0 +1,29 @@
+use contracts::*;
+
+#[contract_trait]
+struct NotATrait;
+
+#[contract_trait]
+impl NotATrait {
+    fn f(&self) {}
+}
+
+#[contract_trait]
+trait Patterns {
+    #[requires(true)]
+    fn struct_rest(&self, Point { x, .. }: Point) -> u32 {
+        x
+    }
+
+    #[requires(true)]
+    fn mut_ref(&self, &mut x: &mut u32) -> u32 {
+        x
+    }
+}
+
+struct Point {
+    x: u32,
+    y: u32,
+}
+
+fn main() {}
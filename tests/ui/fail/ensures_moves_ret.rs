@@ -0,0 +1,12 @@
+use contracts::*;
+
+fn all_positive(values: Vec<u32>) -> bool {
+    values.into_iter().all(|x| x > 0)
+}
+
+#[ensures(all_positive(ret))]
+fn positives() -> Vec<u32> {
+    vec![1, 2, 3]
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use contracts::ensures_ok;
+
+#[ensures_ok(ret > 0)]
+fn not_a_result(x: u32) -> u32 {
+    x
+}
+
+fn main() {
+    not_a_result(1);
+}
@@ -0,0 +1,16 @@
+use contracts::{ensures_none, ensures_some};
+
+#[ensures_some(ret > 0)]
+fn not_an_option(x: u32) -> u32 {
+    x
+}
+
+#[ensures_none(x == 0)]
+fn result(x: u32) -> Result<u32, ()> {
+    Ok(x)
+}
+
+fn main() {
+    not_an_option(1);
+    let _ = result(1);
+}
@@ -0,0 +1,22 @@
+use contracts::*;
+
+struct MyError;
+
+#[requires(x + 1)]
+#[ensures(ret == "one")]
+fn incr(x: u32) -> u32 {
+    x + 1
+}
+
+#[ensures(ret.is_ok())]
+fn parse(s: &str) -> Result<u32, MyError> {
+    let x: u32 = s.parse()?;
+    Ok(x)
+}
+
+#[ensures(ret.size_hint().0 > 0)]
+fn items() -> impl Iterator<Item = u32> {
+    return 5;
+}
+
+fn main() {}
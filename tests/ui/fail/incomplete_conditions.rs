@@ -0,0 +1,11 @@
+use contracts::*;
+
+#[requires(x -> )]
+#[requires(<== x)]
+fn implication(x: bool) {}
+
+#[requires(forall(y in , y > 0))]
+#[ensures(exists(y in values.iter(), ))]
+fn quantifiers(values: Vec<u32>) {}
+
+fn main() {}
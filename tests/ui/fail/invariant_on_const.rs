@@ -0,0 +1,8 @@
+use contracts::invariant;
+
+#[invariant(LIMIT > 0)]
+const LIMIT: i32 = 10;
+
+fn main() {
+    let _ = LIMIT;
+}
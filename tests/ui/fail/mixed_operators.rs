@@ -0,0 +1,8 @@
+use contracts::requires;
+
+#[requires(a <==> b -> c)]
+fn mixed(a: bool, b: bool, c: bool) {}
+
+fn main() {
+    mixed(true, true, true);
+}
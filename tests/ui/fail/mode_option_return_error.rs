@@ -0,0 +1,8 @@
+use contracts::*;
+
+#[requires_or_return(divisor != 0, mode = "log", "division by zero")]
+fn divide(dividend: i32, divisor: i32) -> Result<i32, &'static str> {
+    Ok(dividend / divisor)
+}
+
+fn main() {}
@@ -1,10 +1,19 @@
-use contracts::requires;
+use contracts::*;
 
-#[requires(old(x) == x)]
-fn checked(x: i32) -> i32 {
-    x
+#[requires(old(*x) == 0)]
+fn incr(x: &mut usize) {
+    *x += 1;
 }
 
-fn main() {
-    let _ = checked(1);
+struct Counter {
+    count: usize,
 }
+
+#[invariant(self.count >= old_clone(self.count))]
+impl Counter {
+    fn incr(&mut self) {
+        self.count += 1;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use contracts::*;
+
+#[ensures(v[0] == *old(&v[0]))]
+fn grow(v: &mut Vec<u32>) {
+    v.push(0);
+}
+
+#[ensures(v[0] == *old_ref(&v[0]))]
+fn grow_ref(v: &mut Vec<u32>) {
+    v.push(0);
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+use contracts::*;
+
+#[derive(Clone)]
+struct Counter {
+    max: u32,
+}
+
+#[invariant(self.max == old_self().max)]
+impl Counter {
+    fn grow(&mut self) {}
+}
+
+struct Unique {
+    id: u32,
+}
+
+impl Unique {
+    #[ensures(self.id == old_self().id)]
+    fn touch(&mut self) {}
+}
+
+fn main() {}
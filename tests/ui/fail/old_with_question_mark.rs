@@ -0,0 +1,13 @@
+use contracts::*;
+
+#[ensures(old(name.parse::<u32>()?) > 0)]
+fn pad(name: &str) -> Result<String, std::num::ParseIntError> {
+    Ok(format!("{:>8}", name))
+}
+
+#[ensures(ret == old_clone(values.iter().map(|v| v.checked_add(1)).collect::<Option<Vec<u32>>>()))]
+fn increment(values: &mut Vec<u32>) -> Option<Vec<u32>> {
+    values.iter_mut().map(|v| v.checked_add(1)).collect()
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use contracts::pure;
+
+struct Counter(usize);
+
+impl Counter {
+    #[pure]
+    fn incr(&mut self) -> usize {
+        self.0 += 1;
+        self.0
+    }
+}
+
+fn main() {}
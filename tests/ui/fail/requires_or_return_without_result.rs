@@ -0,0 +1,10 @@
+use contracts::requires_or_return;
+
+#[requires_or_return(x > 0, || "x must be positive")]
+fn checked(x: i32) -> i32 {
+    x
+}
+
+fn main() {
+    let _ = checked(1);
+}
@@ -0,0 +1,15 @@
+use contracts::*;
+
+extern "C" {
+    #[requires(x >= 0)]
+    fn abs(x: i32) -> i32;
+}
+
+trait Stack {
+    #[ensures(!self.is_empty())]
+    fn push(&mut self, value: u32);
+
+    fn is_empty(&self) -> bool;
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use contracts::*;
+
+#[requires(x > 0, when = "exit")]
+fn positive(x: i32) -> i32 {
+    x
+}
+
+#[invariant(*x > 0, when = "always")]
+fn increment(x: &mut i32) {
+    *x += 1;
+}
+
+fn main() {}
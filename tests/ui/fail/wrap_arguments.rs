@@ -0,0 +1,6 @@
+use contracts::wrap;
+
+fn main() {
+    let _ = wrap!(std::cmp::max(a + 1, b), requires(a < 100));
+    let _ = wrap!(std::cmp::min(a, b), checks(a < b));
+}
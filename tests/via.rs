@@ -0,0 +1,61 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+#![cfg(not(any(
+    feature = "disable_contracts",
+    feature = "override_log",
+    feature = "mirai_assertions"
+)))]
+
+use contracts::*;
+
+/// Assertion macro recording the messages of all checks.
+mod checks {
+    use std::cell::RefCell;
+
+    thread_local! {
+        pub static CHECKED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    pub fn take_checked() -> Vec<String> {
+        CHECKED.with(|checked| checked.take())
+    }
+
+    macro_rules! recording_assert {
+        ($cond:expr, $msg:expr) => {{
+            let msg = $msg;
+            $crate::checks::CHECKED.with(|checked| checked.borrow_mut().push(msg.to_string()));
+            if !$cond {
+                panic!("recorded: {}", msg);
+            }
+        }};
+    }
+
+    pub(crate) use recording_assert;
+}
+
+#[requires(x > 0, via = "checks::recording_assert")]
+#[ensures(ret > x, "grows", via = "checks::recording_assert")]
+#[debug_ensures(ret % 2 == 0, via = "checks::recording_assert")]
+fn double(x: u32) -> u32 {
+    x * 2
+}
+
+#[test]
+fn custom_macro_is_invoked() {
+    double(2);
+
+    let checked = checks::take_checked();
+    let expected = if cfg!(debug_assertions) { 3 } else { 2 };
+
+    assert_eq!(checked.len(), expected);
+    assert!(checked[0].starts_with("Pre-condition of double violated: x > 0"));
+    assert!(checked[1].starts_with("Post-condition of double violated: grows: ret > x"));
+}
+
+#[test]
+#[should_panic(expected = "recorded: Pre-condition of double violated: x > 0")]
+fn custom_macro_reports_violations() {
+    double(0);
+}
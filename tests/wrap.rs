@@ -0,0 +1,69 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use contracts::*;
+
+#[cfg(feature = "mirai_assertions")]
+mod mirai_assertion_mocks;
+
+#[test]
+fn wrapped_functions() {
+    let checked_max = wrap!(
+        std::cmp::max(a: u32, b: u32) -> u32,
+        requires(a < 100),
+        ensures(ret >= a, ret >= b),
+    );
+    assert_eq!(checked_max(5, 7), 7);
+
+    // types are inferred from the call if possible
+    let checked_sqrt = wrap!(
+        f64::sqrt(x),
+        requires(x >= 0.0),
+        ensures(ret * ret - x < 1e-9)
+    );
+    assert_eq!(checked_sqrt(4.0), 2.0);
+
+    let text = String::from("hello world");
+    let checked_find = wrap!(str::find(haystack: &str, needle: &str) -> Option<usize>,
+        ensures(ret.is_some() -> haystack[ret.unwrap()..].starts_with(needle)),
+    );
+    assert_eq!(checked_find(&text, "world"), Some(6));
+
+    let unchecked = wrap!(u32::count_ones(x));
+    assert_eq!(unchecked(7), 3);
+}
+
+#[cfg(not(feature = "disable_contracts"))]
+#[test]
+#[should_panic(expected = "Pre-condition of max violated: a is small")]
+fn wrapped_violation() {
+    let checked_max = wrap!(
+        std::cmp::max(a: u32, b: u32) -> u32,
+        requires(a < 100, "a is small"),
+    );
+
+    checked_max(200, 7);
+}
+
+#[test]
+fn wrapped_closures() {
+    let incr = wrap!(|x: i32| x + 1, requires(x >= 0), ensures(ret > 0));
+    assert_eq!(incr(1), 2);
+
+    let offset = 10;
+    let shift = wrap!(move |x: u32| -> u32 { x + offset }, ensures(ret >= offset));
+    assert_eq!(shift(5), 15);
+
+    let answer = wrap!(|| 42, ensures(ret == 42));
+    assert_eq!(answer(), 42);
+}
+
+#[cfg(not(any(feature = "disable_contracts", feature = "disable_post")))]
+#[test]
+#[should_panic(expected = "Post-condition of closure violated: stays positive")]
+fn wrapped_closure_violation() {
+    let decr = wrap!(|x: i32| x - 1, ensures(ret > 0, "stays positive"));
+
+    decr(1);
+}